@@ -1,7 +1,8 @@
 use criterion::*;
 use crdt_testdata::*;
 
-use jumprope::{JumpRope, JumpRopeBuf};
+use jumprope::{JumpRope, JumpRopeBuf, JumpRopeSized};
+use rand::prelude::*;
 
 fn count_chars(s: &String) -> usize {
     s.chars().count()
@@ -146,5 +147,224 @@ fn realworld_benchmarks(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, realworld_benchmarks);
+fn replay_sized<const N: usize>(test_data: &TestData) -> JumpRopeSized<N> {
+    let mut rope = JumpRopeSized::<N>::new();
+    for txn in test_data.txns.iter() {
+        for TestPatch(pos, del_span, ins_content) in &txn.patches {
+            rope.replace(*pos .. *pos + *del_span, ins_content);
+        }
+    }
+    rope
+}
+
+fn random_edits_sized<const N: usize>(seed: u64) -> JumpRopeSized<N> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut rope = JumpRopeSized::<N>::new();
+    for _ in 0..10000 {
+        let len = rope.len_chars();
+        if len == 0 || (len < 100000 && rng.gen_bool(0.5)) {
+            let pos = rng.gen_range(0..=len);
+            rope.insert(pos, "some text ");
+        } else {
+            let pos = rng.gen_range(0..len);
+            let del_len = rng.gen_range(0..10).min(len - pos);
+            rope.remove(pos..pos + del_len);
+        }
+    }
+    rope
+}
+
+// The node size JumpRope uses, so it can be compared against the other sizes below.
+const DEFAULT_NODE_SIZE: usize = JumpRope::max_bytes_per_node();
+
+// Compare a few different node sizes against each other, and against the default node size.
+fn node_size_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("node_size");
+
+    for name in DATASETS {
+        let test_data = testing_data(name);
+
+        group.bench_function(BenchmarkId::new("64", name), |b| {
+            b.iter(|| black_box(replay_sized::<64>(&test_data).len_chars()))
+        });
+        group.bench_function(BenchmarkId::new("136", name), |b| {
+            b.iter(|| black_box(replay_sized::<136>(&test_data).len_chars()))
+        });
+        group.bench_function(BenchmarkId::new("380", name), |b| {
+            b.iter(|| black_box(replay_sized::<380>(&test_data).len_chars()))
+        });
+        group.bench_function(BenchmarkId::new(format!("default ({})", DEFAULT_NODE_SIZE), name), |b| {
+            b.iter(|| black_box(replay_sized::<DEFAULT_NODE_SIZE>(&test_data).len_chars()))
+        });
+        group.bench_function(BenchmarkId::new("1024", name), |b| {
+            b.iter(|| black_box(replay_sized::<1024>(&test_data).len_chars()))
+        });
+    }
+
+    group.bench_function(BenchmarkId::new("64", "random"), |b| {
+        b.iter(|| black_box(random_edits_sized::<64>(321).len_chars()))
+    });
+    group.bench_function(BenchmarkId::new("136", "random"), |b| {
+        b.iter(|| black_box(random_edits_sized::<136>(321).len_chars()))
+    });
+    group.bench_function(BenchmarkId::new("380", "random"), |b| {
+        b.iter(|| black_box(random_edits_sized::<380>(321).len_chars()))
+    });
+    group.bench_function(BenchmarkId::new(format!("default ({})", DEFAULT_NODE_SIZE), "random"), |b| {
+        b.iter(|| black_box(random_edits_sized::<DEFAULT_NODE_SIZE>(321).len_chars()))
+    });
+    group.bench_function(BenchmarkId::new("1024", "random"), |b| {
+        b.iter(|| black_box(random_edits_sized::<1024>(321).len_chars()))
+    });
+
+    group.finish();
+}
+
+// Loading a large document in one go (eg from a file) goes through the bulk load path.
+fn load_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load");
+
+    for name in DATASETS {
+        let test_data = testing_data(name);
+        // Repeat the final document a few times to make it big enough to be interesting.
+        let content = test_data.end_content.repeat(20);
+        group.throughput(Throughput::Bytes(content.len() as u64));
+
+        group.bench_function(BenchmarkId::new("from_str", name), |b| {
+            b.iter(|| black_box(JumpRope::from(content.as_str()).len_chars()))
+        });
+    }
+
+    group.finish();
+}
+
+// Deleting most of a large document (eg select all + delete) removes whole nodes at a time.
+fn big_delete_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("big_delete");
+    let content = "Some text in a big document ↯ with a few lines\n".repeat(20_000_000 / 50);
+
+    group.bench_function("delete_90_percent", |b| {
+        b.iter_batched(|| JumpRope::from(content.as_str()), |mut rope| {
+            let len = rope.len_chars();
+            rope.remove(len / 20..len - len / 20);
+            black_box(rope.len_chars());
+            rope
+        }, BatchSize::LargeInput)
+    });
+
+    group.finish();
+}
+
+// Random access should stay logarithmic as the rope grows. Each step here is 10x bigger.
+fn big_rope_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("big_rope");
+    let line = "Some text in a big document ↯ with a few lines\n";
+
+    for mb in [2, 20, 200] {
+        let rope = JumpRope::from(line.repeat(mb * 1_000_000 / line.len()));
+        let len = rope.len_chars();
+        let mut rng = SmallRng::seed_from_u64(123);
+
+        group.bench_function(BenchmarkId::new("char_at", format!("{}MB", mb)), |b| {
+            b.iter(|| black_box(rope.char_at(rng.gen_range(0..len))))
+        });
+    }
+
+    group.finish();
+}
+
+fn slice_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("slice");
+    let line = "Some text in a big document ↯ with a few lines\n";
+    let rope = JumpRope::from(line.repeat(20_000_000 / line.len()));
+    let len = rope.len_chars();
+
+    // Read a 3 character window at every position across the first 100k characters.
+    group.throughput(Throughput::Elements(100_000));
+    group.bench_function("slice_cow_windows", |b| {
+        b.iter(|| {
+            for pos in 0..100_000.min(len - 3) {
+                black_box(rope.slice_cow(pos, 3).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+// Empty edits and slices return before looking anything up in the rope, so these should cost
+// about the same as an empty loop, no matter how big the rope is.
+fn zero_length_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("zero_length");
+    let line = "Some text in a big document ↯ with a few lines\n";
+    let mut rope = JumpRope::from(line.repeat(20_000_000 / line.len()));
+    let len = rope.len_chars();
+    group.throughput(Throughput::Elements(100_000));
+
+    group.bench_function("remove", |b| {
+        b.iter(|| {
+            for i in 0..100_000 {
+                let pos = i * 197 % len;
+                rope.remove(black_box(pos..pos));
+            }
+        })
+    });
+    group.bench_function("replace", |b| {
+        b.iter(|| {
+            for i in 0..100_000 {
+                let pos = i * 197 % len;
+                rope.replace(black_box(pos..pos), black_box(""));
+            }
+        })
+    });
+    group.bench_function("slice", |b| {
+        b.iter(|| {
+            for i in 0..100_000 {
+                black_box(rope.slice(i * 197 % len, 0).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+// Reads don't revalidate content as UTF-8, so this should be about as fast as copying the bytes.
+fn to_string_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_string");
+    let line = "Some text in a big document ↯ with a few lines\n";
+    let rope = JumpRope::from(line.repeat(20_000_000 / line.len()));
+    group.throughput(Throughput::Bytes(rope.len_bytes() as _));
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| black_box(rope.to_string()))
+    });
+
+    group.finish();
+}
+
+fn parallel_load_benchmarks(c: &mut Criterion) {
+    #[cfg(feature = "rayon")] {
+        let mut group = c.benchmark_group("parallel_load");
+        let line = "Some text in a big document ↯ with a few lines\n";
+        let content = line.repeat(300_000_000 / line.len());
+        group.throughput(Throughput::Bytes(content.len() as _));
+        group.sample_size(10);
+
+        group.bench_function("sequential", |b| {
+            b.iter(|| black_box(JumpRope::from(content.as_str())))
+        });
+        for threads in [1, 2, 4, 8] {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            group.bench_function(BenchmarkId::new("from_str_parallel", threads), |b| {
+                b.iter(|| pool.install(|| black_box(JumpRope::from_str_parallel(&content))))
+            });
+        }
+
+        group.finish();
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = c;
+}
+
+criterion_group!(benches, realworld_benchmarks, node_size_benchmarks, load_benchmarks, big_delete_benchmarks, big_rope_benchmarks, slice_benchmarks, zero_length_benchmarks, to_string_benchmarks, parallel_load_benchmarks);
 criterion_main!(benches);
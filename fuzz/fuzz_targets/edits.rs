@@ -0,0 +1,159 @@
+//! Differential fuzzing against a `String`. The input is decoded into a script of operations,
+//! which are applied to both a rope and a plain string. After every step the two must agree.
+//!
+//! Run with `cargo fuzz run edits` from the repository root.
+//!
+//! Input format: The first byte picks how node heights are chosen (see [`make_rope`]). The rest
+//! of the input is a list of operations. Each one starts with an opcode byte, followed by its
+//! arguments. Positions are 2 byte little endian numbers, and they wrap around to fit in the
+//! current document. Truncated operations at the end of the input are ignored.
+
+#![no_main]
+
+use jumprope::JumpRope;
+use libfuzzer_sys::fuzz_target;
+use rand::rngs::mock::StepRng;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+/// Characters used for inserted content. This has characters of every UTF-8 length, and line
+/// endings for the line counting code.
+const CHARS: &[char] = &['a', 'b', ' ', '\n', '\r', 'é', '¥', '↯', '€', '😀', '𐍈'];
+
+#[derive(Debug)]
+enum Op {
+    /// Insert content at a char position.
+    Insert(usize, String),
+    /// Delete (position, number of chars).
+    Del(usize, usize),
+    /// Read back (position, number of chars) with slice.
+    Slice(usize, usize),
+    /// Read back the whole document through the iterators.
+    Iterate,
+}
+
+/// Decodes operations from raw fuzzer input.
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (&b, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(b)
+    }
+
+    fn u16(&mut self) -> Option<usize> {
+        Some(u16::from_le_bytes([self.byte()?, self.byte()?]) as usize)
+    }
+
+    /// Decodes a char position in a document with len_chars characters.
+    fn pos(&mut self, len_chars: usize) -> Option<usize> {
+        Some(self.u16()? % (len_chars + 1))
+    }
+
+    /// Inserted content is described by 3 bytes: a length, then the first character and the
+    /// step between the characters which follow it. Lengths with the high bit set are multiplied
+    /// by 16, so a short input can still insert enough content to fill lots of nodes.
+    fn content(&mut self) -> Option<String> {
+        let len = self.byte()? as usize;
+        let len = if len & 0x80 != 0 { (len & 0x7f) * 16 } else { len };
+        let start = self.byte()? as usize;
+        let step = self.byte()? as usize;
+        Some((0..len).map(|i| CHARS[(start + i * step) % CHARS.len()]).collect())
+    }
+
+    fn next_op(&mut self, len_chars: usize) -> Option<Op> {
+        Some(match self.byte()? % 4 {
+            0 => Op::Insert(self.pos(len_chars)?, self.content()?),
+            1 => Op::Del(self.pos(len_chars)?, self.u16()?),
+            2 => Op::Slice(self.pos(len_chars)?, self.u16()?),
+            _ => Op::Iterate,
+        })
+    }
+}
+
+/// The reference model. Positions are in chars, like the rope's positions.
+struct Model {
+    s: String,
+}
+
+impl Model {
+    fn len_chars(&self) -> usize {
+        self.s.chars().count()
+    }
+
+    fn byte_pos(&self, char_pos: usize) -> usize {
+        self.s.char_indices().nth(char_pos).map_or(self.s.len(), |(i, _)| i)
+    }
+
+    fn insert(&mut self, pos: usize, content: &str) {
+        let pos = self.byte_pos(pos);
+        self.s.insert_str(pos, content);
+    }
+
+    /// Deletes are truncated at the end of the document, like JumpRope::remove.
+    fn del(&mut self, pos: usize, len: usize) {
+        let start = self.byte_pos(pos);
+        let end = self.byte_pos(pos.saturating_add(len));
+        self.s.replace_range(start..end, "");
+    }
+
+    /// Slices are truncated at the end of the document, like JumpRope::slice.
+    fn slice(&self, pos: usize, len: usize) -> &str {
+        &self.s[self.byte_pos(pos)..self.byte_pos(pos.saturating_add(len))]
+    }
+}
+
+/// Picks the RNG for node heights. Mode 0 makes every node as tall as possible, and mode 1 makes
+/// every node 1 high. Anything else seeds a normal RNG, to get the usual mix of heights.
+fn make_rope(mode: u8) -> JumpRope {
+    match mode {
+        0 => JumpRope::with_rng(StepRng::new(0, 0)),
+        1 => JumpRope::with_rng(StepRng::new(u64::MAX, 0)),
+        seed => JumpRope::with_rng(SmallRng::seed_from_u64(seed as u64)),
+    }
+}
+
+fn check_matches(rope: &JumpRope, model: &Model) {
+    rope.check();
+    assert_eq!(rope.to_string(), model.s);
+    assert_eq!(rope.len_bytes(), model.s.len());
+    assert_eq!(rope.len_chars(), model.len_chars());
+}
+
+fn run(data: &[u8]) {
+    let mut decoder = Decoder { data };
+    let Some(mode) = decoder.byte() else { return; };
+    let mut rope = make_rope(mode);
+    let mut model = Model { s: String::new() };
+
+    while let Some(op) = decoder.next_op(model.len_chars()) {
+        match op {
+            Op::Insert(pos, content) => {
+                rope.insert(pos, &content);
+                model.insert(pos, &content);
+            }
+            Op::Del(pos, len) => {
+                rope.remove(pos..pos.saturating_add(len));
+                model.del(pos, len);
+            }
+            Op::Slice(pos, len) => {
+                assert_eq!(rope.slice(pos, len).unwrap(), model.slice(pos, len));
+                let end = (pos + len).min(model.len_chars());
+                let substrings: String = rope.slice_substrings(pos..end).collect();
+                assert_eq!(substrings, model.slice(pos, len));
+            }
+            Op::Iterate => {
+                let substrings: String = rope.substrings().collect();
+                assert_eq!(substrings, model.s);
+                assert!(rope.chars().eq(model.s.chars()));
+                assert!(rope.chars().rev().eq(model.s.chars().rev()));
+            }
+        }
+        check_matches(&rope, &model);
+    }
+}
+
+fuzz_target!(|data: &[u8]| run(data));
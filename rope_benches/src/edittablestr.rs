@@ -1,4 +1,5 @@
-use super::Rope;
+use super::rope::{Rope, out_of_bounds};
+use jumprope::RopeError;
 use std::ptr;
 
 // pub trait EditableText {
@@ -63,6 +64,10 @@ impl Rope for String {
     // fn len(&self) -> usize { self.len() }
     fn char_len(&self) -> usize { self.chars().count() }
     fn to_string(&self) -> String { self.clone() }
+
+    fn char_at(&self, pos: usize) -> Result<char, RopeError> {
+        self.chars().nth(pos).ok_or_else(|| out_of_bounds(pos, self.chars().count()))
+    }
 }
 
 
@@ -14,6 +14,8 @@ use self::rope::*;
 use jumprope::*;
 
 mod edittablestr;
+mod trace;
+use self::trace::synthetic_trace;
 
 use std::cmp::min;
 
@@ -55,6 +57,15 @@ impl Rope for JumpRope {
 
     #[inline(always)]
     fn char_len(&self) -> usize { self.len_chars() } // in unicode values
+
+    #[inline(always)]
+    fn char_at(&self, pos: usize) -> Result<char, RopeError> {
+        JumpRope::char_at(self, pos).ok_or_else(|| out_of_bounds(pos, self.len_chars()))
+    }
+    #[inline(always)]
+    fn is_empty(&self) -> bool { JumpRope::is_empty(self) }
+
+    fn mem_usage(&self) -> Option<usize> { Some(self.mem_size()) }
 }
 
 impl Rope for JumpRopeBuf {
@@ -83,6 +94,15 @@ impl Rope for JumpRopeBuf {
 
     #[inline(always)]
     fn char_len(&self) -> usize { self.len_chars() } // in unicode values
+
+    #[inline(always)]
+    fn char_at(&self, pos: usize) -> Result<char, RopeError> {
+        self.borrow().char_at(pos).ok_or_else(|| out_of_bounds(pos, self.len_chars()))
+    }
+    #[inline(always)]
+    fn is_empty(&self) -> bool { JumpRopeBuf::is_empty(self) }
+
+    fn mem_usage(&self) -> Option<usize> { Some(self.borrow().mem_size()) }
 }
 
 impl Rope for AnRope {
@@ -135,6 +155,18 @@ impl Rope for XiRope {
         }
         len
     } // in unicode values
+
+    fn char_at(&self, pos: usize) -> Result<char, RopeError> {
+        let mut remaining = pos;
+        for s in self.iter_chunks(..) {
+            let len = s.chars().count();
+            if remaining < len {
+                return Ok(s.chars().nth(remaining).unwrap());
+            }
+            remaining -= len;
+        }
+        Err(out_of_bounds(pos, pos - remaining))
+    }
 }
 
 impl Rope for RopeyRope {
@@ -156,10 +188,15 @@ impl Rope for RopeyRope {
     // fn slice(&self, pos: usize, len: usize) -> Result<String, RopeError>;
 
     #[inline(always)]
-    fn to_string(&self) -> String { unimplemented!() }
+    fn to_string(&self) -> String { String::from(self) }
 
     #[inline(always)]
     fn char_len(&self) -> usize { self.len_chars() } // in unicode values
+
+    #[inline(always)]
+    fn char_at(&self, pos: usize) -> Result<char, RopeError> {
+        self.get_char(pos).ok_or_else(|| out_of_bounds(pos, self.len_chars()))
+    }
 }
 
 impl Rope for CropRope {
@@ -185,6 +222,19 @@ impl Rope for CropRope {
     fn char_len(&self) -> usize {
         self.byte_len()
     }
+
+    // Like the edits, this takes a byte offset.
+    fn char_at(&self, pos: usize) -> Result<char, RopeError> {
+        let mut remaining = pos;
+        for s in self.chunks() {
+            if remaining < s.len() {
+                return s.get(remaining..).and_then(|s| s.chars().next())
+                    .ok_or(RopeError::InvalidCodepoint { pos });
+            }
+            remaining -= s.len();
+        }
+        Err(out_of_bounds(pos, pos - remaining))
+    }
 }
 
 use std::os::raw::c_char;
@@ -200,7 +250,8 @@ extern {
     fn rope_new_with_utf8(s: *const c_char) -> *mut CRopeRaw;
     fn rope_free(r: *mut CRopeRaw);
     fn rope_char_count(r: *const CRopeRaw) -> usize;
-    // fn rope_byte_count(r: *const CRopeRaw) -> usize;
+    fn rope_byte_count(r: *const CRopeRaw) -> usize;
+    fn rope_write_cstr(r: *mut CRopeRaw, dest: *mut u8) -> usize;
 
     fn rope_insert(r: *mut CRopeRaw, pos: usize, s: *const c_char) -> u32;
     fn rope_del(r: *mut CRopeRaw, pos: usize, len: usize) -> u32;
@@ -224,7 +275,15 @@ impl Rope for CRope {
     fn del_at(&mut self, pos: usize, len: usize) {
         unsafe { rope_del(self.0, pos, len); }
     }
-    fn to_string(&self) -> String { unimplemented!() }
+    fn to_string(&self) -> String {
+        unsafe {
+            // rope_write_cstr writes a null terminator after the content.
+            let mut bytes = vec![0; rope_byte_count(self.0) + 1];
+            rope_write_cstr(self.0, bytes.as_mut_ptr());
+            bytes.pop();
+            String::from_utf8(bytes).unwrap()
+        }
+    }
 
     #[inline(always)]
     fn char_len(&self) -> usize { unsafe { rope_char_count(self.0) } } // in unicode values
@@ -249,6 +308,64 @@ fn foo() {
     }
 }
 
+fn exercise<R: Rope>() {
+    let mut r = R::new();
+    assert!(r.is_empty());
+    assert_eq!(r.char_at(0), Err(RopeError::PositionOutOfBounds { pos: 0, len: 0 }));
+
+    r.insert_at(0, "hi 𝄞 there");
+    assert!(!r.is_empty());
+    assert_eq!(r.char_at(0), Ok('h'));
+    assert_eq!(r.char_at(3), Ok('𝄞'));
+    assert_eq!(r.char_at(9), Ok('e'));
+    assert_eq!(r.char_at(10), Err(RopeError::PositionOutOfBounds { pos: 10, len: 10 }));
+
+    r.del_at(0, 10);
+    assert!(r.is_empty());
+}
+
+#[test]
+fn trait_char_at() {
+    exercise::<JumpRope>();
+    exercise::<JumpRopeBuf>();
+    exercise::<RopeyRope>();
+    exercise::<AnRope>();
+    exercise::<CRope>();
+    exercise::<String>();
+}
+
+/// Makes the same random edits to a rope of type R, returning the rope's content after every
+/// edit.
+fn random_edit_snapshots<R: Rope>(seed: u64) -> Vec<String> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut r = R::new();
+    let mut snapshots = Vec::new();
+    for _ in 0..1000 {
+        let len = r.char_len();
+        if len == 0 || rng.gen_bool(0.6) {
+            let pos = rng.gen_range(0..=len);
+            let ins_len = rng.gen_range(1..10);
+            r.insert_at(pos, &random_ascii_string(&mut rng, ins_len));
+        } else {
+            let pos = rng.gen_range(0..len);
+            let del_len = rng.gen_range(1..10usize).min(len - pos);
+            r.del_at(pos, del_len);
+        }
+        snapshots.push(r.to_string());
+    }
+    snapshots
+}
+
+#[test]
+fn differential_random_edits() {
+    for seed in 0..10 {
+        let expected = random_edit_snapshots::<String>(seed);
+        assert_eq!(random_edit_snapshots::<JumpRope>(seed), expected);
+        assert_eq!(random_edit_snapshots::<JumpRopeBuf>(seed), expected);
+        assert_eq!(random_edit_snapshots::<RopeyRope>(seed), expected);
+    }
+}
+
 fn gen_strings(rng: &mut SmallRng) -> Vec<String> {
     // I wish there was a better syntax for just making an array here.
     let mut strings = Vec::<String>::new();
@@ -374,12 +491,17 @@ fn bench_stable_ins_del(c: &mut Criterion) {
         // group.bench_with_input(BenchmarkId::new("anrope", size), size, stable_ins_del::<AnRope>);
         group.bench_with_input(BenchmarkId::new("xirope", size), size, stable_ins_del::<XiRope>);
         group.bench_with_input(BenchmarkId::new("jumprope_c", size), size, stable_ins_del::<CRope>);
+        // Every edit to a flat string is O(n), so this gets very slow for big documents. But its a
+        // useful baseline for smaller documents.
+        if *size <= 100000 {
+            group.bench_with_input(BenchmarkId::new("raw_string", size), size, stable_ins_del::<String>);
+        }
     }
     group.finish();
 }
 
 fn load_named_data(name: &str) -> TestData {
-    let filename = format!("/home/seph/src/diamond-types/benchmark_data/{}.json.gz", name);
+    let filename = format!("{}/../benchmark_data/{}.json.gz", env!("CARGO_MANIFEST_DIR"), name);
     load_testing_data(&filename)
 }
 
@@ -439,11 +561,101 @@ fn realworld(c: &mut Criterion) {
     }
 }
 
+fn replay<R: Rope>(r: &mut R, test_data: &TestData) {
+    for txn in &test_data.txns {
+        for TestPatch(pos, del, ins) in &txn.patches {
+            r.edit_at(*pos, *del, ins);
+        }
+    }
+}
+
+fn print_mem_usage<R: Rope>(name: &str, test_data: &TestData) {
+    let mut r = R::from(test_data.start_content.clone());
+    replay(&mut r, test_data);
+    if let Some(mem) = r.mem_usage() {
+        println!("{}/{}: {:.2} bytes of memory per char", name, R::NAME,
+            mem as f64 / r.char_len().max(1) as f64);
+    }
+}
+
+fn bench_phase<R: Rope>(group: &mut BenchmarkGroup<WallTime>, name: &str, test_data: &TestData) {
+    print_mem_usage::<R>(name, test_data);
+
+    group.bench_function(BenchmarkId::new(R::NAME, name), |b| {
+        b.iter_batched(|| R::from(test_data.start_content.clone()), |mut r| {
+            replay(&mut r, test_data);
+            assert_eq!(r.char_len(), test_data.end_content.chars().count());
+            r
+        }, BatchSize::LargeInput)
+    });
+}
+
+fn bench_read<R: Rope>(group: &mut BenchmarkGroup<WallTime>, name: &str, content: &str) {
+    let r = R::from(content.to_string());
+    let len = r.char_len();
+
+    group.bench_function(BenchmarkId::new(format!("{}/to_string", R::NAME), name), |b| {
+        b.iter(|| black_box(r.to_string()))
+    });
+
+    let mut rng = SmallRng::seed_from_u64(123);
+    let positions: Vec<usize> = (0..len.min(1000)).map(|_| rng.gen_range(0..len)).collect();
+    group.bench_function(BenchmarkId::new(format!("{}/char_at", R::NAME), name), |b| {
+        b.iter(|| {
+            for pos in &positions {
+                black_box(r.char_at(*pos).unwrap());
+            }
+        })
+    });
+}
+
+/// Edit throughput for different phases of editing: building a document from nothing, tearing
+/// most of one down, and a balanced mix of edits. Each phase is a synthetic trace, alongside the
+/// recorded (sequential typing) automerge-paper trace. Throughput is reported in characters
+/// inserted + deleted per second.
+fn trace_phases(c: &mut Criterion) {
+    let traces = [
+        ("automerge-paper", load_named_data("automerge-paper")),
+        ("insert_only", synthetic_trace(1, 0, 200_000, 1.0)),
+        ("delete_heavy", synthetic_trace(2, 1_000_000, 200_000, 0.2)),
+        ("mixed", synthetic_trace(3, 100_000, 200_000, 0.5)),
+    ];
+
+    let mut group = c.benchmark_group("trace_phases");
+    for (name, test_data) in &traces {
+        let num_chars = test_data.patches()
+            .map(|TestPatch(_pos, del, ins)| del + ins.chars().count())
+            .sum::<usize>();
+        group.throughput(Throughput::Elements(num_chars as u64));
+
+        bench_phase::<JumpRope>(&mut group, name, test_data);
+        bench_phase::<JumpRopeBuf>(&mut group, name, test_data);
+        bench_phase::<RopeyRope>(&mut group, name, test_data);
+        bench_phase::<CRope>(&mut group, name, test_data);
+        // Too slow to run with the bigger documents.
+        if test_data.end_content.len() <= 200_000 && test_data.start_content.len() <= 200_000 {
+            bench_phase::<String>(&mut group, name, test_data);
+        }
+    }
+    group.finish();
+
+    // Reading back the final document from each trace.
+    let mut group = c.benchmark_group("trace_read");
+    for (name, test_data) in &traces {
+        group.throughput(Throughput::Elements(test_data.end_content.chars().count() as u64));
+        bench_read::<JumpRope>(&mut group, name, &test_data.end_content);
+        bench_read::<JumpRopeBuf>(&mut group, name, &test_data.end_content);
+        bench_read::<RopeyRope>(&mut group, name, &test_data.end_content);
+    }
+    group.finish();
+}
+
 criterion_group!(benches,
     bench_ins_append,
     bench_ins_random,
     bench_stable_ins_del,
-    realworld
+    realworld,
+    trace_phases
 );
 // criterion_group!(benches, bench_all);
 criterion_main!(benches);
\ No newline at end of file
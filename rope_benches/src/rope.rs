@@ -1,9 +1,5 @@
 // use std::ops::RangeBounds;
-
-// #[derive(Debug)]
-// pub enum RopeError {
-//     PositionOutOfBounds,
-// }
+use jumprope::RopeError;
 
 pub trait Rope: From<String> {
     const NAME: &'static str;
@@ -30,4 +26,20 @@ pub trait Rope: From<String> {
     
     // fn len(&self) -> usize; // in bytes
     fn char_len(&self) -> usize; // in unicode values
+
+    // The default implementation copies the whole rope, so implementations with their own char
+    // lookup should override it.
+    fn char_at(&self, pos: usize) -> Result<char, RopeError> {
+        let s = self.to_string();
+        s.chars().nth(pos).ok_or_else(|| out_of_bounds(pos, s.chars().count()))
+    }
+    fn is_empty(&self) -> bool { self.char_len() == 0 }
+
+    // Total memory used by the rope in bytes, if the implementation can report it.
+    fn mem_usage(&self) -> Option<usize> { None }
+}
+
+/// Out of bounds error for a char_at call on a rope with len characters.
+pub fn out_of_bounds(pos: usize, len: usize) -> RopeError {
+    RopeError::PositionOutOfBounds { pos, len }
 }
\ No newline at end of file
@@ -0,0 +1,40 @@
+use crdt_testdata::{TestData, TestPatch, TestTxn};
+use jumprope::JumpRope;
+use rand::prelude::*;
+
+use super::random_ascii_string;
+
+/// Generates a random editing trace in the same format as the recorded traces. The document
+/// starts with `start_len` random characters, then `num_ops` edits are made at random positions.
+/// Each edit inserts 1-9 characters with probability `insert_ratio`, and otherwise deletes 1-9
+/// characters.
+pub fn synthetic_trace(seed: u64, start_len: usize, num_ops: usize, insert_ratio: f64) -> TestData {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let start_content = random_ascii_string(&mut rng, start_len);
+
+    // JumpRope is used to track the document's length (and the end content) as we go.
+    let mut doc = JumpRope::from(start_content.as_str());
+    let mut patches = Vec::with_capacity(num_ops);
+    for _ in 0..num_ops {
+        let len = doc.len_chars();
+        if len == 0 || rng.gen_bool(insert_ratio) {
+            let pos = rng.gen_range(0..=len);
+            let ins_len = rng.gen_range(1..10);
+            let content = random_ascii_string(&mut rng, ins_len);
+            doc.insert(pos, &content);
+            patches.push(TestPatch(pos, 0, content));
+        } else {
+            let pos = rng.gen_range(0..len);
+            let del_len = rng.gen_range(1..10usize).min(len - pos);
+            doc.remove(pos..pos + del_len);
+            patches.push(TestPatch(pos, del_len, String::new()));
+        }
+    }
+
+    TestData {
+        using_byte_positions: false,
+        start_content,
+        end_content: doc.to_string(),
+        txns: vec![TestTxn { patches }],
+    }
+}
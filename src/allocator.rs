@@ -0,0 +1,54 @@
+//! The allocator trait used for rope nodes.
+//!
+//! With the `allocator-api2` feature enabled, ropes can store their nodes in any
+//! [`allocator_api2::alloc::Allocator`]. (Enable allocator-api2's `nightly` feature to use the
+//! standard library's unstable allocator API instead.) Without it, nodes always come from the
+//! global allocator, via a private stand-in for the trait below.
+
+#[cfg(feature = "allocator-api2")]
+pub use allocator_api2::alloc::{Allocator, Global};
+
+#[cfg(not(feature = "allocator-api2"))]
+pub use fallback::{Allocator, Global};
+
+#[cfg(not(feature = "allocator-api2"))]
+mod fallback {
+    use std::alloc::Layout;
+    use std::ptr::NonNull;
+
+    #[derive(Debug)]
+    pub struct AllocError;
+
+    /// A subset of `allocator_api2::alloc::Allocator`. This isn't exported, so [`Global`] is the
+    /// only implementation.
+    ///
+    /// # Safety
+    ///
+    /// Same as `Allocator::allocate` and `Allocator::deallocate`.
+    pub unsafe trait Allocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+        /// # Safety
+        ///
+        /// ptr must have been returned by allocate with the same layout.
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+    }
+
+    /// The global memory allocator.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct Global;
+
+    unsafe impl Allocator for Global {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            // Node layouts are never zero sized.
+            let ptr = unsafe { std::alloc::alloc(layout) };
+            NonNull::new(ptr)
+                .map(|ptr| NonNull::slice_from_raw_parts(ptr, layout.size()))
+                .ok_or(AllocError)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            std::alloc::dealloc(ptr.as_ptr(), layout)
+        }
+    }
+}
@@ -224,6 +224,7 @@ impl JumpRopeBuf {
     /// the number of bytes the characters take, or the number of grapheme clusters in the string.
     ///
     /// This method returns the length in constant-time (*O(1)*).
+    #[doc(alias = "char_len")]
     pub fn len_chars(&self) -> usize {
         let borrow = self.0.borrow();
         match borrow.1.kind {
@@ -234,6 +235,7 @@ impl JumpRopeBuf {
 
     /// Get the number of bytes used for the UTF8 representation of the rope. This will always match
     /// the .len() property of the equivalent String.
+    #[doc(alias = "byte_len")]
     pub fn len_bytes(&self) -> usize {
         let mut borrow = self.0.borrow_mut();
         match borrow.1.kind {
@@ -0,0 +1,82 @@
+//! Change tracking, for keeping things like syntax highlighting and rendered lines in sync with a
+//! rope. These are only available with the `changes` feature.
+//!
+//! Changes are queued rather than passed to a callback, so the rope can be read from while
+//! handling them.
+
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+/// A single change made to a rope. The characters (and bytes) at `char_pos` / `byte_pos` were
+/// removed, then new content was inserted in their place.
+///
+/// Positions are from the start of the rope just before the change was made. So replaying a
+/// list of changes in order against the original content reproduces the final content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeEvent {
+    /// The position of the change, in unicode characters.
+    pub char_pos: usize,
+    /// The number of unicode characters which were removed.
+    pub chars_removed: usize,
+    /// The number of unicode characters which were inserted.
+    pub chars_inserted: usize,
+    /// The position of the change, in bytes.
+    pub byte_pos: usize,
+    /// The number of bytes which were removed.
+    pub bytes_removed: usize,
+    /// The number of bytes which were inserted.
+    pub bytes_inserted: usize,
+}
+
+/// These methods are only available if the `changes` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "changes")))]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Start recording a [`ChangeEvent`] for every edit made to the rope. Collect them with
+    /// [`take_changes`](Self::take_changes). Calling this again does nothing.
+    ///
+    /// Every insert and delete makes one event, including deletes and inserts made by
+    /// [`replace`](Self::replace) and [`apply_edits`](Self::apply_edits).
+    /// [`append`](Self::append), [`split_at_char`](Self::split_at_char),
+    /// [`set_content`](Self::set_content) and [`clear`](Self::clear) make one event each.
+    ///
+    /// With this feature enabled, the rope counts bytes between nodes (like characters), so the
+    /// byte position of each edit is found in *O(log n)* time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// rope.track_changes();
+    /// rope.insert(2, " ↯");
+    /// rope.remove(0..1);
+    /// assert_eq!(rope.take_changes(), [
+    ///     ChangeEvent { char_pos: 2, chars_removed: 0, chars_inserted: 2, byte_pos: 2, bytes_removed: 0, bytes_inserted: 4 },
+    ///     ChangeEvent { char_pos: 0, chars_removed: 1, chars_inserted: 0, byte_pos: 0, bytes_removed: 1, bytes_inserted: 0 },
+    /// ]);
+    /// assert!(rope.take_changes().is_empty());
+    /// ```
+    pub fn track_changes(&mut self) {
+        if self.changes.is_none() {
+            self.changes = Some(Vec::new());
+        }
+    }
+
+    /// Stop recording changes. Any changes which haven't been taken are discarded.
+    pub fn stop_tracking_changes(&mut self) {
+        self.changes = None;
+    }
+
+    /// Returns every change made since the last call, oldest first. Returns an empty list if
+    /// changes aren't being tracked.
+    pub fn take_changes(&mut self) -> Vec<ChangeEvent> {
+        self.changes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Record a change made without going through a cursor.
+    pub(crate) fn record_change(&mut self, event: ChangeEvent) {
+        if let Some(changes) = self.changes.as_mut() {
+            changes.push(event);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! A range of characters in a rope, like an editor's selection, which can be moved through edits.
+
+use std::ops::Range;
+use crate::{Edit, JumpRopeSized};
+use crate::allocator::Allocator;
+
+/// A range of unicode characters in a rope. The start is never after the end.
+///
+/// Use [`transform_after_edit`](CharRange::transform_after_edit) to keep a range (like a
+/// selection) pointing at the same text while the rope is edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CharRange {
+    start: usize,
+    end: usize,
+}
+
+/// The shape of an edit: `removed` characters at `pos` were replaced with `inserted` characters.
+/// This is what [`CharRange::transform_after_edit`] needs to know about an edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EditSpan {
+    /// The position of the edit, in unicode characters.
+    pub pos: usize,
+    /// The number of characters removed.
+    pub removed: usize,
+    /// The number of characters inserted.
+    pub inserted: usize,
+}
+
+impl EditSpan {
+    /// An insert of `len` characters at `pos`.
+    pub fn insert(pos: usize, len: usize) -> Self {
+        EditSpan { pos, removed: 0, inserted: len }
+    }
+
+    /// A delete of the characters in `range`.
+    pub fn delete(range: Range<usize>) -> Self {
+        EditSpan { pos: range.start, removed: range.len(), inserted: 0 }
+    }
+}
+
+impl From<&Edit> for EditSpan {
+    fn from(edit: &Edit) -> Self {
+        EditSpan {
+            pos: edit.range.start,
+            removed: edit.range.len(),
+            inserted: edit.content.chars().count(),
+        }
+    }
+}
+
+impl CharRange {
+    /// Create a range between two positions, in either order.
+    pub fn new(a: usize, b: usize) -> Self {
+        CharRange { start: a.min(b), end: a.max(b) }
+    }
+
+    /// The start of the range.
+    pub fn start(&self) -> usize { self.start }
+
+    /// The end of the range (exclusive).
+    pub fn end(&self) -> usize { self.end }
+
+    /// The number of characters in the range.
+    pub fn len(&self) -> usize { self.end - self.start }
+
+    /// Returns true if the range is empty (like a cursor with nothing selected).
+    pub fn is_empty(&self) -> bool { self.start == self.end }
+
+    /// The range as a `Range<usize>`.
+    pub fn to_range(&self) -> Range<usize> { self.start..self.end }
+
+    /// Move this range through an edit made to the rope, so it covers the same text afterwards.
+    /// Returns `None` if the edit deleted every character in the range.
+    ///
+    /// - Edits entirely before the range shift it along. This includes inserts at the start of
+    ///   the range.
+    /// - Edits after the range (including inserts at its end) don't change it.
+    /// - Edits inside the range grow or shrink it.
+    /// - If the edit overlaps the start of the range, the range starts after the inserted text. If
+    ///   it overlaps the end, the range ends where the edit starts.
+    ///
+    /// Empty ranges are never deleted. An empty range inside a deleted span moves to the start of
+    /// the edit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hello world");
+    /// let selection = CharRange::new(6, 11); // "world"
+    ///
+    /// rope.insert(0, "oh, ");
+    /// let selection = selection.transform_after_edit(&EditSpan::insert(0, 4)).unwrap();
+    /// assert_eq!(rope.slice_range(&selection), "world");
+    ///
+    /// rope.remove(8..12);
+    /// let selection = selection.transform_after_edit(&EditSpan::delete(8..12)).unwrap();
+    /// assert_eq!(rope.slice_range(&selection), "rld");
+    ///
+    /// assert_eq!(selection.transform_after_edit(&EditSpan::delete(0..11)), None);
+    /// ```
+    pub fn transform_after_edit(&self, edit: &EditSpan) -> Option<CharRange> {
+        let edit_end = edit.pos + edit.removed;
+        let shift = |pos: usize| pos - edit.removed + edit.inserted;
+
+        if self.is_empty() {
+            let pos = self.start;
+            return Some(if edit_end <= pos {
+                CharRange::new(shift(pos), shift(pos))
+            } else if edit.pos >= pos {
+                *self
+            } else {
+                CharRange::new(edit.pos, edit.pos)
+            });
+        }
+
+        Some(if edit_end <= self.start {
+            // Before the range.
+            CharRange::new(shift(self.start), shift(self.end))
+        } else if edit.pos >= self.end {
+            // After the range.
+            *self
+        } else if edit.pos <= self.start && edit_end >= self.end {
+            // The whole range was deleted.
+            return None;
+        } else if edit.pos >= self.start && edit_end <= self.end {
+            // Inside the range.
+            CharRange::new(self.start, shift(self.end))
+        } else if edit.pos < self.start {
+            // Overlapping the start.
+            CharRange::new(edit.pos + edit.inserted, shift(self.end))
+        } else {
+            // Overlapping the end.
+            CharRange::new(self.start, edit.pos)
+        })
+    }
+}
+
+impl From<Range<usize>> for CharRange {
+    /// Convert a range to a `CharRange`. Backwards ranges are flipped around.
+    fn from(range: Range<usize>) -> Self {
+        CharRange::new(range.start, range.end)
+    }
+}
+
+impl From<CharRange> for Range<usize> {
+    fn from(range: CharRange) -> Self {
+        range.to_range()
+    }
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Returns a copy of the characters in the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the rope.
+    pub fn slice_range(&self, range: &CharRange) -> String {
+        self.chars_in_range(range.to_range()).collect()
+    }
+
+    /// Delete the characters in the range. This is the same as [`remove`](Self::remove), so
+    /// anything past the end of the rope is ignored.
+    pub fn delete_range(&mut self, range: &CharRange) {
+        self.remove(range.to_range());
+    }
+
+    /// Replace the characters in the range with `content`. This is the same as
+    /// [`replace`](Self::replace).
+    pub fn replace_range(&mut self, range: &CharRange, content: &str) {
+        self.replace(range.to_range(), content);
+    }
+}
@@ -0,0 +1,150 @@
+//! Incremental content hashing, for cheap "has this changed?" checks. These are only available
+//! with the `content_hash` feature.
+//!
+//! The hash is a polynomial hash over the rope's bytes, modulo the Mersenne prime 2^61 - 1. The
+//! hash of a concatenation can be computed from the hashes of its parts:
+//! `hash(a + b) = hash(a) * BASE^len(b) + hash(b)`. So each node caches the hash of its own
+//! content, and the rope's hash is combined from the node hashes. This doesn't depend on where
+//! the node boundaries are.
+
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+const MODULUS: u64 = (1 << 61) - 1;
+const BASE: u64 = 0x1f35_8a6d_c4b2_9e07 % MODULUS;
+
+/// Stored in HashCache when there's no cached hash. Real hashes are always less than MODULUS.
+const INVALID: u64 = u64::MAX;
+
+fn mul_mod(a: u64, b: u64) -> u64 {
+    let r = a as u128 * b as u128;
+    // 2^61 = 1 (mod MODULUS), so the high bits wrap around onto the low bits.
+    let s = (r as u64 & MODULUS) + (r >> 61) as u64;
+    if s >= MODULUS { s - MODULUS } else { s }
+}
+
+fn add_mod(a: u64, b: u64) -> u64 {
+    let s = a + b;
+    if s >= MODULUS { s - MODULUS } else { s }
+}
+
+fn pow_mod(mut base: u64, mut exp: usize) -> u64 {
+    let mut result = 1;
+    while exp > 0 {
+        if exp & 1 == 1 { result = mul_mod(result, base); }
+        base = mul_mod(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Extend the hash h with more bytes. Each byte is offset by 1, so leading zero bytes still
+/// change the hash.
+fn hash_bytes(mut h: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        h = add_mod(mul_mod(h, BASE), *b as u64 + 1);
+    }
+    h
+}
+
+/// The cached hash of a node's content. This is cleared whenever the node's content changes.
+///
+/// The rope's hash can be computed through a shared reference, so the cache is atomic to keep
+/// ropes Sync.
+pub(crate) struct HashCache(AtomicU64);
+
+impl HashCache {
+    pub(crate) fn new() -> Self {
+        Self(AtomicU64::new(INVALID))
+    }
+
+    fn get(&self) -> Option<u64> {
+        let h = self.0.load(Ordering::Relaxed);
+        (h != INVALID).then_some(h)
+    }
+
+    fn set(&self, h: u64) {
+        self.0.store(h, Ordering::Relaxed);
+    }
+
+    pub(crate) fn invalidate(&mut self) {
+        *self.0.get_mut() = INVALID;
+    }
+}
+
+impl Clone for HashCache {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+impl Debug for HashCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("HashCache").field(&self.get()).finish()
+    }
+}
+
+// The cache doesn't change what a node contains.
+impl PartialEq for HashCache {
+    fn eq(&self, _other: &Self) -> bool { true }
+}
+impl Eq for HashCache {}
+
+/// These methods are only available if the `content_hash` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "content_hash")))]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Start caching the hash of each node's content, so [`content_hash`](Self::content_hash)
+    /// only needs to rehash nodes which changed since it was last called. This makes it
+    /// proportional to the number of nodes rather than the number of bytes in the rope.
+    pub fn enable_content_hash(&mut self) {
+        self.cache_hashes = true;
+    }
+
+    /// Returns a 61 bit hash of the rope's content. Ropes with the same content always have the
+    /// same hash, no matter how their content is split into nodes (or what size their nodes are),
+    /// so this can be used to check whether a document has changed since it was saved. The hash
+    /// of a given string doesn't change between runs.
+    ///
+    /// This isn't a cryptographic hash. Two different documents of up to `n` bytes collide with a
+    /// probability of about `n / 2^61`, but the hash uses a fixed base, so collisions can be
+    /// constructed deliberately. Don't rely on it for content from an adversary.
+    ///
+    /// Without [`enable_content_hash`](Self::enable_content_hash), this hashes every byte in the
+    /// rope each time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// rope.enable_content_hash();
+    /// let saved = rope.content_hash();
+    ///
+    /// rope.insert(2, "!");
+    /// assert_ne!(rope.content_hash(), saved);
+    /// rope.remove(2..3);
+    /// assert_eq!(rope.content_hash(), saved);
+    /// assert_eq!(JumpRope::from("hi there").content_hash(), saved);
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        let mut h = 0;
+        for node in self.node_iter_at_start() {
+            let len = node.str.len_bytes();
+            if len == 0 { continue; }
+
+            let node_hash = match node.str.hash.get() {
+                Some(node_hash) if self.cache_hashes => node_hash,
+                _ => {
+                    let node_hash = hash_bytes(hash_bytes(0, node.str.start_as_str().as_bytes()),
+                        node.str.end_as_str().as_bytes());
+                    if self.cache_hashes { node.str.hash.set(node_hash); }
+                    node_hash
+                }
+            };
+            h = add_mod(mul_mod(h, pow_mod(BASE, len)), node_hash);
+        }
+        h
+    }
+}
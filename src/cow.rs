@@ -0,0 +1,158 @@
+//! Forked ropes, which share their nodes until one of them is edited. These are only available
+//! with the `cow` feature.
+//!
+//! Ropes count how many of them use the same nodes, and nodes are only modified or freed by the
+//! last rope using them. A rope which edits shared nodes first copies all of them (node by node,
+//! like [`Clone`]). Copying less than that doesn't work for a skip list: every node is pointed to
+//! by the nodes before it at each of its levels, and their skip counts change with every edit. So
+//! a modified copy of one node needs modified copies of every node before it too.
+//!
+//! Forks are still useful, because the copy is only made by a rope which is edited, and only once.
+//! A fork made for a background task (like saving) which is dropped before the next edit is never
+//! copied at all.
+
+use std::sync::Arc;
+use std::mem::ManuallyDrop;
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+use crate::jumprope::Node;
+#[cfg(feature = "history")]
+use crate::history::Edit;
+#[cfg(feature = "changes")]
+use crate::changes::ChangeEvent;
+
+/// These methods are only available if the `cow` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "cow")))]
+impl<const N: usize, A: Allocator + Clone> JumpRopeSized<N, A> {
+    /// Make a copy of the rope which shares all of its nodes with the original, in *O(1)* time.
+    /// (Only the first node, which is stored inline in the rope, is copied.) Like
+    /// [`clone`](Clone::clone), marks, history and so on are copied too.
+    ///
+    /// The first edit made to either rope afterwards copies the shared nodes, which takes *O(n)*
+    /// time. After that, the ropes don't share anything and edits are as fast as usual. Ropes
+    /// which are never edited (like a copy being saved in the background) are never copied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// let fork = rope.fork();
+    /// assert!(rope.is_shared());
+    ///
+    /// rope.remove(0..3);
+    /// assert!(!rope.is_shared() && !fork.is_shared());
+    /// assert_eq!(rope, "there");
+    /// assert_eq!(fork, "hi there");
+    /// ```
+    pub fn fork(&self) -> Self {
+        let mut r = self.share();
+        r.copy_state_from(self);
+        r
+    }
+
+    /// Make a rope which shares this rope's nodes, without copying its marks, history and so on.
+    pub(crate) fn share(&self) -> Self {
+        let mut r = self.clone_head();
+        *r.shared = Arc::clone(&self.shared);
+        r
+    }
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Returns true if the rope's nodes are shared with a fork, so the next edit will copy them.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.shared) > 1
+    }
+
+    /// Make sure no fork is using this rope's nodes, by copying them if they're shared. Anything
+    /// which modifies the nodes directly must call this first.
+    pub(crate) fn make_unique(&mut self) {
+        if Arc::get_mut(&mut self.shared).is_some() { return; }
+
+        let first = self.head.first_next().node;
+        unsafe {
+            self.copy_nodes();
+            // If every fork was dropped while we were copying, the old nodes are ours to free.
+            if self.release_nodes() { self.dealloc_list(first); }
+        }
+        self.cursor_valid = false;
+    }
+
+    /// Replace the rope's content with `other`'s nodes, without copying them. Marks move, and the
+    /// history, change events and edit log record this as if the whole rope was replaced (like
+    /// [`set_content`](Self::set_content)).
+    pub(crate) fn swap_in(&mut self, mut other: Self) {
+        #[allow(unused)]
+        let (len, new_len) = (self.len_chars(), other.len_chars());
+        #[cfg(feature = "marks")] {
+            self.marks.remove(0..len);
+            self.marks.insert(0, new_len);
+        }
+        #[cfg(feature = "history")]
+        if self.history.is_some() {
+            let text = self.to_string();
+            if let Some(h) = self.history.as_mut() {
+                h.begin_group();
+                if len > 0 { h.record(Edit::Delete { pos: 0, text }); }
+                if new_len > 0 { h.record(Edit::Insert { pos: 0, len: new_len }); }
+                h.end_group();
+            }
+        }
+        #[cfg(feature = "changes")]
+        if len > 0 || new_len > 0 {
+            self.record_change(ChangeEvent {
+                char_pos: 0, chars_removed: len, chars_inserted: new_len,
+                byte_pos: 0, bytes_removed: self.num_bytes, bytes_inserted: other.num_bytes,
+            });
+        }
+        #[cfg(feature = "edit_log")]
+        if self.edit_log.is_some() && (len > 0 || new_len > 0) {
+            self.log_edit(0, self.to_string(), other.to_string());
+        }
+
+        // Nodes never point back to the inline head, so heads can be swapped freely. The old
+        // nodes are freed with other (unless a fork is still using them).
+        std::mem::swap(&mut self.head, &mut other.head);
+        std::mem::swap(&mut self.num_bytes, &mut other.num_bytes);
+        std::mem::swap(&mut *self.shared, &mut *other.shared);
+        self.cursor_valid = false;
+    }
+
+    /// If this rope's nodes are shared with a fork, leave them to the fork and empty this rope
+    /// without copying anything. This is for methods which are about to replace the whole
+    /// content. Otherwise this does nothing.
+    pub(crate) fn leave_shared_nodes(&mut self) {
+        if Arc::get_mut(&mut self.shared).is_some() { return; }
+
+        let first = self.head.first_next().node;
+        if self.release_nodes() { unsafe { self.dealloc_list(first); } }
+        self.head = Node::new_with_height(1, "");
+        self.num_bytes = 0;
+        self.cursor_valid = false;
+    }
+
+    /// Stop using the current nodes, and start counting uses of new ones. Returns true if no other
+    /// rope is using the old nodes, so this rope needs to free them.
+    fn release_nodes(&mut self) -> bool {
+        let old = std::mem::replace(&mut *self.shared, Arc::new(()));
+        Arc::into_inner(old).is_some()
+    }
+
+    /// Like [`release_nodes`](Self::release_nodes), for when the rope is being dropped.
+    ///
+    /// Safety: The rope must not be used again.
+    pub(crate) unsafe fn release_nodes_on_drop(&mut self) -> bool {
+        Arc::into_inner(ManuallyDrop::take(&mut self.shared)).is_some()
+    }
+
+    /// Free a list of nodes which no rope uses any more, starting from `node`.
+    unsafe fn dealloc_list(&self, mut node: *mut Node<N>) {
+        while !node.is_null() {
+            let next = (*node).first_next().node;
+            self.free_list.dealloc_node(node);
+            node = next;
+        }
+    }
+}
+
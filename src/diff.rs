@@ -0,0 +1,270 @@
+//! Diffing two ropes, to get the edits which turn one into the other.
+//!
+//! The common prefix and suffix of the ropes are found by comparing chunks directly, so only the
+//! part of each rope which changed is ever copied out. The changed part is diffed line by line
+//! (using Myers' algorithm), then each changed run of lines is diffed again character by
+//! character.
+
+use std::ops::Range;
+use crate::fast_str_tools::count_chars;
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+/// Diffs larger than this (in lines or characters) aren't worth searching for. The changed text
+/// is replaced wholesale instead.
+const MAX_D: usize = 1000;
+
+/// A single edit in an edit script, made by [`diff`]. The characters in `range` are replaced with
+/// `content`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// The characters to delete, in unicode characters. This is empty for a pure insert.
+    pub range: Range<usize>,
+    /// The text to insert at `range.start`. This is empty for a pure delete.
+    pub content: String,
+}
+
+/// Returns the edits which turn `a` into `b`. Apply them to `a` with
+/// [`apply_edits`](JumpRopeSized::apply_edits).
+///
+/// The edits are sorted, don't overlap, and all positions are character offsets into `a` as it
+/// was *before* any edits are applied. Identical ropes produce no edits.
+///
+/// This is useful for reloading a file from disk. Diffing the old content against the new content
+/// gives edits which can be used to move cursors and other positions in the document along.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut a = JumpRope::from("hello world");
+/// let b = JumpRope::from("hello there world!");
+/// let edits = diff(&a, &b);
+/// assert_eq!(edits, [
+///     Edit { range: 6..6, content: "there ".into() },
+///     Edit { range: 11..11, content: "!".into() },
+/// ]);
+///
+/// a.apply_edits(&edits);
+/// assert_eq!(a, b);
+/// ```
+pub fn diff<const N: usize, A: Allocator>(a: &JumpRopeSized<N, A>, b: &JumpRopeSized<N, A>) -> Vec<Edit> {
+    let (len_a, len_b) = (a.len_chars(), b.len_chars());
+    let prefix = common_prefix(a.substrings(), b.substrings());
+    if prefix == len_a && prefix == len_b { return Vec::new(); }
+
+    // The suffix can't overlap the prefix.
+    let suffix = common_suffix(a.substrings().rev(), b.substrings().rev())
+        .min(len_a.min(len_b) - prefix);
+
+    let a_mid: String = a.slice_chars(prefix..len_a - suffix).collect();
+    let b_mid: String = b.slice_chars(prefix..len_b - suffix).collect();
+    let a_lines: Vec<&str> = a_mid.split_inclusive('\n').collect();
+    let b_lines: Vec<&str> = b_mid.split_inclusive('\n').collect();
+
+    let mut edits = Vec::new();
+    let Some(ops) = myers(&a_lines, &b_lines) else {
+        edits.push(Edit { range: prefix..len_a - suffix, content: b_mid });
+        return edits;
+    };
+
+    // The character position in a of the start of each line, and one past the end.
+    let mut line_pos = Vec::with_capacity(a_lines.len() + 1);
+    line_pos.push(prefix);
+    for line in &a_lines {
+        line_pos.push(line_pos.last().unwrap() + count_chars(line));
+    }
+
+    for (a_range, b_range) in hunks(&ops) {
+        let base = line_pos[a_range.start];
+        let a_chars: Vec<char> = a_lines[a_range.clone()].iter().flat_map(|l| l.chars()).collect();
+        let b_chars: Vec<char> = b_lines[b_range].iter().flat_map(|l| l.chars()).collect();
+
+        match myers(&a_chars, &b_chars) {
+            Some(ops) => {
+                for (ra, rb) in hunks(&ops) {
+                    edits.push(Edit {
+                        range: base + ra.start..base + ra.end,
+                        content: b_chars[rb].iter().collect(),
+                    });
+                }
+            }
+            None => edits.push(Edit {
+                range: base..line_pos[a_range.end],
+                content: b_chars.into_iter().collect(),
+            }),
+        }
+    }
+    edits
+}
+
+/// The number of characters at the start of a and b which are the same.
+pub(crate) fn common_prefix<'a>(mut a: impl Iterator<Item=&'a str>, mut b: impl Iterator<Item=&'a str>) -> usize {
+    let (mut sa, mut sb) = ("", "");
+    let mut chars = 0;
+    loop {
+        if sa.is_empty() {
+            let Some(s) = a.next() else { return chars; };
+            sa = s;
+            continue;
+        }
+        if sb.is_empty() {
+            let Some(s) = b.next() else { return chars; };
+            sb = s;
+            continue;
+        }
+
+        let len = sa.len().min(sb.len());
+        match sa.as_bytes()[..len].iter().zip(&sb.as_bytes()[..len]).position(|(x, y)| x != y) {
+            None => {
+                // The shorter string ends at len, so len is a char boundary in both.
+                chars += count_chars(&sa[..len]);
+                sa = &sa[len..];
+                sb = &sb[len..];
+            }
+            Some(mut i) => {
+                while !sa.is_char_boundary(i) { i -= 1; }
+                return chars + count_chars(&sa[..i]);
+            }
+        }
+    }
+}
+
+/// The number of characters at the end of a and b which are the same. The iterators return chunks
+/// from the end of each rope backwards.
+pub(crate) fn common_suffix<'a>(mut a: impl Iterator<Item=&'a str>, mut b: impl Iterator<Item=&'a str>) -> usize {
+    let (mut sa, mut sb) = ("", "");
+    let mut chars = 0;
+    loop {
+        if sa.is_empty() {
+            let Some(s) = a.next() else { return chars; };
+            sa = s;
+            continue;
+        }
+        if sb.is_empty() {
+            let Some(s) = b.next() else { return chars; };
+            sb = s;
+            continue;
+        }
+
+        let len = sa.len().min(sb.len());
+        let (ta, tb) = (&sa.as_bytes()[sa.len() - len..], &sb.as_bytes()[sb.len() - len..]);
+        match ta.iter().rev().zip(tb.iter().rev()).position(|(x, y)| x != y) {
+            None => {
+                chars += count_chars(&sa[sa.len() - len..]);
+                sa = &sa[..sa.len() - len];
+                sb = &sb[..sb.len() - len];
+            }
+            Some(mut i) => {
+                // i bytes at the end are the same. Trim that back to whole characters.
+                while !sa.is_char_boundary(sa.len() - i) { i -= 1; }
+                return chars + count_chars(&sa[sa.len() - i..]);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op { Equal, Delete, Insert }
+
+/// Myers' diff algorithm. Returns the ops which turn a into b, or None if that needs more than
+/// MAX_D inserts and deletes.
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Op>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    // v[k + offset] is the furthest x reached along diagonal k (where k = x - y).
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+    // trace[d] is the part of v used by step d (diagonals -d..=d), for walking back through.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max.min(MAX_D as isize) {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+        trace.push(v[at(-d)..=at(d)].to_vec());
+    }
+    None
+}
+
+/// Walk back through the steps recorded by myers from (x, y) to the start.
+fn backtrack(trace: &[Vec<isize>], mut x: isize, mut y: isize) -> Vec<Op> {
+    let mut ops = Vec::new();
+    for d in (1..=trace.len() as isize).rev() {
+        // The previous step covered diagonals -(d - 1)..=(d - 1).
+        let prev = &trace[d as usize - 1];
+        let get = |k: isize| prev[(k + d - 1) as usize];
+
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        ops.push(if prev_k == k + 1 { Op::Insert } else { Op::Delete });
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.extend((0..x).map(|_| Op::Equal));
+    ops.reverse();
+    ops
+}
+
+/// Group the ops into runs of changes. Returns the range of items in a and b for each run.
+fn hunks(ops: &[Op]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut result = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let mut i = 0;
+    while i < ops.len() {
+        if ops[i] == Op::Equal {
+            x += 1;
+            y += 1;
+            i += 1;
+            continue;
+        }
+
+        let (start_x, start_y) = (x, y);
+        while i < ops.len() && ops[i] != Op::Equal {
+            if ops[i] == Op::Delete { x += 1; } else { y += 1; }
+            i += 1;
+        }
+        result.push((start_x..x, start_y..y));
+    }
+    result
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Apply an edit script made by [`diff`]. The edits must be sorted and not overlap, with
+    /// positions relative to the rope before any of the edits are applied. (They're applied from
+    /// the end of the rope backwards, so earlier positions stay valid.)
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edit's range is out of bounds.
+    pub fn apply_edits(&mut self, edits: &[Edit]) {
+        #[cfg(feature = "history")]
+        self.begin_undo_group();
+        for edit in edits.iter().rev() {
+            self.replace(edit.range.clone(), &edit.content);
+        }
+        #[cfg(feature = "history")]
+        self.end_undo_group();
+    }
+}
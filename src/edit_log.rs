@@ -0,0 +1,209 @@
+//! Edit logs, for recording a session of edits so it can be replayed later (for example, to
+//! reproduce a bug report). These are only available with the `edit_log` feature.
+//!
+//! Unlike the undo history, the log stores the text of every insert as well as every delete, so
+//! the entries alone describe the whole session.
+
+use std::collections::VecDeque;
+use std::collections::vec_deque::Iter;
+use crate::{CharRange, EditSpan, JumpRopeSized};
+use crate::allocator::Allocator;
+use crate::fast_str_tools::count_chars;
+
+/// One recorded edit: `deleted` was removed from the rope at `pos`, and then `inserted` was
+/// inserted there. Positions are in unicode characters.
+///
+/// The fields are public (and plain strings), so entries can be saved in any format and pushed
+/// back into an [`EditLog`] with [`EditLog::push`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LogEntry {
+    /// The position of the edit, in unicode characters.
+    pub pos: usize,
+    /// The text which was removed.
+    pub deleted: String,
+    /// The text which was inserted.
+    pub inserted: String,
+}
+
+impl LogEntry {
+    /// The shape of this edit, for moving positions and ranges through it.
+    pub fn span(&self) -> EditSpan {
+        EditSpan { pos: self.pos, removed: count_chars(&self.deleted), inserted: count_chars(&self.inserted) }
+    }
+
+    /// Make this edit to a rope.
+    pub fn apply<const N: usize, A: Allocator>(&self, rope: &mut JumpRopeSized<N, A>) {
+        let span = self.span();
+        rope.replace(span.pos..span.pos + span.removed, &self.inserted);
+    }
+}
+
+/// A log of the edits made to a rope. Each entry moves the log forward one revision.
+///
+/// A log can be limited to its most recent entries, so it doesn't grow without bound. Once older
+/// entries are dropped, [`first_revision`](EditLog::first_revision) moves forward, and the log
+/// can only be replayed onto the content from that revision.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditLog {
+    entries: VecDeque<LogEntry>,
+    first_revision: usize,
+    limit: Option<usize>,
+}
+
+impl EditLog {
+    /// Create an empty log with no limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty log which keeps at most `limit` entries, dropping the oldest.
+    pub fn with_limit(limit: usize) -> Self {
+        EditLog { limit: Some(limit), ..Self::default() }
+    }
+
+    /// Change the maximum number of entries kept. Pass `None` to keep every entry.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.trim();
+    }
+
+    /// The revision after the most recent entry. This is the number of entries pushed since the
+    /// log was created (including entries which have since been dropped).
+    pub fn revision(&self) -> usize {
+        self.first_revision + self.entries.len()
+    }
+
+    /// The revision the oldest entry in the log applies to. This is 0 unless entries have been
+    /// dropped.
+    pub fn first_revision(&self) -> usize {
+        self.first_revision
+    }
+
+    /// The number of entries in the log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no entries in the log.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate through the entries in the log, oldest first.
+    pub fn entries(&self) -> Iter<'_, LogEntry> {
+        self.entries.iter()
+    }
+
+    /// Add an entry to the end of the log. Ropes do this for each edit while their log is
+    /// enabled.
+    pub fn push(&mut self, entry: LogEntry) {
+        self.entries.push_back(entry);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        let Some(limit) = self.limit else { return; };
+        while self.entries.len() > limit {
+            self.entries.pop_front();
+            self.first_revision += 1;
+        }
+    }
+
+    /// Make every edit in the log to `base`, which should hold the content the rope had at
+    /// [`first_revision`](Self::first_revision). Afterwards, `base` has the content from the
+    /// latest revision.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// rope.enable_edit_log();
+    /// rope.insert(2, " there");
+    /// rope.remove(0..3);
+    ///
+    /// let mut copy = JumpRope::from("hi");
+    /// rope.edit_log().unwrap().replay(&mut copy);
+    /// assert_eq!(copy, "there");
+    /// ```
+    pub fn replay<const N: usize, A: Allocator>(&self, base: &mut JumpRopeSized<N, A>) {
+        for entry in &self.entries {
+            entry.apply(base);
+        }
+    }
+
+    /// Move a position from revision `from_rev` through every later edit in the log, returning
+    /// where it ends up in the latest revision.
+    ///
+    /// Positions move like an empty [`CharRange`]: a position inside deleted text moves to the
+    /// start of the edit, and text inserted at the position goes before it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from_rev` is before [`first_revision`](Self::first_revision) or after
+    /// [`revision`](Self::revision).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// rope.enable_edit_log();
+    /// rope.insert(0, "oh, ");
+    /// rope.remove(4..7);
+    /// let log = rope.edit_log().unwrap();
+    /// assert_eq!(log.map_position(0, 3), 4); // "there"
+    /// assert_eq!(log.map_position(1, 9), 6); // "ere"
+    /// ```
+    pub fn map_position(&self, from_rev: usize, pos: usize) -> usize {
+        assert!(from_rev >= self.first_revision && from_rev <= self.revision(),
+            "Revision {} is not in the log", from_rev);
+        self.entries.iter().skip(from_rev - self.first_revision).fold(pos, |pos, entry| {
+            CharRange::new(pos, pos).transform_after_edit(&entry.span()).unwrap().start()
+        })
+    }
+}
+
+/// These methods are only available if the `edit_log` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "edit_log")))]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Start recording every edit made to the rope in an [`EditLog`]. The log starts at revision 0,
+    /// with the rope's current content. Calling this again does nothing.
+    ///
+    /// Every insert and delete is one entry (so [`replace`](Self::replace) makes two).
+    /// [`append`](Self::append), [`split_at_char`](Self::split_at_char),
+    /// [`set_content`](Self::set_content) and [`clear`](Self::clear) make one entry each.
+    ///
+    /// The log keeps every entry until it's limited with
+    /// [`set_edit_log_limit`](Self::set_edit_log_limit).
+    pub fn enable_edit_log(&mut self) {
+        if self.edit_log.is_none() {
+            self.edit_log = Some(Box::default());
+        }
+    }
+
+    /// Limit the log to its most recent `limit` entries. This does nothing if the log isn't
+    /// enabled.
+    pub fn set_edit_log_limit(&mut self, limit: usize) {
+        if let Some(log) = self.edit_log.as_mut() {
+            log.set_limit(Some(limit));
+        }
+    }
+
+    /// The edits recorded so far, or `None` if the log isn't enabled.
+    pub fn edit_log(&self) -> Option<&EditLog> {
+        self.edit_log.as_deref()
+    }
+
+    /// Stop recording edits, and return the log.
+    pub fn take_edit_log(&mut self) -> Option<EditLog> {
+        self.edit_log.take().map(|log| *log)
+    }
+
+    /// Record an edit made without going through a cursor.
+    pub(crate) fn log_edit(&mut self, pos: usize, deleted: String, inserted: String) {
+        if let Some(log) = self.edit_log.as_mut() {
+            log.push(LogEntry { pos, deleted, inserted });
+        }
+    }
+}
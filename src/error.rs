@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+
+/// Errors returned by the checked (`try_*`) rope methods, and by methods which take byte offsets.
+///
+/// The unchecked methods (like [`insert`](crate::JumpRope::insert)) never return errors. Instead
+/// they clamp out of range positions to the end of the rope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RopeError {
+    /// The requested position (in unicode characters) is past the end of the rope.
+    ///
+    /// Methods which take a line and column report whichever one was out of range, along with the
+    /// number of lines in the rope or characters in the line. Methods which take byte offsets
+    /// report the offset and length in bytes.
+    PositionOutOfBounds {
+        pos: usize,
+        len: usize,
+    },
+
+    /// The requested byte offset is in the middle of a multi-byte unicode character.
+    InvalidCodepoint {
+        pos: usize,
+    },
+
+    /// The buffer passed to [`copy_bytes`](crate::JumpRope::copy_bytes) is too small. It needed
+    /// `needed` bytes, but only had room for `len`.
+    BufferTooSmall {
+        needed: usize,
+        len: usize,
+    },
+}
+
+impl Display for RopeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RopeError::PositionOutOfBounds { pos, len } => {
+                write!(f, "position {} is out of bounds for rope of length {}", pos, len)
+            }
+            RopeError::InvalidCodepoint { pos } => {
+                write!(f, "byte offset {} is not on a character boundary", pos)
+            }
+            RopeError::BufferTooSmall { needed, len } => {
+                write!(f, "buffer of {} bytes is too small to hold {} bytes", len, needed)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RopeError {}
@@ -0,0 +1,182 @@
+//! Frozen ropes: read-only copies of a rope which can be shared with background threads.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::sync::Arc;
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+use crate::utils::{find_in_chunks, str_chars_to_bytes};
+
+/// The rope's chunks are merged into chunks of about this many bytes when it's frozen. (The rope's
+/// own nodes are much smaller, since they need to be cheap to edit.)
+const CHUNK_BYTES: usize = 4096;
+
+struct Chunk {
+    content: Box<str>,
+    /// The character offset of the start of this chunk in the frozen rope.
+    char_start: usize,
+    char_len: usize,
+}
+
+/// A read-only copy of a rope, made by [`JumpRopeSized::freeze`]. Frozen ropes are `Send + Sync`,
+/// and cloning one is cheap (*O(1)*), so they can be handed to background tasks like indexing,
+/// saving or spell checking while the original rope keeps being edited.
+///
+/// The content is stored as a list of immutable chunks, so freezing a large document never
+/// builds one giant string. Reading a character or a range from a frozen rope is *O(log n)*, like the rope itself.
+#[derive(Clone)]
+pub struct FrozenRope {
+    chunks: Arc<[Chunk]>,
+    len_chars: usize,
+    len_bytes: usize,
+}
+
+impl FrozenRope {
+    /// The length of the frozen content in unicode characters.
+    pub fn len_chars(&self) -> usize { self.len_chars }
+
+    /// The length of the frozen content in bytes.
+    pub fn len_bytes(&self) -> usize { self.len_bytes }
+
+    /// Returns `true` if the frozen content is empty.
+    pub fn is_empty(&self) -> bool { self.len_bytes == 0 }
+
+    /// The index of the chunk containing the character at `char_pos`, which must be less than
+    /// `len_chars`.
+    fn chunk_index(&self, char_pos: usize) -> usize {
+        self.chunks.partition_point(|c| c.char_start <= char_pos) - 1
+    }
+
+    /// Iterate through the chunks of the frozen content. Like
+    /// [`JumpRopeSized::substrings`], how the content is split into chunks is an implementation
+    /// detail.
+    pub fn substrings(&self) -> impl Iterator<Item = &str> + '_ {
+        self.chunks.iter().map(|c| &*c.content)
+    }
+
+    /// Iterate through the substrings which make up a range of unicode characters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the content, or if it starts after it ends.
+    pub fn slice_substrings(&self, range: Range<usize>) -> impl Iterator<Item = &str> + '_ {
+        assert!(range.start <= range.end && range.end <= self.len_chars,
+            "Range {:?} is out of bounds (length {})", range, self.len_chars);
+        let first = if range.is_empty() { self.chunks.len() } else { self.chunk_index(range.start) };
+
+        self.chunks[first..].iter()
+            .take_while(move |c| c.char_start < range.end)
+            .map(move |c| {
+                let s = &*c.content;
+                let from = range.start.saturating_sub(c.char_start);
+                let to = (range.end - c.char_start).min(c.char_len);
+                let start = str_chars_to_bytes(s, from);
+                let end = start + str_chars_to_bytes(&s[start..], to - from);
+                &s[start..end]
+            })
+    }
+
+    /// Iterate through all the characters in the frozen content.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.substrings().flat_map(str::chars)
+    }
+
+    /// Iterate through the characters in a range of unicode characters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the content, or if it starts after it ends.
+    pub fn slice_chars(&self, range: Range<usize>) -> impl Iterator<Item = char> + '_ {
+        self.slice_substrings(range).flat_map(str::chars)
+    }
+
+    /// Returns the character at the given unicode character offset, or `None` if the position is
+    /// past the end.
+    pub fn char_at(&self, char_pos: usize) -> Option<char> {
+        if char_pos >= self.len_chars { return None; }
+        let chunk = &self.chunks[self.chunk_index(char_pos)];
+        let s = &*chunk.content;
+        s[str_chars_to_bytes(s, char_pos - chunk.char_start)..].chars().next()
+    }
+
+    /// Search for `pattern`, starting at unicode character offset `start`. This works like
+    /// [`JumpRopeSized::find_at`].
+    pub fn find_at(&self, pattern: &str, start: usize) -> Option<usize> {
+        if start > self.len_chars { return None; }
+        find_in_chunks(self.slice_substrings(start..self.len_chars), pattern).map(|i| start + i)
+    }
+}
+
+impl Debug for FrozenRope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FrozenRope").field(&self.to_string()).finish()
+    }
+}
+
+impl Display for FrozenRope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for s in self.substrings() {
+            f.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsRef<str>> PartialEq<T> for FrozenRope {
+    fn eq(&self, other: &T) -> bool {
+        // Compare bytes, since a character in other can straddle the end of a chunk.
+        let mut other = other.as_ref().as_bytes();
+        if other.len() != self.len_bytes { return false; }
+        self.substrings().all(|s| {
+            let (head, rest) = other.split_at(s.len());
+            other = rest;
+            head == s.as_bytes()
+        })
+    }
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Make a read-only copy of the rope which can be shared between threads. The copy isn't
+    /// affected by later edits to the rope. Freezing takes *O(n)* time, since nodes aren't shared
+    /// between ropes.
+    ///
+    /// The content is copied chunk by chunk, without first joining it into a single string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// let frozen = rope.freeze();
+    /// rope.remove(0..3);
+    ///
+    /// let handle = std::thread::spawn(move || frozen.find_at("there", 0));
+    /// assert_eq!(handle.join().unwrap(), Some(3));
+    /// ```
+    pub fn freeze(&self) -> FrozenRope {
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        let mut char_start = 0;
+        let mut char_len = 0;
+
+        for (s, len) in self.substrings_with_len() {
+            if !current.is_empty() && current.len() + s.len() > CHUNK_BYTES {
+                chunks.push(Chunk { content: current.into_boxed_str(), char_start, char_len });
+                current = String::with_capacity(CHUNK_BYTES);
+                char_start += char_len;
+                char_len = 0;
+            }
+            current.push_str(s);
+            char_len += len;
+        }
+        if !current.is_empty() {
+            chunks.push(Chunk { content: current.into_boxed_str(), char_start, char_len });
+        }
+
+        FrozenRope {
+            chunks: chunks.into(),
+            len_chars: self.len_chars(),
+            len_bytes: self.len_bytes(),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Helpers for fuzzing and property testing code which uses jumprope. These are only available
+//! with the `fuzzing` feature.
+
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+/// An edit to make to a rope, with positions in unicode characters. Positions don't need to be
+/// in range - see [`JumpRopeSized::apply_fuzz_op`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzOp {
+    /// Insert the string at the position.
+    Insert(usize, String),
+    /// Delete (position, number of characters).
+    Del(usize, usize),
+}
+
+#[cfg_attr(doc_cfg, doc(cfg(feature = "fuzzing")))]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Applies an arbitrary edit to the rope. Insert and delete positions past the end of the rope
+    /// wrap around to `pos % (len_chars() + 1)`, and deletes are truncated at the end of the
+    /// rope. So any value of [`FuzzOp`] can be applied to any rope without panicking, which makes
+    /// it easy to throw randomly generated ops at a rope in a fuzzer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// rope.apply_fuzz_op(FuzzOp::Insert(5, "XX".into())); // 5 % 3 = 2
+    /// assert_eq!(rope, "hiXX");
+    /// rope.apply_fuzz_op(FuzzOp::Del(1, 100));
+    /// assert_eq!(rope, "h");
+    /// ```
+    pub fn apply_fuzz_op(&mut self, op: FuzzOp) {
+        let len = self.len_chars();
+        match op {
+            FuzzOp::Insert(pos, content) => {
+                self.insert(pos % (len + 1), &content);
+            }
+            FuzzOp::Del(pos, del_len) => {
+                let pos = pos % (len + 1);
+                self.remove(pos..pos.saturating_add(del_len));
+            }
+        }
+    }
+}
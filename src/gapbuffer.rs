@@ -1,9 +1,15 @@
 use crate::fast_str_tools::*;
 #[cfg(feature = "line_conversion")]
-use crate::utils::count_lines;
+use crate::utils::{count_lines, line_start_byte};
 use crate::utils::str_chars_to_bytes_rev;
 use get_size::GetSize;
+#[cfg(feature = "content_hash")]
+use crate::content_hash::HashCache;
 
+/// The text content of a single skip list node. The gap sits wherever the node was last edited,
+/// so a run of inserts (or backspaces) at the same place only writes into the gap. Bytes are only
+/// moved when an edit happens somewhere else in the node. Readers see the content as two strings,
+/// one on each side of the gap.
 #[derive(Debug, Clone, Eq, GetSize)]
 pub struct GapBuffer<const LEN: usize> {
     data: [u8; LEN],
@@ -21,11 +27,26 @@ pub struct GapBuffer<const LEN: usize> {
 
     pub(crate) gap_len: u16,
     all_ascii: bool,
+
+    /// The hash of the content, once it's been computed. See content_hash.rs.
+    #[cfg(feature = "content_hash")]
+    #[get_size(size = 0)]
+    pub(crate) hash: HashCache,
 }
 
+/// Every read of a node's content goes through here.
+///
+/// The content on either side of the gap is always valid UTF-8: strings are only ever inserted
+/// whole, and content is only split (or moved between nodes) at character boundaries. So release
+/// builds skip validation, which would otherwise make every read O(n) in the amount of content
+/// read. Debug builds (and fuzzing builds, so fuzzers catch corruption) still validate.
+///
+/// # Safety
+///
+/// arr must be valid UTF-8.
 #[inline]
 unsafe fn slice_to_str(arr: &[u8]) -> &str {
-    if cfg!(debug_assertions) {
+    if cfg!(any(debug_assertions, feature = "fuzzing")) {
         std::str::from_utf8(arr).unwrap()
     } else {
         std::str::from_utf8_unchecked(arr)
@@ -33,6 +54,7 @@ unsafe fn slice_to_str(arr: &[u8]) -> &str {
 }
 
 impl<const LEN: usize> GapBuffer<LEN> {
+    // Offsets into the buffer are stored as u16s. JumpRopeSized::VALID_NODE_SIZE checks LEN fits.
     pub fn new() -> Self {
         Self {
             data: [0; LEN],
@@ -44,6 +66,8 @@ impl<const LEN: usize> GapBuffer<LEN> {
             gap_start_lines: 0,
             gap_len: LEN as u16,
             all_ascii: true,
+            #[cfg(feature = "content_hash")]
+            hash: HashCache::new(),
         }
     }
 
@@ -149,6 +173,8 @@ impl<const LEN: usize> GapBuffer<LEN> {
         let len = s.len();
         let char_len = count_chars(s);
         assert!(len <= self.gap_len as usize);
+        #[cfg(feature = "content_hash")]
+        self.hash.invalidate();
 
         let start = self.gap_start_bytes as usize;
         self.data[start..start+len].copy_from_slice(s.as_bytes());
@@ -182,6 +208,8 @@ impl<const LEN: usize> GapBuffer<LEN> {
 
     /// Remove chars after the gap (ie, at gap .. gap+del_len)
     pub fn remove_after_gap(&mut self, del_bytes: usize) {
+        #[cfg(feature = "content_hash")]
+        self.hash.invalidate();
         if cfg!(debug_assertions) {
             // Zero out the deleted bytes in debug mode.
             self.data[
@@ -216,6 +244,8 @@ impl<const LEN: usize> GapBuffer<LEN> {
 
         if del_len == 0 { return 0; }
         debug_assert!(del_len <= self.len_bytes() - pos);
+        #[cfg(feature = "content_hash")]
+        self.hash.invalidate();
         let mut rm_start_bytes = 0;
 
         let gap_chars = self.gap_start_chars as usize;
@@ -346,15 +376,55 @@ impl<const LEN: usize> GapBuffer<LEN> {
         }
     }
 
+    /// Calculate & return the number of newlines in `[0..char_pos]`
+    #[cfg(feature = "line_conversion")]
+    pub(crate) fn count_lines(&self, char_pos: usize) -> usize {
+        let gap_chars = self.gap_start_chars as usize;
+        if char_pos == gap_chars {
+            self.gap_start_lines as usize
+        } else if char_pos < gap_chars {
+            let s = self.start_as_str();
+            let bytes = self.int_str_get_byte_offset(s, char_pos);
+            count_lines(&s[..bytes])
+        } else {
+            let s = self.end_as_str();
+            let bytes = self.int_str_get_byte_offset(s, char_pos - gap_chars);
+            self.gap_start_lines as usize + count_lines(&s[..bytes])
+        }
+    }
+
+    /// Returns the char offset of the start of the named line in this buffer - that is, the
+    /// position directly after the line'th newline. The buffer must contain at least that many
+    /// newlines.
+    #[cfg(feature = "line_conversion")]
+    pub(crate) fn line_to_char(&self, line: usize) -> usize {
+        let gap_lines = self.gap_start_lines as usize;
+        if line <= gap_lines {
+            let s = self.start_as_str();
+            self.count_internal_chars(&s[..line_start_byte(s, line)])
+        } else {
+            let s = self.end_as_str();
+            let bytes = line_start_byte(s, line - gap_lines);
+            self.gap_start_chars as usize + self.count_internal_chars(&s[..bytes])
+        }
+    }
+
     /// Take the remaining contents in the gap buffer. Mark them as deleted, but return them.
     /// This will leave those items non-zero, but that doesn't matter.
     pub fn take_rest(&mut self) -> &str {
+        #[cfg(feature = "content_hash")]
+        self.hash.invalidate();
         let last_idx = (self.gap_start_bytes + self.gap_len) as usize;
         self.gap_len = LEN as u16 - self.gap_start_bytes;
         unsafe { slice_to_str(&self.data[last_idx..LEN]) }
     }
 
     pub(crate) fn check(&self) {
+        // Each side of the gap must be valid UTF-8 by itself. (Reads only validate the content in
+        // debug builds.)
+        assert!(std::str::from_utf8(&self.data[..self.gap_start_bytes as usize]).is_ok());
+        assert!(std::str::from_utf8(&self.data[(self.gap_start_bytes + self.gap_len) as usize..]).is_ok());
+
         let char_len = count_chars(self.start_as_str());
         assert_eq!(char_len, self.gap_start_chars as usize);
 
@@ -437,6 +507,39 @@ mod test {
         check_eq(&b, "xhxi");
     }
 
+    #[test]
+    #[cfg(feature = "line_conversion")]
+    fn lines() {
+        let mut b = GapBuffer::<20>::new_from_str("a\nb\n");
+        b.try_insert(2, "ό\n").unwrap(); // 'a\nό\nb\n' with the gap after ό\n
+        check_eq(&b, "a\nό\nb\n");
+
+        assert_eq!(b.count_lines(0), 0);
+        assert_eq!(b.count_lines(2), 1);
+        assert_eq!(b.count_lines(4), 2);
+        assert_eq!(b.count_lines(6), 3);
+
+        assert_eq!(b.line_to_char(0), 0);
+        assert_eq!(b.line_to_char(1), 2);
+        assert_eq!(b.line_to_char(2), 4);
+        assert_eq!(b.line_to_char(3), 6);
+    }
+
+    #[test]
+    fn larger_than_u8() {
+        let s = "a½↯𐆐".repeat(30); // 300 bytes.
+        let mut b = GapBuffer::<300>::new_from_str(&s);
+        check_eq(&b, &s);
+        assert_eq!(b.gap_len, 0);
+        b.check();
+
+        assert_eq!(b.remove_chars(40, 20), 50);
+        assert_eq!(b.len_bytes(), 250);
+        b.try_insert(100, &s[..50]).unwrap();
+        check_eq(&b, &s);
+        b.check();
+    }
+
     #[test]
     fn remove() {
         let mut b = GapBuffer::<5>::new_from_str("hi");
@@ -0,0 +1,222 @@
+//! Undo and redo. These are only available with the `history` feature.
+//!
+//! Once history is enabled, every insert and delete records an [`Edit`]. Undoing a step applies
+//! the inverse of its edits through the normal editing methods, and those edits are recorded in
+//! turn as a step on the redo stack. Redoing does the same thing in the other direction.
+
+use std::collections::VecDeque;
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+/// A recorded change to the rope. Positions are in unicode characters.
+#[derive(Debug, Clone)]
+pub(crate) enum Edit {
+    /// len characters were inserted at pos.
+    Insert { pos: usize, len: usize },
+    /// The text at pos was deleted.
+    Delete { pos: usize, text: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    /// Edits are being made by undo or redo. They're collected into `group` and end up on the
+    /// other stack.
+    Replaying,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    undo: VecDeque<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    /// Edits recorded since the outermost begin_undo_group call (or during undo / redo).
+    group: Vec<Edit>,
+    depth: usize,
+    limit: usize,
+    mode: Mode,
+}
+
+impl History {
+    fn new() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            group: Vec::new(),
+            depth: 0,
+            limit: usize::MAX,
+            mode: Mode::Normal,
+        }
+    }
+
+    pub(crate) fn record(&mut self, edit: Edit) {
+        if self.depth > 0 || self.mode == Mode::Replaying {
+            self.group.push(edit);
+        } else {
+            self.redo.clear();
+            self.push_undo(vec![edit]);
+        }
+    }
+
+    fn push_undo(&mut self, step: Vec<Edit>) {
+        if self.limit == 0 { return; }
+        if self.undo.len() == self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(step);
+    }
+
+    pub(crate) fn begin_group(&mut self) {
+        self.depth += 1;
+    }
+
+    pub(crate) fn end_group(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        // Groups made while replaying are part of the step being replayed.
+        if self.depth == 0 && self.mode == Mode::Normal && !self.group.is_empty() {
+            self.redo.clear();
+            let step = std::mem::take(&mut self.group);
+            self.push_undo(step);
+        }
+    }
+}
+
+/// These methods are only available if the `history` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "history")))]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Start recording edits, so they can be undone with [`undo`](Self::undo). The rope's current
+    /// content is the furthest back undo can go. Calling this again does nothing.
+    ///
+    /// Each insert, remove or replace is one undo step. Use
+    /// [`begin_undo_group`](Self::begin_undo_group) to group edits (like a burst of typing) into
+    /// a single step.
+    ///
+    /// Deleted text is kept in the history, so by default the history grows without bound. See
+    /// [`set_history_limit`](Self::set_history_limit).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// rope.enable_history();
+    /// rope.insert(2, " there");
+    /// rope.remove(0..3);
+    /// assert_eq!(rope, "there");
+    ///
+    /// assert!(rope.undo());
+    /// assert_eq!(rope, "hi there");
+    /// assert!(rope.undo());
+    /// assert_eq!(rope, "hi");
+    /// assert!(!rope.undo());
+    ///
+    /// assert!(rope.redo());
+    /// assert_eq!(rope, "hi there");
+    /// ```
+    pub fn enable_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(Box::new(History::new()));
+        }
+    }
+
+    /// Stop recording edits, and discard the undo and redo history.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Limit the number of undo steps kept in the history. Once there are more than `limit` steps,
+    /// the oldest are discarded. Redo steps aren't limited, but there are never more redo steps
+    /// than undo steps which were undone.
+    ///
+    /// This does nothing if history isn't enabled.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        if let Some(h) = self.history.as_mut() {
+            h.limit = limit;
+            while h.undo.len() > limit {
+                h.undo.pop_front();
+            }
+        }
+    }
+
+    /// Start grouping edits into a single undo step. The group ends at the matching call to
+    /// [`end_undo_group`](Self::end_undo_group). Groups can be nested, in which case the outermost
+    /// group is the undo step.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::new();
+    /// rope.enable_history();
+    /// rope.begin_undo_group();
+    /// rope.push_str("a");
+    /// rope.push_str("b");
+    /// rope.end_undo_group();
+    /// rope.undo();
+    /// assert_eq!(rope, "");
+    /// ```
+    pub fn begin_undo_group(&mut self) {
+        if let Some(h) = self.history.as_mut() {
+            h.begin_group();
+        }
+    }
+
+    /// End a group of edits started with [`begin_undo_group`](Self::begin_undo_group).
+    pub fn end_undo_group(&mut self) {
+        if let Some(h) = self.history.as_mut() {
+            h.end_group();
+        }
+    }
+
+    /// Undo the most recent undo step. Returns false if there's nothing to undo (or history isn't
+    /// enabled).
+    ///
+    /// If an undo group is open, it's ended first.
+    pub fn undo(&mut self) -> bool {
+        let Some(h) = self.history.as_mut() else { return false; };
+        if h.depth > 0 {
+            h.depth = 1;
+            h.end_group();
+        }
+        let Some(step) = h.undo.pop_back() else { return false; };
+
+        let inverse = self.replay_inverse(&step);
+        self.history.as_mut().unwrap().redo.push(inverse);
+        true
+    }
+
+    /// Redo the most recently undone step. Returns false if there's nothing to redo. Any edit
+    /// (other than undo) discards the steps which could be redone.
+    pub fn redo(&mut self) -> bool {
+        let Some(h) = self.history.as_mut() else { return false; };
+        if h.depth > 0 {
+            h.depth = 1;
+            h.end_group();
+        }
+        let Some(step) = h.redo.pop() else { return false; };
+
+        let inverse = self.replay_inverse(&step);
+        self.history.as_mut().unwrap().push_undo(inverse);
+        true
+    }
+
+    /// Apply the inverse of each edit in a step (in reverse order), returning the edits that made.
+    fn replay_inverse(&mut self, step: &[Edit]) -> Vec<Edit> {
+        self.history.as_mut().unwrap().mode = Mode::Replaying;
+        for edit in step.iter().rev() {
+            match edit {
+                Edit::Insert { pos, len } => self.remove(*pos..*pos + *len),
+                Edit::Delete { pos, text } => self.insert(*pos, text),
+            }
+        }
+        let h = self.history.as_mut().unwrap();
+        h.mode = Mode::Normal;
+        std::mem::take(&mut h.group)
+    }
+
+    /// Record an edit made without going through a cursor.
+    pub(crate) fn record_edit(&mut self, edit: Edit) {
+        if let Some(h) = self.history.as_mut() {
+            h.record(edit);
+        }
+    }
+}
@@ -1,14 +1,15 @@
-use std::ops::Range;
+use std::ops::{Range, RangeBounds};
 use crate::jumprope::*;
-use crate::utils::str_chars_to_bytes;
+use crate::allocator::{Allocator, Global};
+use crate::utils::{bounds_to_range, str_chars_to_bytes};
 
 /// An iterator over chunks (nodes) in the list.
-pub(crate) struct NodeIter<'a>(Option<&'a Node>);
+pub(crate) struct NodeIter<'a, const N: usize>(Option<&'a Node<N>>);
 
-impl<'a> Iterator for NodeIter<'a> {
-    type Item = &'a Node;
+impl<'a, const N: usize> Iterator for NodeIter<'a, N> {
+    type Item = &'a Node<N>;
 
-    fn next(&mut self) -> Option<&'a Node> {
+    fn next(&mut self) -> Option<&'a Node<N>> {
         let prev = self.0;
         if let Some(n) = self.0 {
             // TODO: What?
@@ -18,27 +19,53 @@ impl<'a> Iterator for NodeIter<'a> {
     }
 }
 
+/// An iterator which consumes a rope, yielding its content as owned strings. Each node is freed as
+/// soon as its content has been yielded. See [`JumpRopeSized::into_chunks`].
+pub struct IntoChunks<const N: usize = NODE_STR_SIZE, A: Allocator = Global> {
+    rope: JumpRopeSized<N, A>,
+}
+
+impl<const N: usize, A: Allocator> Iterator for IntoChunks<N, A> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.rope.take_first_chunk()
+    }
+}
+
 /// A content iterator iterates over the strings in the rope
-pub struct ContentIter<'a> {
-    next: Option<&'a Node>,
+pub struct ContentIter<'a, const N: usize = NODE_STR_SIZE> {
+    next: Option<&'a Node<N>>,
     /// Are we at the start or the end of the gap buffer?
     at_start: bool,
+
+    head: &'a Node<N>,
+    /// The node the back of the iterator is in. This is found the first time next_back() is
+    /// called, because most iterators are only used forwards.
+    back: Option<&'a Node<N>>,
+    /// Is the back of the iterator at the end of the gap buffer?
+    back_at_end: bool,
+
+    /// The number of characters left to yield from either end. The two ends stop when they meet.
+    remaining: usize,
 }
 
-impl<'a> ContentIter<'a> {
-    pub fn substrings(self) -> Substrings<'a> {
+impl<'a, const N: usize> ContentIter<'a, N> {
+    pub fn substrings(self) -> Substrings<'a, Self> {
         Substrings(self)
     }
 
-    pub fn chars(self) -> Chars<'a> {
+    pub fn chars(self) -> Chars<'a, Self> {
         self.into()
     }
 }
 
-impl<'a> Iterator for ContentIter<'a> {
+impl<'a, const N: usize> Iterator for ContentIter<'a, N> {
     type Item = (&'a str, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 { return None; }
+
         while let Some(n) = self.next {
             let s = if self.at_start {
                 self.at_start = false;
@@ -50,6 +77,37 @@ impl<'a> Iterator for ContentIter<'a> {
             };
 
             if s.1 > 0 {
+                self.remaining -= s.1;
+                return Some(s);
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, const N: usize> DoubleEndedIterator for ContentIter<'a, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 { return None; }
+        if self.back.is_none() {
+            self.back = Some(self.head.last_node());
+            self.back_at_end = true;
+        }
+
+        while let Some(n) = self.back {
+            let s = if self.back_at_end {
+                self.back_at_end = false;
+                (n.str.end_as_str(), n.num_chars() - n.str.gap_start_chars as usize)
+            } else {
+                // Nodes directly after the head don't point back to it.
+                self.back = unsafe { n.prev.as_ref() }
+                    .or_else(|| if std::ptr::eq(n, self.head) { None } else { Some(self.head) });
+                self.back_at_end = true;
+                (n.str.start_as_str(), n.str.gap_start_chars as usize)
+            };
+
+            if s.1 > 0 {
+                self.remaining -= s.1;
                 return Some(s);
             }
         }
@@ -77,17 +135,26 @@ impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for Substrings<'a, I> {
     }
 }
 
+impl<'a, I: DoubleEndedIterator<Item=(&'a str, usize)>> DoubleEndedIterator for Substrings<'a, I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(s, _)| s)
+    }
+}
+
 /// Iterator over the individual characters in a rope (or rope slice).
 pub struct Chars<'a, I: Iterator<Item=(&'a str, usize)> = ContentIter<'a>> {
     inner: I,
     current: std::str::Chars<'a>,
+    // The substring being read from the back, when iterating in reverse.
+    back: std::str::Chars<'a>,
 }
 
 impl<'a, I: Iterator<Item=(&'a str, usize)>> From<I> for Chars<'a, I> {
     fn from(inner: I) -> Self {
         Self {
             inner,
-            current: "".chars()
+            current: "".chars(),
+            back: "".chars(),
         }
     }
 }
@@ -97,36 +164,56 @@ impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for Chars<'a, I> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.current.next().or_else(|| {
-            self.current = self.inner.next()?.0.chars();
-            let next = self.current.next();
-            // None of the items returned from our inner iterator should be empty.
-            debug_assert!(next.is_some());
-            next
+            match self.inner.next() {
+                Some((s, _)) => {
+                    self.current = s.chars();
+                    let next = self.current.next();
+                    // None of the items returned from our inner iterator should be empty.
+                    debug_assert!(next.is_some());
+                    next
+                }
+                // The rest of the characters (if any) are in the substring being read backwards.
+                None => self.back.next(),
+            }
+        })
+    }
+}
+
+impl<'a, I: DoubleEndedIterator<Item=(&'a str, usize)>> DoubleEndedIterator for Chars<'a, I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| {
+            match self.inner.next_back() {
+                Some((s, _)) => {
+                    self.back = s.chars();
+                    self.back.next_back()
+                }
+                None => self.current.next_back(),
+            }
         })
     }
 }
 
 /// Iterate over a sub-range of the rope.
-pub struct SliceIter<'a> {
-    inner: ContentIter<'a>,
+pub struct SliceIter<'a, const N: usize = NODE_STR_SIZE> {
+    inner: ContentIter<'a, N>,
     skip: usize,
     take_len: usize,
 }
 
-pub type SubstringsInRange<'a> = Substrings<'a, SliceIter<'a>>;
-pub type CharsInRange<'a> = Chars<'a, SliceIter<'a>>;
+pub type SubstringsInRange<'a, const N: usize = NODE_STR_SIZE> = Substrings<'a, SliceIter<'a, N>>;
+pub type CharsInRange<'a, const N: usize = NODE_STR_SIZE> = Chars<'a, SliceIter<'a, N>>;
 
-impl<'a> SliceIter<'a> {
-    pub fn substrings(self) -> SubstringsInRange<'a> {
+impl<'a, const N: usize> SliceIter<'a, N> {
+    pub fn substrings(self) -> SubstringsInRange<'a, N> {
         Substrings(self)
     }
 
-    pub fn chars(self) -> CharsInRange<'a> {
+    pub fn chars(self) -> CharsInRange<'a, N> {
         self.into()
     }
 }
 
-impl<'a> Iterator for SliceIter<'a> {
+impl<'a, const N: usize> Iterator for SliceIter<'a, N> {
     type Item = (&'a str, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -155,8 +242,77 @@ impl<'a> Iterator for SliceIter<'a> {
     }
 }
 
-impl JumpRope {
-    pub(crate) fn node_iter_at_start(&self) -> NodeIter { NodeIter(Some(&self.head)) }
+/// Iterate over the byte offset of the start of each character in the rope, followed by the
+/// length of the rope in bytes.
+pub struct CharBoundaries<'a, const N: usize = NODE_STR_SIZE> {
+    chunks: Substrings<'a, ContentIter<'a, N>>,
+    current: std::str::CharIndices<'a>,
+    /// The byte offset and length of the chunk current reads from.
+    chunk_start: usize,
+    chunk_len: usize,
+    /// Yielded once at the end.
+    len_bytes: Option<usize>,
+}
+
+impl<'a, const N: usize> Iterator for CharBoundaries<'a, N> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if let Some((i, _)) = self.current.next() {
+                return Some(self.chunk_start + i);
+            }
+
+            match self.chunks.next() {
+                Some(s) => {
+                    self.chunk_start += self.chunk_len;
+                    self.chunk_len = s.len();
+                    self.current = s.char_indices();
+                }
+                None => return self.len_bytes.take(),
+            }
+        }
+    }
+}
+
+/// Iterate over the lines in the rope from a starting line, without their line endings. See
+/// [`JumpRopeSized::lines_at`].
+#[cfg(feature = "line_conversion")]
+pub struct LinesAt<'a, const N: usize = NODE_STR_SIZE> {
+    chunks: SubstringsInRange<'a, N>,
+    /// The rest of the chunk we're reading lines from.
+    current: &'a str,
+}
+
+#[cfg(feature = "line_conversion")]
+impl<'a, const N: usize> Iterator for LinesAt<'a, N> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let mut line = String::new();
+        loop {
+            if let Some(i) = self.current.find('\n') {
+                line.push_str(&self.current[..i]);
+                self.current = &self.current[i + 1..];
+                break;
+            }
+            line.push_str(self.current);
+            self.current = "";
+            match self.chunks.next() {
+                Some(s) => self.current = s,
+                // Like str::lines, the content after a trailing newline isn't a line.
+                None if line.is_empty() => return None,
+                None => return Some(line),
+            }
+        }
+
+        if line.ends_with('\r') { line.pop(); }
+        Some(line)
+    }
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    pub(crate) fn node_iter_at_start(&self) -> NodeIter<'_, N> { NodeIter(Some(&self.head)) }
 
     /// Iterate over the rope, visiting each substring in [`str`] chunks. Whenever possible, this is
     /// the best way for a program to read back the contents of a rope, because it avoids allocating
@@ -180,10 +336,54 @@ impl JumpRope {
     /// }
     /// assert_eq!(string, "oh hai");
     /// ```
-    pub fn substrings(&self) -> Substrings<'_> {
+    pub fn substrings(&self) -> Substrings<'_, ContentIter<'_, N>> {
         self.substrings_with_len().substrings()
     }
 
+    /// Call `f` with each substring in the rope, in order. This visits the same substrings as
+    /// [`substrings`](Self::substrings), but it walks the nodes directly rather than keeping an
+    /// iterator's state, so it can be a little faster for simple scans.
+    ///
+    /// The same stability warning applies: the way characters are grouped into substrings may
+    /// change at any time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("oh hai");
+    /// let mut bytes = 0;
+    /// rope.for_each_chunk(|s| bytes += s.len());
+    /// assert_eq!(bytes, rope.len_bytes());
+    /// ```
+    pub fn for_each_chunk<F: FnMut(&str)>(&self, mut f: F) {
+        for node in self.node_iter_at_start() {
+            // Each node's content is split in two by its gap.
+            for s in [node.str.start_as_str(), node.str.end_as_str()] {
+                if !s.is_empty() { f(s); }
+            }
+        }
+    }
+
+    /// Consume the rope, yielding its content as a series of owned strings. Each internal node is
+    /// deallocated once its content has been yielded, so the rope's memory is released as
+    /// iteration proceeds. Dropping the iterator frees the rest of the rope.
+    ///
+    /// Like [`substrings`](Self::substrings), the way characters are grouped into chunks is an
+    /// implementation detail which may change at any time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("oh hai");
+    /// let chunks: Vec<String> = rope.into_chunks().collect();
+    /// assert_eq!(chunks.concat(), "oh hai");
+    /// ```
+    pub fn into_chunks(self) -> IntoChunks<N, A> {
+        IntoChunks { rope: self }
+    }
+
     /// Iterate over all substrings in the rope, but also yield the unicode character length for
     /// each item. A caller could obviously recalculate these lengths from the provided &str
     /// objects, but since the unicode lengths are known this allows small optimizations.
@@ -209,10 +409,14 @@ impl JumpRope {
     /// }
     /// assert_eq!(string, "oh hai");
     /// ```
-    pub fn substrings_with_len(&self) -> ContentIter {
+    pub fn substrings_with_len(&self) -> ContentIter<'_, N> {
         ContentIter {
             next: Some(&self.head),
-            at_start: true
+            at_start: true,
+            head: &self.head,
+            back: None,
+            back_at_end: true,
+            remaining: self.len_chars(),
         }
     }
 
@@ -228,10 +432,54 @@ impl JumpRope {
     /// let rope = JumpRope::from("oh hai");
     /// assert_eq!("oh hai", rope.chars().collect::<String>());
     /// ```
-    pub fn chars(&self) -> Chars {
+    pub fn chars(&self) -> Chars<'_, ContentIter<'_, N>> {
         self.substrings_with_len().chars()
     }
 
+    /// Get an iterator over the lines in the rope, starting at the given (0-based) line. Like
+    /// [`str::lines`], lines are yielded without their trailing `\n` or `\r\n`, and a newline at
+    /// the end of the rope doesn't start another line. Lines past the end of the rope yield
+    /// nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one\ntwo\r\nthree\n");
+    /// assert_eq!(rope.lines_at(1).collect::<Vec<_>>(), ["two", "three"]);
+    /// assert_eq!(rope.lines_at(3).count(), 0);
+    /// ```
+    #[cfg(feature = "line_conversion")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "line_conversion")))]
+    pub fn lines_at(&self, line: usize) -> LinesAt<'_, N> {
+        let start = self.line_to_char(line);
+        LinesAt {
+            chunks: self.slice_substrings(start..self.len_chars()),
+            current: "",
+        }
+    }
+
+    /// Get an iterator over the byte offset of the start of each character in the rope. The last
+    /// item is the length of the rope in bytes (the end of the last character), so an empty rope
+    /// yields a single 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a↯b");
+    /// assert_eq!(rope.char_boundaries().collect::<Vec<_>>(), [0, 1, 4, 5]);
+    /// ```
+    pub fn char_boundaries(&self) -> CharBoundaries<'_, N> {
+        CharBoundaries {
+            chunks: self.substrings(),
+            current: "".char_indices(),
+            chunk_start: 0,
+            chunk_len: 0,
+            len_bytes: Some(self.len_bytes()),
+        }
+    }
+
 
 
     /// Iterate through all the substrings within the specified unicode character range in the
@@ -248,7 +496,7 @@ impl JumpRope {
     /// }
     /// assert_eq!(string, "Greetings!");
     /// ```
-    pub fn slice_substrings(&self, range: Range<usize>) -> SubstringsInRange {
+    pub fn slice_substrings(&self, range: Range<usize>) -> SubstringsInRange<'_, N> {
         self.slice_substrings_with_len(range).substrings()
     }
 
@@ -275,7 +523,7 @@ impl JumpRope {
     /// let string = rope.slice_substrings_with_len(3..13).map(|(str, _len)| str).collect::<String>();
     /// assert_eq!(string, "Greetings!");
     /// ```
-    pub fn slice_substrings_with_len(&self, range: Range<usize>) -> SliceIter {
+    pub fn slice_substrings_with_len(&self, range: Range<usize>) -> SliceIter<'_, N> {
         let cursor = self.read_cursor_at_char(range.start, false);
         let node_gap_start = cursor.node.str.gap_start_chars as usize;
         let local_pos = cursor.offset_chars;
@@ -288,7 +536,12 @@ impl JumpRope {
 
         SliceIter {
             inner: ContentIter {
-                next: Some(cursor.node), at_start
+                next: Some(cursor.node), at_start,
+                head: &self.head,
+                back: None,
+                back_at_end: true,
+                // Counting from the start of the first chunk.
+                remaining: self.len_chars() - (range.start - skip),
             },
             skip,
             take_len: range.end - range.start
@@ -308,18 +561,249 @@ impl JumpRope {
     ///     rope.slice_chars(3..rope.len_chars() - 3).collect::<String>()
     /// );
     /// ```
-    pub fn slice_chars(&self, range: Range<usize>) -> CharsInRange {
+    pub fn slice_chars(&self, range: Range<usize>) -> CharsInRange<'_, N> {
         self.slice_substrings_with_len(range).chars()
     }
 
+    /// Iterate through the substrings which make up a range of unicode characters in the rope.
+    /// This is the same as [`slice_substrings`](Self::slice_substrings), but it accepts any kind
+    /// of range. It's useful for rendering just the visible part of a document without copying
+    /// anything.
+    ///
+    /// The first and last items may be parts of the rope's internal chunks. See the stability
+    /// warning on [`substrings`](Self::substrings).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the rope, or if it starts after it ends.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxGreetings!xxx");
+    /// assert_eq!(rope.chunks_in_range(3..13).collect::<String>(), "Greetings!");
+    /// assert_eq!(rope.chunks_in_range(13..).collect::<String>(), "xxx");
+    /// assert_eq!(rope.chunks_in_range(..=2).collect::<String>(), "xxx");
+    /// ```
+    pub fn chunks_in_range<R: RangeBounds<usize>>(&self, range: R) -> SubstringsInRange<'_, N> {
+        let len = self.len_chars();
+        let range = bounds_to_range(range, len);
+        assert!(range.start <= range.end && range.end <= len,
+            "range {:?} is out of bounds for rope of length {}", range, len);
+        self.slice_substrings(range)
+    }
+
+    /// Iterate through the characters in a range of unicode characters in the rope. This is the
+    /// same as [`slice_chars`](Self::slice_chars), but it accepts any kind of range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the rope, or if it starts after it ends.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxκόσμε!xxx");
+    /// assert_eq!(rope.chars_in_range(3..9).collect::<String>(), "κόσμε!");
+    /// assert_eq!(rope.chars_in_range(9..).collect::<String>(), "xxx");
+    /// ```
+    pub fn chars_in_range<R: RangeBounds<usize>>(&self, range: R) -> CharsInRange<'_, N> {
+        self.chunks_in_range(range).0.chars()
+    }
+
+    /// Walk forward through the characters starting at `from`, while `f` returns true. Returns the
+    /// position of the first character `f` returned false for, or the length of the rope if it
+    /// never did.
+    ///
+    /// This finds `from` once (in *O(log n)*), then reads the rope's chunks in place. Nothing is
+    /// allocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("x = 1234 + y");
+    /// assert_eq!(rope.scan_forward(4, |c| c.is_ascii_digit()), 8);
+    /// assert_eq!(rope.scan_forward(4, |_| true), 12);
+    /// ```
+    pub fn scan_forward<F: FnMut(char) -> bool>(&self, from: usize, mut f: F) -> usize {
+        let mut pos = from;
+        for s in self.slice_substrings(from..self.len_chars()) {
+            for c in s.chars() {
+                if !f(c) { return pos; }
+                pos += 1;
+            }
+        }
+        pos
+    }
+
+    /// Walk backward through the characters before `from`, while `f` returns true. Returns the
+    /// position just after the first character `f` returned false for (so `f` returned true for
+    /// every character between the result and `from`), or 0 if it never did.
+    ///
+    /// Like [`scan_forward`](Self::scan_forward), this doesn't allocate or search from the start of
+    /// the rope for each character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("x = 1234 + y");
+    /// assert_eq!(rope.scan_backward(8, |c| c.is_ascii_digit()), 4);
+    /// assert_eq!(rope.scan_backward(8, |_| true), 0);
+    /// ```
+    pub fn scan_backward<F: FnMut(char) -> bool>(&self, from: usize, mut f: F) -> usize {
+        // Start a content iterator's back end in the node containing from, and trim the first
+        // chunk it yields back to from.
+        let cursor = self.read_cursor_at_char(from, true);
+        let node = cursor.node;
+        let local = cursor.offset_chars;
+        let gap_start = node.str.gap_start_chars as usize;
+        let (back_at_end, chunk_end) = if local > gap_start {
+            (true, node.num_chars())
+        } else {
+            (false, gap_start)
+        };
+
+        let iter = ContentIter {
+            next: None,
+            at_start: true,
+            head: &self.head,
+            back: Some(node),
+            back_at_end,
+            remaining: from - local + chunk_end,
+        };
+        let mut trim = chunk_end - local;
+
+        let mut pos = from;
+        for (mut s, len) in iter.rev() {
+            if trim > 0 {
+                s = &s[..str_chars_to_bytes(s, len - trim)];
+                trim = 0;
+            }
+            for c in s.chars().rev() {
+                if !f(c) { return pos; }
+                pos -= 1;
+            }
+        }
+        pos
+    }
+
+    /// Returns the rope's content as a single string slice when it's stored contiguously, without
+    /// copying anything. Otherwise returns `None`.
+    ///
+    /// Short ropes are usually stored in a single node. But each node has a gap where it was last
+    /// edited, so this also returns `None` after an edit in the middle of the content. (Appending
+    /// to the end of a rope keeps the content contiguous.)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// rope.push_str(" there");
+    /// assert_eq!(rope.as_str(), Some("hi there"));
+    ///
+    /// rope.insert(2, "!");
+    /// assert_eq!(rope.as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        let mut chunks = self.substrings();
+        match (chunks.next(), chunks.next()) {
+            (None, _) => Some(""),
+            (Some(s), None) => Some(s),
+            _ => None,
+        }
+    }
+
     // We also have a to_string implementation from Display, but that doesn't provide size hints.
     pub fn to_string(&self) -> String {
-        let mut result = String::with_capacity(self.len_bytes());
+        let mut result = String::new();
+        self.write_to_string(&mut result);
+        result
+    }
+
+    /// Copy the contents of the rope into a new byte vector, with exactly the capacity needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κό");
+    /// assert_eq!(rope.to_bytes(), "κό".as_bytes());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.len_bytes());
         for s in self.substrings() {
-            result.push_str(s);
+            result.extend_from_slice(s.as_bytes());
         }
         result
     }
+
+    /// Collect the characters in the rope into a new vector, with exactly the capacity needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κό");
+    /// assert_eq!(rope.to_chars(), vec!['κ', 'ό']);
+    /// ```
+    pub fn to_chars(&self) -> Vec<char> {
+        let mut result = Vec::with_capacity(self.len_chars());
+        for s in self.substrings() {
+            result.extend(s.chars());
+        }
+        result
+    }
+
+    /// Append the contents of the rope to the end of `out`. This is the same as
+    /// [`to_string`](Self::to_string), but it lets the caller reuse a scratch buffer. If `out`
+    /// already has enough spare capacity, this method does not allocate.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("world");
+    /// let mut s = String::from("hello ");
+    /// rope.write_to_string(&mut s);
+    /// assert_eq!(s, "hello world");
+    /// ```
+    pub fn write_to_string(&self, out: &mut String) {
+        out.reserve(self.len_bytes());
+        for s in self.substrings() {
+            out.push_str(s);
+        }
+    }
+
+    /// Append the characters in the given range of the rope (in unicode characters) to the end of
+    /// `out`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxGreetings!xxx");
+    /// let mut s = String::new();
+    /// rope.to_string_in_range(3..13, &mut s);
+    /// assert_eq!(s, "Greetings!");
+    /// ```
+    pub fn to_string_in_range(&self, range: Range<usize>, out: &mut String) {
+        for s in self.slice_substrings(range) {
+            out.push_str(s);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -10,16 +10,33 @@
 // use rope::*;
 
 use std::str;
+use std::borrow::Cow;
 use std::cmp::min;
 use std::fmt::{Debug, Display, Formatter};
 use std::marker::PhantomData;
-use std::ops::Range;
-use std::ptr::null_mut;
+use std::ops::{Range, RangeBounds};
+use std::alloc::{handle_alloc_error, Layout};
+use std::ptr::{null_mut, NonNull};
+use std::sync::Arc;
+#[cfg(feature = "cow")]
+use std::mem::ManuallyDrop;
 use rand::prelude::*;
-use rand::Rng;
 use get_size::GetSize;
 use crate::fast_str_tools::*;
 use crate::gapbuffer::GapBuffer;
+#[cfg(feature = "line_conversion")]
+use crate::utils::count_lines;
+use crate::utils::{add_signed, bounds_to_range, find_in_chunks, floor_char_boundary, str_chars_to_bytes};
+use crate::error::RopeError;
+use crate::allocator::{Allocator, Global};
+#[cfg(feature = "marks")]
+use crate::marks::MarkSet;
+#[cfg(feature = "history")]
+use crate::history::{Edit, History};
+#[cfg(feature = "changes")]
+use crate::changes::ChangeEvent;
+#[cfg(feature = "edit_log")]
+use crate::edit_log::{EditLog, LogEntry};
 // use crate::utils::*;
 // use crate::params::*;
 
@@ -31,7 +48,9 @@ use crate::gapbuffer::GapBuffer;
 const BIAS: u8 = 65;
 // const BIAS: u8 = XX_BIAS;
 
-// The rope will become less efficient after the string is 2 ^ ROPE_MAX_HEIGHT nodes.
+// Each level of the skip list has about 1/4 as many nodes as the level below it (BIAS / 256). The
+// head is always one taller than the tallest node, so searches stay logarithmic until the rope
+// has about 4 ^ MAX_HEIGHT (~10^12) nodes - far more than will fit in memory.
 
 #[cfg(debug_assertions)]
 pub(crate) const NODE_STR_SIZE: usize = 10;
@@ -39,7 +58,7 @@ pub(crate) const NODE_STR_SIZE: usize = 10;
 pub(crate) const NODE_STR_SIZE: usize = 392;
 // pub(crate) const NODE_STR_SIZE: usize = XX_SIZE;
 
-const MAX_HEIGHT: usize = 20;//NODE_STR_SIZE / mem::size_of::<SkipEntry>();
+const MAX_HEIGHT: usize = 20;
 const MAX_HEIGHT_U8: u8 = MAX_HEIGHT as u8;
 
 // Using StdRng notably increases wasm code size, providing some tiny extra protection against
@@ -49,26 +68,143 @@ type RopeRng = StdRng;
 #[cfg(not(feature = "ddos_protection"))]
 type RopeRng = SmallRng;
 
+/// The source of randomness used to pick node heights. Custom RNGs are boxed so the rope type
+/// doesn't need another generic parameter. The default RNG is stored inline.
+enum RngSource {
+    Default(RopeRng),
+    Custom(Box<dyn RngCore + Send>),
+}
+
+impl RngSource {
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            RngSource::Default(rng) => rng.next_u64(),
+            RngSource::Custom(rng) => rng.next_u64(),
+        }
+    }
+}
+
+impl Debug for RngSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RngSource::Default(rng) => rng.fmt(f),
+            RngSource::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Hands out random bytes for picking node heights. Most nodes only need one byte, so bytes are
+/// taken 8 at a time from a single u64 instead of asking the RNG for each one.
+#[derive(Debug)]
+struct HeightRng {
+    source: RngSource,
+    /// Unused random bytes, consumed from the low end.
+    bits: u64,
+    num_bytes: u8,
+}
+
+impl HeightRng {
+    fn new(source: RngSource) -> Self {
+        Self { source, bits: 0, num_bytes: 0 }
+    }
+
+    fn gen_u8(&mut self) -> u8 {
+        if self.num_bytes == 0 {
+            self.bits = self.source.next_u64();
+            self.num_bytes = 8;
+        }
+        let byte = self.bits as u8;
+        self.bits >>= 8;
+        self.num_bytes -= 1;
+        byte
+    }
+
+    #[cfg(feature = "rayon")]
+    fn next_u64(&mut self) -> u64 {
+        self.source.next_u64()
+    }
+}
+
 
 // The node structure is designed in a very fancy way which would be more at home in C or something
 // like that. The basic idea is that the node structure is fixed size in memory, but the proportion
 // of that space taken up by characters and by the height are different depentant on a node's
 // height.
+/// A rope whose skip list nodes each store up to `N` bytes of UTF-8 text.
+///
+/// Most programs should use [`JumpRope`], which picks a node size tuned for general text editing.
+/// Smaller nodes make edits cheaper at the cost of more pointer chasing (and more memory overhead)
+/// when seeking. Larger nodes do the reverse. `N` must be at least 4 (so any character fits in a
+/// node) and at most `u16::MAX`.
+///
+/// `A` is the allocator used for the rope's nodes. Other allocators can only be used with the
+/// `allocator-api2` feature. See [`new_in`](JumpRopeSized::new_in).
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRopeSized::<64>::from("hi there");
+/// rope.insert(2, " you");
+/// assert_eq!(rope, "hi you there");
+/// assert_eq!(JumpRopeSized::<64>::max_bytes_per_node(), 64);
+/// ```
 #[repr(C)]
 #[derive(GetSize)]
-pub struct JumpRope {
+#[get_size(ignore(A))]
+pub struct JumpRopeSized<const N: usize, A: Allocator = Global> {
     #[get_size(size = 0)]
-    rng: RopeRng,
+    rng: HeightRng,
     // The total number of characters in the rope
     // num_chars: usize,
 
     // The total number of bytes which the characters in the rope take up
-    num_bytes: usize,
+    pub(super) num_bytes: usize,
 
     // The first node is inline. The height is the max height we've ever used in the rope + 1. The
     // highest entry points "past the end" of the list, including the entire list length.
     // TODO: Get rid of this and just rely on nexts out of here.
-    pub(super) head: Node,
+    pub(super) head: Node<N>,
+
+    // Nodes removed from the list are kept here for reuse, to save trips to the allocator.
+    #[get_size(size = 0)]
+    pub(super) free_list: FreeList<N, A>,
+
+    // Storage for the entries of MutCursor. After an insert or remove, this holds the cursor from
+    // that edit. Edits are usually close to the previous edit, so we can often repair this cursor
+    // instead of searching from the head. cursor_valid must be cleared whenever the rope is
+    // modified through any other path. (Creating a MutCursor does that automatically.)
+    #[get_size(size = 0)]
+    cursor: [SkipEntry<N>; MAX_HEIGHT+1],
+    pub(super) cursor_valid: bool,
+
+    // Positions which are updated by every insert and delete. See marks.rs.
+    #[cfg(feature = "marks")]
+    #[get_size(size = 0)]
+    pub(super) marks: MarkSet,
+
+    // Undo and redo steps, once history is enabled. See history.rs.
+    #[cfg(feature = "history")]
+    #[get_size(size = 0)]
+    pub(super) history: Option<Box<History>>,
+
+    // Changes which haven't been taken yet, once change tracking is enabled. See changes.rs.
+    #[cfg(feature = "changes")]
+    #[get_size(size = 0)]
+    pub(super) changes: Option<Vec<ChangeEvent>>,
+
+    // Every edit made since the edit log was enabled. See edit_log.rs.
+    #[cfg(feature = "edit_log")]
+    #[get_size(size = 0)]
+    pub(super) edit_log: Option<Box<EditLog>>,
+
+    // Whether nodes cache the hash of their content. See content_hash.rs.
+    #[cfg(feature = "content_hash")]
+    pub(super) cache_hashes: bool,
+
+    // Shared by every rope using the nodes after the head, once the rope has been forked. The
+    // nodes are only modified (or freed) by the last rope using them. See cow.rs.
+    #[cfg(feature = "cow")]
+    #[get_size(size = 0)]
+    pub(super) shared: ManuallyDrop<Arc<()>>,
 
     // This is so dirty. The first node is embedded in JumpRope; but we need to allocate enough room
     // for height to get arbitrarily large. I could insist on JumpRope always getting allocated on
@@ -80,33 +216,48 @@ pub struct JumpRope {
     // list. The size is the size of the entire list.
 }
 
+/// The rope type most programs should use. Node size is tuned for general text editing
+/// workloads. (It is much smaller in debug builds to exercise node splitting in tests.)
+pub type JumpRope = JumpRopeSized<NODE_STR_SIZE>;
+
 /// JumpRope is Send and Sync, because the only way to (safely) mutate the rope is via a &mut
 /// reference.
-unsafe impl Send for JumpRope {}
-unsafe impl Sync for JumpRope {}
+unsafe impl<const N: usize, A: Allocator + Send> Send for JumpRopeSized<N, A> {}
+unsafe impl<const N: usize, A: Allocator + Sync> Sync for JumpRopeSized<N, A> {}
 
+// I tried aligning nodes to cache lines (repr(align(64))) and issuing a software prefetch for the
+// next node while seeking. Neither made a measurable difference on x86_64, either replaying the
+// editing traces or doing random reads and edits in a 10MB document: the differences were
+// smaller than the run to run noise. So the layout is left to the compiler.
 #[derive(GetSize)]
-pub(super) struct Node {
+pub(super) struct Node<const N: usize> {
     // The first num_bytes of this store a valid utf8 string.
     // str: [u8; NODE_STR_SIZE],
     //
     // // Number of bytes in str in use
     // num_bytes: u8,
-    pub(super) str: GapBuffer<NODE_STR_SIZE>,
+    pub(super) str: GapBuffer<N>,
 
     // Height of nexts array.
     pub(super) height: u8,
 
+    // The previous node at level 0. This costs 8 bytes per node (about 1% of a node in release
+    // builds), but it lets us walk the list backwards. Edit performance is unchanged. It's null
+    // for the first node after the head. (The head is stored inline in the rope and moves
+    // whenever the rope is moved, so nodes can't hold pointers to it.)
+    #[get_size(size = 0)]
+    pub(super) prev: *mut Node<N>,
+
     // #[repr(align(std::align_of::<SkipEntry>()))]
 
     // Only the first height items are used in this. Earlier versions made explicit allocator calls
     // to reduce memory usage, but that makes miri quite sad, so I'm now just wasting some memory
     // in each nexts[] array.
     #[get_size(size_fn = next_node_size)]
-    nexts: [SkipEntry; MAX_HEIGHT+1],
+    nexts: [SkipEntry<N>; MAX_HEIGHT+1],
 }
 
-fn next_node_size(nexts: &[SkipEntry; MAX_HEIGHT+1]) -> usize {
+fn next_node_size<const N: usize>(nexts: &[SkipEntry<N>; MAX_HEIGHT+1]) -> usize {
     match unsafe {nexts[0].node.as_ref()} {
         Some(n) => n.get_size(),
         None => 0,
@@ -114,74 +265,352 @@ fn next_node_size(nexts: &[SkipEntry; MAX_HEIGHT+1]) -> usize {
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub(super) struct SkipEntry {
-    pub(super) node: *mut Node,
+pub(super) struct SkipEntry<const N: usize> {
+    pub(super) node: *mut Node<N>,
     /// The number of *characters* between the start of the current node and the start of the next
     /// node.
     pub(super) skip_chars: usize,
 
     #[cfg(feature = "wchar_conversion")]
     pub(super) skip_pairs: usize,
+
+    /// The number of newlines between the start of the current node and the start of the next
+    /// node.
+    #[cfg(feature = "line_conversion")]
+    pub(super) skip_lines: usize,
+
+    /// The number of bytes between the start of the current node and the start of the next node.
+    /// This is only tracked for change events, which need the byte position of every edit.
+    #[cfg(feature = "changes")]
+    pub(super) skip_bytes: usize,
+}
+
+/// A saved copy of a rope's content, made by [`JumpRopeSized::checkpoint`]. Pass it to
+/// [`JumpRopeSized::restore`] to put the content back.
+///
+/// Checkpoints can also be read, and cloning one is cheap (*O(1)*), so they can be kept in a
+/// stack (like an undo stack) or passed between threads freely.
+///
+/// Without the `cow` feature, a checkpoint stores the content as a single string, and restoring
+/// one refills the rope's existing nodes with it. Both take *O(n)* time. With `cow`, a checkpoint
+/// is a fork of the rope which shares its nodes. Taking one is *O(1)*, and restoring one swaps its
+/// nodes back into the rope without copying any content. (The next edit to a rope whose nodes
+/// are shared with a checkpoint copies them. See `rope.fork()`.)
+pub struct Checkpoint<const N: usize = NODE_STR_SIZE, A: Allocator = Global> {
+    #[cfg(not(feature = "cow"))]
+    content: Arc<str>,
+    #[cfg(not(feature = "cow"))]
+    len_chars: usize,
+    #[cfg(not(feature = "cow"))]
+    phantom: PhantomData<fn() -> A>,
+
+    #[cfg(feature = "cow")]
+    rope: JumpRopeSized<N, A>,
+}
+
+/// A checkpoint taken to be read, rather than restored. Made by [`JumpRopeSized::snapshot`].
+pub type RopeSnapshot<const N: usize = NODE_STR_SIZE, A = Global> = Checkpoint<N, A>;
+
+impl<const N: usize, A: Allocator> Checkpoint<N, A> {
+    /// The length of the saved content in unicode characters.
+    pub fn len_chars(&self) -> usize {
+        #[cfg(not(feature = "cow"))] { self.len_chars }
+        #[cfg(feature = "cow")] { self.rope.len_chars() }
+    }
+
+    /// The length of the saved content in bytes.
+    pub fn len_bytes(&self) -> usize {
+        #[cfg(not(feature = "cow"))] { self.content.len() }
+        #[cfg(feature = "cow")] { self.rope.len_bytes() }
+    }
+
+    /// Returns `true` if the saved content is empty.
+    pub fn is_empty(&self) -> bool { self.len_bytes() == 0 }
+
+    /// Iterate through the saved content in chunks. Like [`JumpRopeSized::substrings`], how the
+    /// content is split into chunks is an implementation detail.
+    pub fn substrings(&self) -> impl Iterator<Item = &str> + '_ {
+        #[cfg(not(feature = "cow"))] { std::iter::once(&*self.content).filter(|s| !s.is_empty()) }
+        #[cfg(feature = "cow")] { self.rope.substrings() }
+    }
+
+    /// Iterate through the characters in the saved content.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.substrings().flat_map(str::chars)
+    }
+}
+
+impl<const N: usize, A: Allocator + Clone> Clone for Checkpoint<N, A> {
+    fn clone(&self) -> Self {
+        #[cfg(not(feature = "cow"))] {
+            Checkpoint { content: self.content.clone(), len_chars: self.len_chars, phantom: PhantomData }
+        }
+        #[cfg(feature = "cow")] {
+            Checkpoint { rope: self.rope.share() }
+        }
+    }
+}
+
+impl<const N: usize, A: Allocator> Debug for Checkpoint<N, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Checkpoint").field(&self.to_string()).finish()
+    }
+}
+
+impl<const N: usize, A: Allocator> Display for Checkpoint<N, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for s in self.substrings() {
+            f.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsRef<str>, const N: usize, A: Allocator> PartialEq<T> for Checkpoint<N, A> {
+    fn eq(&self, other: &T) -> bool {
+        #[cfg(not(feature = "cow"))] { *self.content == *other.as_ref() }
+        #[cfg(feature = "cow")] { self.rope.eq_str(other.as_ref()) }
+    }
+}
+
+/// Statistics about the internal structure of a rope, returned by
+/// [`JumpRopeSized::stats`].
+///
+/// The first node of the rope is stored inline in the rope itself. It is counted like any other
+/// node, except for the height histogram. (Its height is the height of the whole rope.)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RopeStats {
+    /// The number of nodes storing the rope's content.
+    pub num_nodes: usize,
+    /// The number of unused nodes kept around for reuse by later inserts.
+    pub num_free_nodes: usize,
+    /// The total number of bytes of text the nodes could store. Each node stores up to
+    /// [`max_bytes_per_node()`](JumpRopeSized::max_bytes_per_node) bytes on every target. (Only
+    /// the size of each node's skip list pointers depends on the target's pointer width, which
+    /// changes [`mem_usage()`](JumpRopeSized::mem_usage) but not the capacity.)
+    pub capacity_bytes: usize,
+    /// The number of bytes of text actually stored. This is the same as `len_bytes()`.
+    pub used_bytes: usize,
+    /// `used_bytes / capacity_bytes`.
+    pub fill_factor: f64,
+    /// `height_histogram[h]` is the number of nodes of height `h + 1`.
+    pub height_histogram: [usize; MAX_HEIGHT],
+}
+
+/// The maximum number of unused nodes each rope keeps around for reuse.
+const MAX_FREE_NODES: usize = 64;
+
+/// A singly linked stack of unused nodes, chained through nexts[0]. All nodes have the same size
+/// in memory regardless of their height, so any free node can be reused for any insert.
+#[derive(Debug)]
+pub(super) struct FreeList<const N: usize, A: Allocator> {
+    head: *mut Node<N>,
+    len: usize,
+    // Every node in the rope (not just the ones in the free list) is allocated and freed here.
+    alloc: A,
+}
+
+impl<const N: usize, A: Allocator> FreeList<N, A> {
+    fn new(alloc: A) -> Self {
+        Self { head: null_mut(), len: 0, alloc }
+    }
+
+    fn alloc_node(&self, node: Node<N>) -> *mut Node<N> {
+        let layout = Layout::new::<Node<N>>();
+        let ptr = match self.alloc.allocate(layout) {
+            Ok(ptr) => ptr.cast::<Node<N>>().as_ptr(),
+            Err(_) => handle_alloc_error(layout),
+        };
+        unsafe { ptr.write(node); }
+        ptr
+    }
+
+    /// Safety: The node must have been created by alloc_node() and must no longer be reachable.
+    pub(super) unsafe fn dealloc_node(&self, node: *mut Node<N>) {
+        std::ptr::drop_in_place(node);
+        self.alloc.deallocate(NonNull::new_unchecked(node).cast(), Layout::new::<Node<N>>());
+    }
+
+    fn alloc(&mut self, height: u8, content: &str) -> *mut Node<N> {
+        let node = self.head;
+        if node.is_null() {
+            self.alloc_node(Node::new_with_height(height, content))
+        } else {
+            unsafe {
+                self.head = (*node).nexts[0].node;
+                self.len -= 1;
+                *node = Node::new_with_height(height, content);
+            }
+            node
+        }
+    }
+
+    /// Safety: The node must have been created by alloc() and must no longer be reachable.
+    unsafe fn free(&mut self, node: *mut Node<N>) {
+        if self.len < MAX_FREE_NODES {
+            (*node).nexts[0].node = self.head;
+            self.head = node;
+            self.len += 1;
+        } else {
+            self.dealloc_node(node);
+        }
+    }
+
+    fn release_all(&mut self) {
+        let mut node = self.head;
+        while !node.is_null() {
+            unsafe {
+                let next = (*node).nexts[0].node;
+                self.dealloc_node(node);
+                node = next;
+            }
+        }
+        self.head = null_mut();
+        self.len = 0;
+    }
 }
 
 // Make sure nexts uses correct alignment. This should be guaranteed by repr(C)
 // This test will fail if this ever stops being true.
 #[test]
 fn test_align() {
-    #[repr(C)] struct Check([SkipEntry; 0]);
-    assert!(std::mem::align_of::<Check>() >= std::mem::align_of::<SkipEntry>());
+    #[repr(C)] struct Check([SkipEntry<NODE_STR_SIZE>; 0]);
+    assert!(std::mem::align_of::<Check>() >= std::mem::align_of::<SkipEntry<NODE_STR_SIZE>>());
+}
+
+// The checks while walking the skip list are only debug asserts. Make sure they still catch a
+// corrupted list.
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Reached rope end prematurely")]
+fn test_traversal_catches_corruption() {
+    let mut rope = JumpRopeSized::<16>::from("abcdefghij".repeat(100));
+    let top = rope.head.height as usize - 1;
+    assert!(top > 0);
+    // Claim the rope has more characters than its nodes do.
+    rope.head.nexts[top].skip_chars += 5;
+    let len = rope.len_chars();
+    rope.read_cursor_at_char(len, true);
+}
+
+#[test]
+fn test_recompute_len() {
+    let mut rope = JumpRopeSized::<16>::from("κόσμε ↯ 𐆚\n".repeat(50));
+    let expected = rope.to_string();
+    rope.recompute_len();
+    rope.check();
+
+    // Scramble the cached counts everywhere.
+    rope.num_bytes += 3;
+    let head_height = rope.head.height as usize;
+    for e in &mut rope.head.nexts[..head_height] { e.skip_chars += 7; }
+    unsafe {
+        let mut node = rope.head.nexts[0].node;
+        while !node.is_null() {
+            for e in (*node).nexts_mut() {
+                e.skip_chars = 1;
+                #[cfg(feature = "wchar_conversion")] { e.skip_pairs += 2; }
+                #[cfg(feature = "line_conversion")] { e.skip_lines = 0; }
+                #[cfg(feature = "changes")] { e.skip_bytes = 5; }
+            }
+            node = (*node).nexts[0].node;
+        }
+    }
+    assert_ne!(rope.len_chars(), expected.chars().count());
+
+    rope.recompute_len();
+    rope.check();
+    assert_eq!(rope, expected.as_str());
+    assert_eq!(rope.len_bytes(), expected.len());
+
+    // Edits still work afterwards.
+    rope.insert(100, "hi");
+    rope.remove(0..300);
+    rope.check();
 }
 
-fn random_height(rng: &mut RopeRng) -> u8 {
+fn random_height(rng: &mut HeightRng) -> u8 {
     let mut h: u8 = 1;
     // TODO: This is using the thread_local rng, which is secure (?!). Check
     // this is actually fast.
-    while h < MAX_HEIGHT_U8 && rng.gen::<u8>() < BIAS { h+=1; }
+    while h < MAX_HEIGHT_U8 && rng.gen_u8() < BIAS { h+=1; }
     h
 }
 
-impl SkipEntry {
+// Heights should be geometrically distributed, with each height BIAS / 256 times as likely as the
+// one below it. This is a chi-squared test against that distribution.
+#[test]
+#[cfg_attr(miri, ignore)] // Far too slow under miri.
+fn test_height_distribution() {
+    const SAMPLES: usize = 200_000;
+    // Heights 1 to 6, and everything taller.
+    const BUCKETS: usize = 7;
+
+    let mut rng = HeightRng::new(RngSource::Default(RopeRng::seed_from_u64(10)));
+    let mut counts = [0usize; BUCKETS];
+    for _ in 0..SAMPLES {
+        let h = random_height(&mut rng) as usize;
+        counts[h.min(BUCKETS) - 1] += 1;
+    }
+
+    let p = BIAS as f64 / 256.0;
+    let mut chi_squared = 0.0;
+    for (i, &count) in counts.iter().enumerate() {
+        let prob = if i + 1 < BUCKETS { p.powi(i as i32) * (1.0 - p) } else { p.powi(i as i32) };
+        let expected = prob * SAMPLES as f64;
+        chi_squared += (count as f64 - expected).powi(2) / expected;
+    }
+    // The 99.9th percentile of the chi-squared distribution with 6 degrees of freedom.
+    assert!(chi_squared < 22.46, "heights are not geometric: {:?} (chi squared {})", counts, chi_squared);
+}
+
+impl<const N: usize> SkipEntry<N> {
     fn new() -> Self {
         SkipEntry {
             node: null_mut(),
             skip_chars: 0,
             #[cfg(feature = "wchar_conversion")]
-            skip_pairs: 0
+            skip_pairs: 0,
+            #[cfg(feature = "line_conversion")]
+            skip_lines: 0,
+            #[cfg(feature = "changes")]
+            skip_bytes: 0,
         }
     }
 }
 
-impl Default for SkipEntry {
+impl<const N: usize> Default for SkipEntry<N> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Node {
+impl<const N: usize> Node<N> {
     pub(super) fn next_ptr(&self) -> *const Self { // TODO: Pin.
         self.first_next().node
     }
 
     // Do I need to be explicit about the lifetime of the references being tied
     // to the lifetime of the node?
-    fn nexts(&self) -> &[SkipEntry] {
+    fn nexts(&self) -> &[SkipEntry<N>] {
         &self.nexts[..self.height as usize]
         // unsafe {
         //     std::slice::from_raw_parts(self.nexts.as_ptr(), self.height as usize)
         // }
     }
 
-    fn nexts_mut(&mut self) -> &mut [SkipEntry] {
+    fn nexts_mut(&mut self) -> &mut [SkipEntry<N>] {
         &mut self.nexts[..self.height as usize]
         // unsafe {
         //     std::slice::from_raw_parts_mut(self.nexts.as_mut_ptr(), self.height as usize)
         // }
     }
 
-    fn new_with_height(height: u8, content: &str) -> Self {
+    pub(super) fn new_with_height(height: u8, content: &str) -> Self {
         Self {
             str: GapBuffer::new_from_str(content),
             height,
+            prev: null_mut(),
             nexts: [SkipEntry::default(); MAX_HEIGHT+1]
         }
     }
@@ -233,11 +662,11 @@ impl Node {
     }
 
     // The height is at least 1, so this is always valid.
-    pub(super) fn first_next(&self) -> &SkipEntry {
+    pub(super) fn first_next(&self) -> &SkipEntry<N> {
         unsafe { &*self.nexts.as_ptr() }
     }
 
-    fn first_next_mut(&mut self) -> &mut SkipEntry {
+    fn first_next_mut(&mut self) -> &mut SkipEntry<N> {
         unsafe { &mut *self.nexts.as_mut_ptr() }
     }
 
@@ -249,6 +678,25 @@ impl Node {
     pub(super) fn num_surrogate_pairs(&self) -> usize {
         self.first_next().skip_pairs
     }
+
+    #[cfg(feature = "line_conversion")]
+    pub(super) fn num_lines(&self) -> usize {
+        self.first_next().skip_lines
+    }
+
+    /// Walk down from the top of this node's tower to find the last node in the list. (Returns self
+    /// if nothing comes after it.)
+    pub(super) fn last_node(&self) -> &Self {
+        let mut n = self;
+        for h in (0..self.height as usize).rev() {
+            // Only the head is guaranteed to be tall enough, but towers we step onto are at least
+            // as tall as the level we stepped on at.
+            while let Some(next) = unsafe { n.nexts[h].node.as_ref() } {
+                n = next;
+            }
+        }
+        n
+    }
 }
 
 /// Cursors are a bit weird, and they deserve an explanation.
@@ -265,19 +713,28 @@ impl Node {
 /// A cursor also implicitly references a &mut JumpRope. So we store some "deep pointers" in to
 /// the jumprope itself so the jumprope reference can stay unused while the cursor is live.
 #[derive(Debug)]
-pub(super) struct MutCursor<'a> {
-    inner: [SkipEntry; MAX_HEIGHT+1],
+pub(super) struct MutCursor<'a, const N: usize, A: Allocator> {
+    inner: &'a mut [SkipEntry<N>; MAX_HEIGHT+1],
 
     // head_nexts: &'a mut [SkipEntry; MAX_HEIGHT+1],
 
     // head_height: &'a mut u8,
-    rng: &'a mut RopeRng,
+    rng: &'a mut HeightRng,
     num_bytes: &'a mut usize,
-
-    phantom: PhantomData<&'a mut JumpRope>,
+    free_list: &'a mut FreeList<N, A>,
+    #[cfg(feature = "marks")]
+    marks: &'a mut MarkSet,
+    #[cfg(feature = "history")]
+    history: &'a mut Option<Box<History>>,
+    #[cfg(feature = "changes")]
+    changes: &'a mut Option<Vec<ChangeEvent>>,
+    #[cfg(feature = "edit_log")]
+    edit_log: &'a mut Option<Box<EditLog>>,
+
+    phantom: PhantomData<&'a mut JumpRopeSized<N, A>>,
 }
 
-impl<'a> MutCursor<'a> {
+impl<'a, const N: usize, A: Allocator> MutCursor<'a, N, A> {
     fn head_height_u8(&self) -> u8 {
         unsafe {
             (*self.inner[MAX_HEIGHT].node).height
@@ -294,41 +751,59 @@ impl<'a> MutCursor<'a> {
         }
     }
 
-    fn is_head(&self, ptr: *const Node) -> bool {
+    fn is_head(&self, ptr: *const Node<N>) -> bool {
         std::ptr::eq(ptr, self.inner[MAX_HEIGHT].node)
     }
 
-    fn update_offsets(&mut self, height: usize, by_chars: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize) {
+    /// Set the level 0 back pointer of node to prev. Does nothing if node is null (the end of the
+    /// list).
+    unsafe fn set_prev(&self, node: *mut Node<N>, prev: *mut Node<N>) {
+        if !node.is_null() {
+            (*node).prev = if self.is_head(prev) { null_mut() } else { prev };
+        }
+    }
+
+    fn update_offsets(&mut self, height: usize, by_chars: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize, #[cfg(feature = "line_conversion")] by_lines: isize, #[cfg(feature = "changes")] by_bytes: isize) {
         for i in 0..height {
             unsafe {
                 // This is weird but makes sense when you realise the nexts in
                 // the cursor are pointers into the elements that have the
                 // actual pointers.
-
-                // Also adding a usize + isize is awful in rust :/
                 let entry = &mut (*self.inner[i].node).nexts[i];
-                entry.skip_chars = entry.skip_chars.wrapping_add(by_chars as usize);
+                entry.skip_chars = add_signed(entry.skip_chars, by_chars);
                 #[cfg(feature = "wchar_conversion")] {
-                    entry.skip_pairs = entry.skip_pairs.wrapping_add(by_pairs as usize);
+                    entry.skip_pairs = add_signed(entry.skip_pairs, by_pairs);
+                }
+                #[cfg(feature = "line_conversion")] {
+                    entry.skip_lines = add_signed(entry.skip_lines, by_lines);
+                }
+                #[cfg(feature = "changes")] {
+                    entry.skip_bytes = add_signed(entry.skip_bytes, by_bytes);
                 }
             }
         }
     }
 
-    fn move_within_node(&mut self, height: usize, by_chars: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize) {
+    fn move_within_node(&mut self, height: usize, by_chars: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize, #[cfg(feature = "line_conversion")] by_lines: isize, #[cfg(feature = "changes")] by_bytes: isize) {
         for e in &mut self.inner[..height] {
-            e.skip_chars = e.skip_chars.wrapping_add(by_chars as usize);
+            e.skip_chars = add_signed(e.skip_chars, by_chars);
             #[cfg(feature = "wchar_conversion")] {
-                e.skip_pairs = e.skip_pairs.wrapping_add(by_pairs as usize);
+                e.skip_pairs = add_signed(e.skip_pairs, by_pairs);
+            }
+            #[cfg(feature = "line_conversion")] {
+                e.skip_lines = add_signed(e.skip_lines, by_lines);
+            }
+            #[cfg(feature = "changes")] {
+                e.skip_bytes = add_signed(e.skip_bytes, by_bytes);
             }
         }
     }
 
-    pub(crate) fn here_ptr(&self) -> *mut Node {
+    pub(crate) fn here_ptr(&self) -> *mut Node<N> {
         self.inner[0].node
     }
 
-    pub(crate) fn here_mut_ptr(&mut self) -> *mut Node {
+    pub(crate) fn here_mut_ptr(&mut self) -> *mut Node<N> {
         self.inner[0].node
     }
 
@@ -342,13 +817,18 @@ impl<'a> MutCursor<'a> {
         entry.skip_chars + entry.skip_pairs
     }
 
+    #[cfg(feature = "changes")]
+    pub(crate) fn global_byte_pos(&self) -> usize {
+        self.inner[self.head_height() - 1].skip_bytes
+    }
+
     pub(crate) fn local_char_pos(&self) -> usize {
         self.inner[0].skip_chars
     }
 }
 
-pub(crate) struct ReadCursor<'a> {
-    pub(super) node: &'a Node,
+pub(crate) struct ReadCursor<'a, const N: usize> {
+    pub(super) node: &'a Node<N>,
 
     /// The number of *characters* between the start of the current node and the start of the next
     /// node.
@@ -362,7 +842,7 @@ pub(crate) struct ReadCursor<'a> {
     #[cfg(feature = "wchar_conversion")]
     global_pairs: usize,
 
-    phantom: PhantomData<&'a JumpRope>
+    phantom: PhantomData<&'a JumpRopeSized<N>>
 }
 
 // impl ReadCursor {
@@ -371,9 +851,21 @@ pub(crate) struct ReadCursor<'a> {
 
 /// A rope is a "rich string" data structure for storing fancy strings, like the contents of a
 /// text editor. See module level documentation for more information.
-impl JumpRope {
-    fn new_with_rng(rng: RopeRng) -> Self {
-        JumpRope {
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    // Evaluated at compile time for each N used. Gap buffer offsets are stored in u16s, and every
+    // node must be able to hold at least one (4 byte) character.
+    const VALID_NODE_SIZE: () = assert!(N >= 4 && N <= u16::MAX as usize,
+        "JumpRopeSized node size must be between 4 and 65535 bytes");
+
+    // After a delete, nodes with fewer bytes than this are merged with the following node (if
+    // the contents fit).
+    const UNDERFULL_BYTES: usize = N / 4;
+
+    fn new_with_rng_in(rng: HeightRng, alloc: A) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID_NODE_SIZE;
+
+        JumpRopeSized {
             rng,
             num_bytes: 0,
             // nexts: [SkipEntry::new(); MAX_HEIGHT],
@@ -381,6 +873,21 @@ impl JumpRope {
             // We don't ever store characters in the head node, but the height
             // here is the maximum height of the entire rope.
             head: Node::new_with_height(1, ""),
+            free_list: FreeList::new(alloc),
+            cursor: [SkipEntry::new(); MAX_HEIGHT+1],
+            cursor_valid: false,
+            #[cfg(feature = "marks")]
+            marks: MarkSet::default(),
+            #[cfg(feature = "history")]
+            history: None,
+            #[cfg(feature = "changes")]
+            changes: None,
+            #[cfg(feature = "edit_log")]
+            edit_log: None,
+            #[cfg(feature = "content_hash")]
+            cache_hashes: false,
+            #[cfg(feature = "cow")]
+            shared: ManuallyDrop::new(Arc::new(())),
             // head: Node {
             //     str: GapBuffer::new(),
             //     height: 1,
@@ -390,6 +897,26 @@ impl JumpRope {
         }
     }
 
+    /// Creates a new, empty rope which stores its nodes in the passed allocator. Node heights are
+    /// picked the same way as [`new`](JumpRopeSized::new).
+    ///
+    /// Methods which move nodes between ropes (like [`append`](JumpRopeSized::append)) are only
+    /// available for ropes using the global allocator.
+    pub fn new_in(alloc: A) -> Self {
+        let rng = if cfg!(test) || cfg!(debug_assertions) || !cfg!(feature = "ddos_protection") {
+            RopeRng::seed_from_u64(123)
+        } else {
+            RopeRng::from_entropy()
+        };
+        Self::new_with_rng_in(HeightRng::new(RngSource::Default(rng)), alloc)
+    }
+}
+
+impl<const N: usize> JumpRopeSized<N> {
+    fn new_with_rng(rng: HeightRng) -> Self {
+        Self::new_with_rng_in(rng, Global)
+    }
+
     /// Creates and returns a new, empty rope.
     ///
     /// In release mode this method is an alias for [`new_from_entropy`](Self::new_from_entropy).
@@ -399,16 +926,12 @@ impl JumpRope {
     /// Note using this method in wasm significantly increases bundle size. Use
     /// [`new_with_seed`](Self::new_from_seed) instead.
     pub fn new() -> Self {
-        if cfg!(test) || cfg!(debug_assertions) || !cfg!(feature = "ddos_protection") {
-            Self::new_from_seed(123)
-        } else {
-            Self::new_from_entropy()
-        }
+        Self::new_in(Global)
     }
 
     /// Creates a new, empty rope seeded from an entropy source.
     pub fn new_from_entropy() -> Self {
-        Self::new_with_rng(RopeRng::from_entropy())
+        Self::new_with_rng(HeightRng::new(RngSource::Default(RopeRng::from_entropy())))
     }
 
     /// Creates a new, empty rope using an RNG seeded from the passed u64 parameter.
@@ -419,7 +942,29 @@ impl JumpRope {
     ///
     /// - Jumprope will always use a fixed seed
     pub fn new_from_seed(seed: u64) -> Self {
-        Self::new_with_rng(RopeRng::seed_from_u64(seed))
+        Self::new_with_rng(HeightRng::new(RngSource::Default(RopeRng::seed_from_u64(seed))))
+    }
+
+    /// Creates a new, empty rope which uses the passed RNG to pick the height of each node.
+    ///
+    /// This is mostly useful for testing and fuzzing with controlled entropy. The RNG is boxed, so
+    /// picking heights is slightly slower than with the built in RNG. A poor RNG will make the
+    /// rope slow, but it will never make it incorrect.
+    ///
+    /// Note that cloning the rope does not clone the RNG. Clones use [`new`](Self::new).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use rand::rngs::mock::StepRng;
+    /// // This RNG always returns u64::MAX, so every node has a height of 1.
+    /// let mut rope = JumpRopeSized::<16>::with_rng(StepRng::new(u64::MAX, 0));
+    /// rope.insert(0, &"hi there ".repeat(10));
+    /// assert_eq!(rope.current_height(), 1);
+    /// ```
+    pub fn with_rng<R: RngCore + Send + 'static>(rng: R) -> Self {
+        Self::new_with_rng(HeightRng::new(RngSource::Custom(Box::new(rng))))
     }
 
     fn new_from_str(s: &str) -> Self {
@@ -428,6 +973,285 @@ impl JumpRope {
         rope
     }
 
+    /// Creates a new rope from a string, building separate parts of the rope on different threads
+    /// and then joining them together. The result contains exactly the same content as
+    /// `JumpRope::from(s)`, but it's much faster to create for large strings on a multicore
+    /// machine.
+    ///
+    /// The string is split into one segment per thread in rayon's current thread pool. Strings
+    /// too small to benefit from splitting are loaded on the current thread.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let content = "hi there\n".repeat(100_000);
+    /// let rope = JumpRope::from_str_parallel(&content);
+    /// assert_eq!(rope, content);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn from_str_parallel(s: &str) -> Self {
+        use rayon::prelude::*;
+
+        // Building ropes with fewer than this many nodes isn't worth a trip to another thread.
+        let min_segment_bytes = N * 1024;
+        let segment_bytes = (s.len() / rayon::current_num_threads()).max(min_segment_bytes);
+
+        let mut segments = Vec::new();
+        let mut remainder = s;
+        while !remainder.is_empty() {
+            let (segment, rem) = remainder.split_at(floor_char_boundary(remainder, segment_bytes));
+            segments.push(segment);
+            remainder = rem;
+        }
+
+        let mut rope = Self::new();
+        // Each part gets its own seed, so the parts don't all end up with the same node heights.
+        let seed = rope.rng.next_u64();
+        let parts: Vec<Self> = segments.into_par_iter().enumerate()
+            .map(|(i, segment)| {
+                let mut part = Self::new_from_seed(seed.wrapping_add(i as u64));
+                part.insert(0, segment);
+                part
+            })
+            .collect();
+
+        for part in parts {
+            rope.append(part);
+        }
+        rope
+    }
+
+    /// Moves all the content of `other` onto the end of this rope. The nodes in `other` are linked
+    /// into this rope as they are, rather than copied. So apart from the first few bytes of
+    /// `other` (which are stored inline), this takes *O(log n)* time no matter how big either rope
+    /// is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi ");
+    /// rope.append(JumpRope::from("there"));
+    /// assert_eq!(rope, "hi there");
+    /// ```
+    pub fn append(&mut self, mut other: Self) {
+        if other.is_empty() { return; }
+        #[cfg(feature = "cow")] {
+            self.make_unique();
+            other.make_unique();
+        }
+        // Most of other's content is spliced in without going through insert, so the marks,
+        // history and changes are updated all at once at the end.
+        #[cfg(any(feature = "marks", feature = "history", feature = "changes", feature = "edit_log"))]
+        let len = self.len_chars();
+        #[cfg(feature = "marks")]
+        let mut marks = std::mem::take(&mut self.marks);
+        #[cfg(feature = "history")]
+        let history = self.history.take();
+        #[cfg(feature = "changes")]
+        let (changes, len_bytes) = (self.changes.take(), self.num_bytes);
+        #[cfg(feature = "edit_log")]
+        let (edit_log, appended) = match self.edit_log.take() {
+            Some(log) => (Some(log), other.to_string()),
+            None => (None, String::new()),
+        };
+
+        // The first node of other is stored inline in other itself, so it can't be moved across.
+        // Copy its content instead.
+        let pos = self.len_chars();
+        self.insert(pos, other.head.as_str_1());
+        let pos = self.len_chars();
+        self.insert(pos, other.head.as_str_2());
+
+        // The head must be taller than every node in the list.
+        let other_height = other.head.height as usize;
+        for h in self.head.height as usize..other_height {
+            self.head.nexts[h] = self.head.nexts[h - 1];
+            self.head.height += 1;
+        }
+
+        let cursor = self.mut_cursor_at_end();
+        // Skip entries in other's head count the content of the head itself. That content is now
+        // before the join.
+        let copied = other.head.nexts[0];
+        let total = other.head.nexts[other_height - 1];
+        for i in 0..cursor.head_height() {
+            let next = if i < other_height { other.head.nexts[i] } else { total };
+            unsafe {
+                let entry = &mut (*cursor.inner[i].node).nexts[i];
+                debug_assert!(entry.node.is_null());
+                *entry = SkipEntry {
+                    node: if i < other_height { next.node } else { null_mut() },
+                    skip_chars: entry.skip_chars + next.skip_chars - copied.skip_chars,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: entry.skip_pairs + next.skip_pairs - copied.skip_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: entry.skip_lines + next.skip_lines - copied.skip_lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: entry.skip_bytes + next.skip_bytes - copied.skip_bytes,
+                };
+            }
+        }
+        unsafe { cursor.set_prev(other.head.nexts[0].node, cursor.inner[0].node); }
+        *cursor.num_bytes += other.num_bytes - other.head.str.len_bytes();
+
+        // The nodes belong to this rope now.
+        for entry in &mut other.head.nexts {
+            entry.node = null_mut();
+        }
+
+        #[cfg(feature = "marks")] {
+            marks.insert(len, other.len_chars());
+            self.marks = marks;
+        }
+        #[cfg(feature = "history")] {
+            self.history = history;
+            self.record_edit(Edit::Insert { pos: len, len: other.len_chars() });
+        }
+        #[cfg(feature = "changes")] {
+            self.changes = changes;
+            self.record_change(ChangeEvent {
+                char_pos: len, chars_removed: 0, chars_inserted: other.len_chars(),
+                byte_pos: len_bytes, bytes_removed: 0, bytes_inserted: other.len_bytes(),
+            });
+        }
+        #[cfg(feature = "edit_log")] {
+            self.edit_log = edit_log;
+            self.log_edit(len, String::new(), appended);
+        }
+    }
+
+    /// Returns a new rope containing this rope's content repeated `n` times, like [`str::repeat`].
+    /// The result is built by repeatedly appending a rope to a copy of itself, so this only needs
+    /// *O(log n)* appends.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("ab");
+    /// assert_eq!(rope.repeat(3), "ababab");
+    /// ```
+    pub fn repeat(&self, mut n: usize) -> Self {
+        let mut result = Self::new();
+        if self.is_empty() { return result; }
+
+        let mut doubled = self.clone();
+        while n > 0 {
+            if n & 1 == 1 { result.append(doubled.clone()); }
+            n >>= 1;
+            if n > 0 { doubled.append(doubled.clone()); }
+        }
+        result
+    }
+
+    /// Consume the rope, splitting it in two at the given unicode character offset. The second
+    /// rope takes over all the nodes after the split from this one, so only the content of the
+    /// node containing the split point is copied. Returns an error if the position is past the
+    /// end of the rope.
+    ///
+    /// This walks the nodes given to the second rope to count their bytes, so it takes time
+    /// proportional to the length of the second half.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi there");
+    /// let (left, right) = rope.split_at_char(3).unwrap();
+    /// assert_eq!(left, "hi ");
+    /// assert_eq!(right, "there");
+    /// ```
+    pub fn split_at_char(mut self, char_pos: usize) -> Result<(Self, Self), RopeError> {
+        let len = self.len_chars();
+        if char_pos > len {
+            return Err(RopeError::PositionOutOfBounds { pos: char_pos, len });
+        }
+
+        let mut right = Self::new();
+        if char_pos == len { return Ok((self, right)); }
+        // Marks and history stay in the first rope.
+        #[cfg(feature = "marks")]
+        self.marks.remove(char_pos..len);
+        #[cfg(feature = "history")]
+        if self.history.is_some() {
+            let text = self.slice_chars(char_pos..len).collect();
+            self.record_edit(Edit::Delete { pos: char_pos, text });
+        }
+        #[cfg(feature = "edit_log")]
+        if self.edit_log.is_some() {
+            let text = self.slice_chars(char_pos..len).collect();
+            self.log_edit(char_pos, text, String::new());
+        }
+        #[cfg(feature = "changes")]
+        let len_bytes = self.num_bytes;
+
+        self.mut_cursor_at_char(char_pos, true);
+        let mut cursor = self.cursor;
+        // Pointers to the head came from an earlier borrow of the rope. Replace them so we don't
+        // write through stale pointers.
+        let head: *mut Node<N> = &mut self.head;
+        for e in &mut cursor {
+            if std::ptr::eq(e.node, head) { e.node = head; }
+        }
+
+        unsafe {
+            let node = cursor[0].node;
+            let content = (*node).str.to_string();
+            let tail = &content[str_chars_to_bytes(&content, cursor[0].skip_chars)..];
+            let tail_chars = count_chars(tail);
+            #[cfg(feature = "wchar_conversion")]
+            let tail_pairs = count_utf16_surrogates(tail);
+            #[cfg(feature = "line_conversion")]
+            let tail_lines = count_lines(tail);
+            #[cfg(feature = "changes")]
+            let tail_bytes = tail.len();
+
+            // Entries in the cursor point past the split. Those move to the second rope, and the
+            // first rope now ends at the split. The rest of the split node will be inserted into
+            // the second rope's head, so it isn't counted yet.
+            right.head.height = self.head.height;
+            for (i, c) in cursor[..self.head.height as usize].iter().enumerate() {
+                let e = &mut (*c.node).nexts[i];
+                right.head.nexts[i] = SkipEntry {
+                    node: e.node,
+                    skip_chars: e.skip_chars - c.skip_chars - tail_chars,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: e.skip_pairs - c.skip_pairs - tail_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: e.skip_lines - c.skip_lines - tail_lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: e.skip_bytes - c.skip_bytes - tail_bytes,
+                };
+                *e = SkipEntry { node: null_mut(), ..*c };
+            }
+            (*node).str.remove_chars(cursor[0].skip_chars, tail_chars);
+
+            let mut moved_bytes = 0;
+            let mut n = right.head.nexts[0].node;
+            if !n.is_null() { (*n).prev = null_mut(); }
+            while !n.is_null() {
+                moved_bytes += (*n).str.len_bytes();
+                n = (*n).first_next().node;
+            }
+            self.num_bytes -= moved_bytes + tail.len();
+            right.num_bytes = moved_bytes;
+
+            right.insert(0, tail);
+        }
+
+        #[cfg(feature = "changes")]
+        self.record_change(ChangeEvent {
+            char_pos, chars_removed: len - char_pos, chars_inserted: 0,
+            byte_pos: self.num_bytes, bytes_removed: len_bytes - self.num_bytes, bytes_inserted: 0,
+        });
+        Ok((self, right))
+    }
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+
     /// Return the length of the rope in unicode characters. Note this is not the same as either
     /// the number of bytes the characters take, or the number of grapheme clusters in the string.
     ///
@@ -446,6 +1270,7 @@ impl JumpRope {
     /// let snowman = JumpRope::from("☃️");
     /// assert_eq!(snowman.len_chars(), 2);
     /// ```
+    #[doc(alias = "char_len")]
     pub fn len_chars(&self) -> usize {
         self.head.nexts[self.head.height as usize - 1].skip_chars
     }
@@ -465,20 +1290,40 @@ impl JumpRope {
         skip_pairs + skip_chars
     }
 
-    /// Does the rope only contain ASCII characters? (Unicode codepoints < 128). There are some
-    /// optimizations that can be done if this is true.
-    #[cfg(feature = "wchar_conversion")]
-    pub fn is_ascii_only(&self) -> bool {
-        self.head.nexts[self.head.height as usize - 1].skip_pairs == 0
-    }
+    /// Returns `true` if every character in the rope is ASCII (`< 128`). When this is true,
+    /// character offsets and byte offsets are the same.
+    ///
+    /// A character takes one byte in UTF-8 if and only if it is ASCII, so this is a constant time
+    /// comparison of the rope's length in bytes and in characters. There's no need to scan the
+    /// content.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// assert!(rope.is_ascii());
+    /// rope.insert(2, "↯");
+    /// assert!(!rope.is_ascii());
+    /// ```
+    pub fn is_ascii(&self) -> bool {
+        self.num_bytes == self.len_chars()
+    }
+
+    /// Does the rope only contain ASCII characters? (Unicode codepoints < 128). There are some
+    /// optimizations that can be done if this is true.
+    #[cfg(feature = "wchar_conversion")]
+    pub fn is_ascii_only(&self) -> bool {
+        self.head.nexts[self.head.height as usize - 1].skip_pairs == 0
+    }
 
     /// Returns read cursor and global surrogate pair position.
     ///
     /// Surrogate pairs are only counted if wchar_conversion feature enabled.
-    pub(crate) fn read_cursor_at_char(&self, char_pos: usize, stick_end: bool) -> ReadCursor<'_> {
+    pub(crate) fn read_cursor_at_char(&self, char_pos: usize, stick_end: bool) -> ReadCursor<'_, N> {
         assert!(char_pos <= self.len_chars());
 
-        let mut e: *const Node = &self.head;
+        let mut e: *const Node<N> = &self.head;
         let mut height = self.head.height as usize - 1;
 
         let mut offset_chars = char_pos; // How many more chars to skip
@@ -498,7 +1343,7 @@ impl JumpRope {
                     global_pairs += next.skip_pairs;
                 }
                 e = next.node;
-                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+                debug_assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
                 // Go down.
                 if height != 0 {
@@ -524,10 +1369,14 @@ impl JumpRope {
         };
     }
 
-    pub(super) fn mut_cursor_at_char(&mut self, char_pos: usize, stick_end: bool) -> MutCursor<'_> {
+    pub(super) fn mut_cursor_at_char(&mut self, char_pos: usize, stick_end: bool) -> MutCursor<'_, N, A> {
         assert!(char_pos <= self.len_chars());
+        #[cfg(feature = "cow")]
+        self.make_unique();
+        self.cursor_valid = false;
 
-        let mut e: *mut Node = &mut self.head;
+        let head: *mut Node<N> = &mut self.head;
+        let mut e = head;
         let head_height = self.head.height as usize;
         let mut height = head_height - 1;
 
@@ -535,18 +1384,36 @@ impl JumpRope {
 
         #[cfg(feature = "wchar_conversion")]
         let mut surrogate_pairs = 0; // Current wchar pos from the start of the rope
+        #[cfg(feature = "line_conversion")]
+        let mut lines = 0; // Current line from the start of the rope
+        #[cfg(feature = "changes")]
+        let mut bytes = 0; // Current byte pos from the start of the rope
 
         // It would be nice to pop this into a function, but miri gets confused if we pass the node
         // pointer out of this method. So I'm keeping this inline.
-        let mut cursor = MutCursor {
-            inner: [SkipEntry {
-                node: e,
-                skip_chars: 0,
-                #[cfg(feature = "wchar_conversion")]
-                skip_pairs: 0
-            }; MAX_HEIGHT+1],
+        self.cursor = [SkipEntry {
+            node: e,
+            skip_chars: 0,
+            #[cfg(feature = "wchar_conversion")]
+            skip_pairs: 0,
+            #[cfg(feature = "line_conversion")]
+            skip_lines: 0,
+            #[cfg(feature = "changes")]
+            skip_bytes: 0,
+        }; MAX_HEIGHT+1];
+        let cursor = MutCursor {
+            inner: &mut self.cursor,
             rng: &mut self.rng,
             num_bytes: &mut self.num_bytes,
+            free_list: &mut self.free_list,
+            #[cfg(feature = "marks")]
+            marks: &mut self.marks,
+            #[cfg(feature = "history")]
+            history: &mut self.history,
+            #[cfg(feature = "changes")]
+            changes: &mut self.changes,
+            #[cfg(feature = "edit_log")]
+            edit_log: &mut self.edit_log,
             phantom: PhantomData,
         };
 
@@ -557,14 +1424,21 @@ impl JumpRope {
             if offset > skip || (!stick_end && offset == skip && !next.node.is_null()) {
                 // Go right.
 
-                // This breaks miri for some reason.
-                // assert!(e == &mut self.head || !en.str.is_empty());
+                // Compare against the saved pointer. Writing &mut self.head here would reborrow the
+                // head, which invalidates e (and every pointer in the cursor) under stacked borrows.
+                debug_assert!(std::ptr::eq(e, head) || !en.str.is_empty());
                 offset -= skip;
                 #[cfg(feature = "wchar_conversion")] {
                     surrogate_pairs += next.skip_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    lines += next.skip_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    bytes += next.skip_bytes;
+                }
                 e = next.node;
-                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+                debug_assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
                 // Record this and go down.
                 cursor.inner[height] = SkipEntry {
@@ -572,7 +1446,11 @@ impl JumpRope {
                     node: e,
                     skip_chars: offset,
                     #[cfg(feature = "wchar_conversion")]
-                    skip_pairs: surrogate_pairs
+                    skip_pairs: surrogate_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: bytes,
                 };
 
                 if height != 0 {
@@ -587,12 +1465,28 @@ impl JumpRope {
                             }
                         }
                     }
+                    #[cfg(feature = "line_conversion")] {
+                        lines += en.str.count_lines(offset);
+                        if lines > 0 {
+                            for entry in &mut cursor.inner[0..head_height] {
+                                entry.skip_lines = lines - entry.skip_lines;
+                            }
+                        }
+                    }
+                    #[cfg(feature = "changes")] {
+                        bytes += en.str.count_bytes(offset);
+                        for entry in &mut cursor.inner[0..head_height] {
+                            entry.skip_bytes = bytes - entry.skip_bytes;
+                        }
+                    }
                     break;
                 }
             }
         };
 
-        assert!(offset <= NODE_STR_SIZE);
+        // offset is in characters, so compare it to the node's character count (not N, which
+        // counts bytes).
+        debug_assert!(offset <= unsafe { (*e).num_chars() });
 
         cursor
     }
@@ -602,7 +1496,7 @@ impl JumpRope {
         assert!(wchar_pos <= self.len_wchars());
 
         let mut height = self.head.height as usize - 1;
-        let mut e: *const Node = &self.head;
+        let mut e: *const Node<N> = &self.head;
 
         let mut offset = wchar_pos; // How many more chars to skip
 
@@ -618,7 +1512,7 @@ impl JumpRope {
                 offset -= skip;
                 char_pos += next.skip_chars;
                 e = next.node;
-                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+                debug_assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
                 // Go down.
                 if height != 0 {
@@ -633,26 +1527,47 @@ impl JumpRope {
 
     /// Create a cursor pointing wchar characters into the rope
     #[cfg(feature = "wchar_conversion")]
-    pub(crate) fn mut_cursor_at_wchar(&mut self, wchar_pos: usize, stick_end: bool) -> MutCursor {
+    pub(crate) fn mut_cursor_at_wchar(&mut self, wchar_pos: usize, stick_end: bool) -> MutCursor<'_, N, A> {
         assert!(wchar_pos <= self.len_wchars());
+        #[cfg(feature = "cow")]
+        self.make_unique();
+        self.cursor_valid = false;
 
         let head_height = self.head.height as usize;
-        let mut e: *mut Node = &mut self.head;
+        let mut e: *mut Node<N> = &mut self.head;
         let mut height = self.head.height as usize - 1;
 
         let mut offset = wchar_pos; // How many more chars to skip
 
         let mut char_pos = 0; // Char pos from the start of the rope
+        #[cfg(feature = "line_conversion")]
+        let mut lines = 0; // Current line from the start of the rope
+        #[cfg(feature = "changes")]
+        let mut bytes = 0; // Current byte pos from the start of the rope
 
-        let mut cursor = MutCursor {
-            inner: [SkipEntry {
-                node: e,
-                skip_chars: 0,
-                #[cfg(feature = "wchar_conversion")]
-                skip_pairs: 0
-            }; MAX_HEIGHT+1],
+        self.cursor = [SkipEntry {
+            node: e,
+            skip_chars: 0,
+            #[cfg(feature = "wchar_conversion")]
+            skip_pairs: 0,
+            #[cfg(feature = "line_conversion")]
+            skip_lines: 0,
+            #[cfg(feature = "changes")]
+            skip_bytes: 0,
+        }; MAX_HEIGHT+1];
+        let cursor = MutCursor {
+            inner: &mut self.cursor,
             rng: &mut self.rng,
             num_bytes: &mut self.num_bytes,
+            free_list: &mut self.free_list,
+            #[cfg(feature = "marks")]
+            marks: &mut self.marks,
+            #[cfg(feature = "history")]
+            history: &mut self.history,
+            #[cfg(feature = "changes")]
+            changes: &mut self.changes,
+            #[cfg(feature = "edit_log")]
+            edit_log: &mut self.edit_log,
             phantom: PhantomData,
         };
 
@@ -665,68 +1580,355 @@ impl JumpRope {
                 // assert!(e == &self.head || !en.str.is_empty());
                 offset -= skip;
                 char_pos += next.skip_chars;
+                #[cfg(feature = "line_conversion")] {
+                    lines += next.skip_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    bytes += next.skip_bytes;
+                }
                 e = next.node;
-                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+                debug_assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
                 // Record this and go down.
                 cursor.inner[height] = SkipEntry {
                     node: e,
                     skip_chars: char_pos,
-                    skip_pairs: offset
+                    skip_pairs: offset,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: bytes,
                 };
 
                 if height != 0 {
                     height -= 1;
                 } else {
-                    char_pos += en.str.count_chars_in_wchars(offset);
+                    let local_chars = en.str.count_chars_in_wchars(offset);
+                    char_pos += local_chars;
+                    #[cfg(feature = "line_conversion")] {
+                        lines += en.str.count_lines(local_chars);
+                    }
+                    #[cfg(feature = "changes")] {
+                        bytes += en.str.count_bytes(local_chars);
+                    }
                     for entry in &mut cursor.inner[0..head_height] {
                         let skip_chars = char_pos - entry.skip_chars;
                         entry.skip_chars = skip_chars;
                         entry.skip_pairs -= skip_chars;
+                        #[cfg(feature = "line_conversion")] {
+                            entry.skip_lines = lines - entry.skip_lines;
+                        }
+                        #[cfg(feature = "changes")] {
+                            entry.skip_bytes = bytes - entry.skip_bytes;
+                        }
                     }
                     break;
                 }
             }
         };
 
-        assert!(offset <= NODE_STR_SIZE);
+        // offset is in UTF-16 code units.
+        debug_assert!(offset <= unsafe { (*e).num_chars() + (*e).num_surrogate_pairs() });
 
         cursor
     }
 
-    fn mut_cursor_at_start(&mut self) -> MutCursor<'_> {
-        MutCursor {
-            inner: [SkipEntry {
-                node: &mut self.head,
-                skip_chars: 0,
+    /// Try to move the cached cursor from the previous edit to char_pos. This only succeeds if
+    /// char_pos is in the same node as the cached cursor, or in the node directly after it.
+    ///
+    /// On success, self.cursor has the same entries as `mut_cursor_at_char(char_pos, true)`.
+    fn repair_cached_cursor(&mut self, char_pos: usize) -> bool {
+        if !self.cursor_valid { return false; }
+        self.cursor_valid = false;
+
+        let head: *mut Node<N> = &mut self.head;
+        let inner = &mut self.cursor;
+        // If the rope has been moved in memory since the cursor was saved, any pointers to the
+        // head node are now invalid.
+        if inner[MAX_HEIGHT].node != head { return false; }
+
+        let head_height = self.head.height as usize;
+        let old_pos = inner[head_height - 1].skip_chars;
+        let local = inner[0].skip_chars;
+
+        // Pointers to the head node were derived from an earlier borrow of the rope. Replace them
+        // so we don't dereference stale pointers.
+        inner[MAX_HEIGHT].node = head;
+        for e in &mut inner[..head_height] {
+            if std::ptr::eq(e.node, head) { e.node = head; }
+        }
+        let node_ptr = inner[0].node;
+        let node = unsafe { &*node_ptr };
+
+        // The cursor must end up exactly where mut_cursor_at_char(.., stick_end: true) would put
+        // it. That means it never rests at the start of a node, except at the start of the head.
+        if char_pos + local >= old_pos {
+            let new_local = char_pos + local - old_pos;
+            if new_local <= node.num_chars() && (new_local > 0 || std::ptr::eq(node_ptr, head)) {
+                // The new position is in the same node.
                 #[cfg(feature = "wchar_conversion")]
-                skip_pairs: 0
-            }; MAX_HEIGHT+1],
+                let pairs_delta = node.str.count_surrogate_pairs(new_local) as isize
+                    - node.str.count_surrogate_pairs(local) as isize;
+                #[cfg(feature = "line_conversion")]
+                let lines_delta = node.str.count_lines(new_local) as isize
+                    - node.str.count_lines(local) as isize;
+                #[cfg(feature = "changes")]
+                let bytes_delta = node.str.count_bytes(new_local) as isize
+                    - node.str.count_bytes(local) as isize;
+
+                for e in &mut inner[..head_height] {
+                    e.skip_chars = e.skip_chars + new_local - local;
+                    #[cfg(feature = "wchar_conversion")] {
+                        e.skip_pairs = add_signed(e.skip_pairs, pairs_delta);
+                    }
+                    #[cfg(feature = "line_conversion")] {
+                        e.skip_lines = add_signed(e.skip_lines, lines_delta);
+                    }
+                    #[cfg(feature = "changes")] {
+                        e.skip_bytes = add_signed(e.skip_bytes, bytes_delta);
+                    }
+                }
+                return true;
+            }
+        }
+
+        if char_pos > old_pos {
+            let next_ptr = node.first_next().node;
+            let Some(next) = (unsafe { next_ptr.as_ref() }) else { return false; };
+            let next_local = char_pos - old_pos + local - node.num_chars();
+            if next_local == 0 || next_local > next.num_chars() { return false; }
+
+            // The new position is in the next node. Entries up to the next node's height now point
+            // to it. Entries above that still point to the same node as before.
+            #[cfg(feature = "wchar_conversion")]
+            let next_pairs = next.str.count_surrogate_pairs(next_local);
+            #[cfg(feature = "wchar_conversion")]
+            let pairs_delta = node.num_surrogate_pairs() - node.str.count_surrogate_pairs(local) + next_pairs;
+            #[cfg(feature = "line_conversion")]
+            let next_lines = next.str.count_lines(next_local);
+            #[cfg(feature = "line_conversion")]
+            let lines_delta = node.num_lines() - node.str.count_lines(local) + next_lines;
+            #[cfg(feature = "changes")]
+            let next_bytes = next.str.count_bytes(next_local);
+            #[cfg(feature = "changes")]
+            let bytes_delta = node.str.len_bytes() - node.str.count_bytes(local) + next_bytes;
+
+            let next_height = next.height as usize;
+            for e in &mut inner[..next_height] {
+                *e = SkipEntry {
+                    node: next_ptr,
+                    skip_chars: next_local,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: next_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: next_lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: next_bytes,
+                };
+            }
+            for e in &mut inner[next_height..head_height] {
+                e.skip_chars += char_pos - old_pos;
+                #[cfg(feature = "wchar_conversion")] {
+                    e.skip_pairs += pairs_delta;
+                }
+                #[cfg(feature = "line_conversion")] {
+                    e.skip_lines += lines_delta;
+                }
+                #[cfg(feature = "changes")] {
+                    e.skip_bytes += bytes_delta;
+                }
+            }
+            return true;
+        }
+
+        false
+    }
+
+    /// Create a cursor at char_pos (with stick_end: true). This reuses the cursor from the
+    /// previous edit if possible.
+    fn mut_cursor_at_char_cached(&mut self, char_pos: usize) -> MutCursor<'_, N, A> {
+        debug_assert!(char_pos <= self.len_chars());
+        // Copying shared nodes invalidates the cached cursor, so this has to happen first.
+        #[cfg(feature = "cow")]
+        self.make_unique();
+        if !self.repair_cached_cursor(char_pos) {
+            return self.mut_cursor_at_char(char_pos, true);
+        }
+
+        if cfg!(debug_assertions) {
+            let repaired = self.cursor;
+            let height = self.head.height as usize;
+            self.mut_cursor_at_char(char_pos, true);
+            assert_eq!(&repaired[..height], &self.cursor[..height], "Cached cursor is invalid");
+        }
+
+        MutCursor {
+            inner: &mut self.cursor,
+            rng: &mut self.rng,
+            num_bytes: &mut self.num_bytes,
+            free_list: &mut self.free_list,
+            #[cfg(feature = "marks")]
+            marks: &mut self.marks,
+            #[cfg(feature = "history")]
+            history: &mut self.history,
+            #[cfg(feature = "changes")]
+            changes: &mut self.changes,
+            #[cfg(feature = "edit_log")]
+            edit_log: &mut self.edit_log,
+            phantom: PhantomData,
+        }
+    }
+
+    fn mut_cursor_at_start(&mut self) -> MutCursor<'_, N, A> {
+        #[cfg(feature = "cow")]
+        self.make_unique();
+        self.cursor_valid = false;
+        self.cursor = [SkipEntry {
+            node: &mut self.head,
+            skip_chars: 0,
+            #[cfg(feature = "wchar_conversion")]
+            skip_pairs: 0,
+            #[cfg(feature = "line_conversion")]
+            skip_lines: 0,
+            #[cfg(feature = "changes")]
+            skip_bytes: 0,
+        }; MAX_HEIGHT+1];
+        MutCursor {
+            inner: &mut self.cursor,
             rng: &mut self.rng,
             num_bytes: &mut self.num_bytes,
+            free_list: &mut self.free_list,
+            #[cfg(feature = "marks")]
+            marks: &mut self.marks,
+            #[cfg(feature = "history")]
+            history: &mut self.history,
+            #[cfg(feature = "changes")]
+            changes: &mut self.changes,
+            #[cfg(feature = "edit_log")]
+            edit_log: &mut self.edit_log,
             phantom: PhantomData,
         }
     }
 
-    fn mut_cursor_at_end(&mut self) -> MutCursor {
+    fn mut_cursor_at_end(&mut self) -> MutCursor<'_, N, A> {
         self.mut_cursor_at_char(self.len_chars(), true)
     }
 
-    fn insert_node_at(cursor: &mut MutCursor, contents: &str, num_chars: usize, update_cursor: bool, #[cfg(feature = "wchar_conversion")] num_pairs: usize) {
+    /// Move the cursor forward by `by_chars` characters. The search starts from the lowest level
+    /// of the cursor which reaches the new position (rather than from the head), so short moves
+    /// are cheap. The cursor ends up where `mut_cursor_at_char(.., stick_end: true)` would put it.
+    fn advance_cursor(cursor: &mut MutCursor<N, A>, by_chars: usize) {
+        let head_height = cursor.head_height();
+        // The top entry is the head, which skips over the whole rope.
+        debug_assert!(cursor.global_char_pos() + by_chars
+            <= unsafe { (*cursor.inner[head_height - 1].node).nexts[head_height - 1].skip_chars });
+
+        // Entries at this level and above keep pointing to the same nodes.
+        let mut height = 0;
+        while height < head_height - 1 {
+            let c = &cursor.inner[height];
+            if c.skip_chars + by_chars <= unsafe { (*c.node).nexts[height].skip_chars } { break; }
+            height += 1;
+        }
+
+        let start = cursor.inner[height];
+        let mut e = start.node;
+        let mut offset = start.skip_chars + by_chars;
+        // Counted from the start of the node at the starting level, then converted like in
+        // mut_cursor_at_char.
+        #[cfg(feature = "wchar_conversion")]
+        let mut surrogate_pairs = 0;
+        #[cfg(feature = "line_conversion")]
+        let mut lines = 0;
+        #[cfg(feature = "changes")]
+        let mut bytes = 0;
+
+        let mut h = height;
+        loop {
+            let next = unsafe { (*e).nexts[h] };
+            if offset > next.skip_chars {
+                offset -= next.skip_chars;
+                #[cfg(feature = "wchar_conversion")] {
+                    surrogate_pairs += next.skip_pairs;
+                }
+                #[cfg(feature = "line_conversion")] {
+                    lines += next.skip_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    bytes += next.skip_bytes;
+                }
+                e = next.node;
+                debug_assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+            } else {
+                cursor.inner[h] = SkipEntry {
+                    node: e,
+                    skip_chars: offset,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: surrogate_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: bytes,
+                };
+                if h == 0 { break; }
+                h -= 1;
+            }
+        }
+
+        // Add on the counts within the node the cursor ended up in.
+        #[cfg(feature = "wchar_conversion")] {
+            surrogate_pairs += unsafe { (*e).str.count_surrogate_pairs(offset) };
+            for entry in &mut cursor.inner[..=height] {
+                entry.skip_pairs = surrogate_pairs - entry.skip_pairs;
+            }
+        }
+        #[cfg(feature = "line_conversion")] {
+            lines += unsafe { (*e).str.count_lines(offset) };
+            for entry in &mut cursor.inner[..=height] {
+                entry.skip_lines = lines - entry.skip_lines;
+            }
+        }
+        #[cfg(feature = "changes")] {
+            bytes += unsafe { (*e).str.count_bytes(offset) };
+            for entry in &mut cursor.inner[..=height] {
+                entry.skip_bytes = bytes - entry.skip_bytes;
+            }
+        }
+        // The nodes above are now further behind the cursor, by however far it moved.
+        for entry in &mut cursor.inner[height + 1..head_height] {
+            entry.skip_chars += by_chars;
+            #[cfg(feature = "wchar_conversion")] {
+                entry.skip_pairs += surrogate_pairs - start.skip_pairs;
+            }
+            #[cfg(feature = "line_conversion")] {
+                entry.skip_lines += lines - start.skip_lines;
+            }
+            #[cfg(feature = "changes")] {
+                entry.skip_bytes += bytes - start.skip_bytes;
+            }
+        }
+    }
+
+    fn insert_node_at(cursor: &mut MutCursor<N, A>, contents: &str, num_chars: usize, update_cursor: bool, #[cfg(feature = "wchar_conversion")] num_pairs: usize, #[cfg(feature = "line_conversion")] num_lines: usize) {
         // println!("Insert_node_at {} len {}", contents.len(), self.num_bytes);
         // assert!(contents.len() < NODE_STR_SIZE);
         debug_assert_eq!(count_chars(contents), num_chars);
         #[cfg(feature = "wchar_conversion")] {
             debug_assert_eq!(count_utf16_surrogates(contents), num_pairs);
         }
-        debug_assert!(num_chars <= NODE_STR_SIZE);
+        #[cfg(feature = "line_conversion")] {
+            debug_assert_eq!(count_lines(contents), num_lines);
+        }
+        debug_assert!(contents.len() <= N);
+        #[cfg(feature = "changes")]
+        let num_bytes = contents.len();
 
         // TODO: Pin this sucka.
         // let new_node = Pin::new(Node::alloc());
         // let new_node = Node::alloc(cursor.rng, contents);
 
         let new_height = random_height(cursor.rng);
-        let new_node = Box::into_raw(Box::new(Node::new_with_height(new_height, contents)));
+        let new_node = cursor.free_list.alloc(new_height, contents);
 
         let new_height = new_height as usize;
 
@@ -749,6 +1951,12 @@ impl JumpRope {
             cursor.set_height(head_height);
         }
 
+        unsafe {
+            let prev = cursor.inner[0].node;
+            cursor.set_prev((*prev).nexts[0].node, new_node);
+            cursor.set_prev(new_node, prev);
+        }
+
         for i in 0..new_height {
             let prev_skip = unsafe { &mut (*cursor.inner[i].node).nexts[i] };
             let nexts = unsafe { &mut (*new_node).nexts };
@@ -762,6 +1970,14 @@ impl JumpRope {
                 nexts[i].skip_pairs = num_pairs + prev_skip.skip_pairs - cursor.inner[i].skip_pairs;
                 prev_skip.skip_pairs = cursor.inner[i].skip_pairs;
             }
+            #[cfg(feature = "line_conversion")] {
+                nexts[i].skip_lines = num_lines + prev_skip.skip_lines - cursor.inner[i].skip_lines;
+                prev_skip.skip_lines = cursor.inner[i].skip_lines;
+            }
+            #[cfg(feature = "changes")] {
+                nexts[i].skip_bytes = num_bytes + prev_skip.skip_bytes - cursor.inner[i].skip_bytes;
+                prev_skip.skip_bytes = cursor.inner[i].skip_bytes;
+            }
 
             // & move the iterator to the end of the newly inserted node.
             if update_cursor {
@@ -770,6 +1986,12 @@ impl JumpRope {
                 #[cfg(feature = "wchar_conversion")] {
                     cursor.inner[i].skip_pairs = num_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    cursor.inner[i].skip_lines = num_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    cursor.inner[i].skip_bytes = num_bytes;
+                }
             }
         }
 
@@ -780,12 +2002,24 @@ impl JumpRope {
                 #[cfg(feature = "wchar_conversion")] {
                     (*cursor.inner[i].node).nexts[i].skip_pairs += num_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    (*cursor.inner[i].node).nexts[i].skip_lines += num_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    (*cursor.inner[i].node).nexts[i].skip_bytes += num_bytes;
+                }
             }
             if update_cursor {
                 cursor.inner[i].skip_chars += num_chars;
                 #[cfg(feature = "wchar_conversion")] {
                     cursor.inner[i].skip_pairs += num_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    cursor.inner[i].skip_lines += num_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    cursor.inner[i].skip_bytes += num_bytes;
+                }
             }
         }
 
@@ -793,7 +2027,163 @@ impl JumpRope {
         *cursor.num_bytes += contents.len();
     }
 
-    fn insert_at_cursor(cursor: &mut MutCursor, contents: &str) {
+    /// Bulk insert a long string at the cursor, as a run of new nodes. The string is split into
+    /// maximally full chunks (on character boundaries). The chunks are linked together into a
+    /// chain using a tower of the last node seen at each height, then the whole chain is spliced
+    /// into the rope in one pass. This is much cheaper than inserting each node with
+    /// [`insert_node_at`](Self::insert_node_at), which updates every level of the cursor.
+    ///
+    /// The cursor is moved to the end of the inserted content.
+    fn insert_nodes_at(cursor: &mut MutCursor<N, A>, contents: &str) {
+        // For each height, the first node in the chain with that height and the number of chars
+        // (etc) in the chain before it.
+        let mut firsts = [SkipEntry::<N>::new(); MAX_HEIGHT];
+        // For each height, the last node in the chain with that height and the number of chars
+        // (etc) in the chain before it.
+        let mut tails = [SkipEntry::<N>::new(); MAX_HEIGHT];
+        // The total size of the chain so far. The node pointer is unused.
+        let mut total = SkipEntry::<N>::new();
+        let mut max_height = 0;
+
+        let mut remainder = contents;
+        while !remainder.is_empty() {
+            let cut = floor_char_boundary(remainder, N);
+            let (chunk, rem) = remainder.split_at(cut);
+            assert!(!chunk.is_empty());
+            remainder = rem;
+
+            let height = random_height(cursor.rng);
+            let node = cursor.free_list.alloc(height, chunk);
+            let height = height as usize;
+            if max_height > 0 {
+                unsafe { (*node).prev = tails[0].node; }
+            }
+
+            for i in 0..height {
+                let here = SkipEntry { node, ..total };
+                if i < max_height {
+                    let tail = &tails[i];
+                    unsafe {
+                        (*tail.node).nexts[i] = SkipEntry {
+                            node,
+                            skip_chars: total.skip_chars - tail.skip_chars,
+                            #[cfg(feature = "wchar_conversion")]
+                            skip_pairs: total.skip_pairs - tail.skip_pairs,
+                            #[cfg(feature = "line_conversion")]
+                            skip_lines: total.skip_lines - tail.skip_lines,
+                            #[cfg(feature = "changes")]
+                            skip_bytes: total.skip_bytes - tail.skip_bytes,
+                        };
+                    }
+                } else {
+                    firsts[i] = here;
+                }
+                tails[i] = here;
+            }
+            max_height = max_height.max(height);
+
+            total.skip_chars += count_chars(chunk);
+            #[cfg(feature = "wchar_conversion")] {
+                total.skip_pairs += count_utf16_surrogates(chunk);
+            }
+            #[cfg(feature = "line_conversion")] {
+                total.skip_lines += count_lines(chunk);
+            }
+            #[cfg(feature = "changes")] {
+                total.skip_bytes += chunk.len();
+            }
+        }
+
+        let mut head_height = cursor.head_height();
+        while head_height <= max_height {
+            // Same as insert_node_at.
+            unsafe {
+                let head = &mut (*cursor.inner[head_height].node);
+                head.nexts[head_height] = head.nexts[head_height - 1];
+            }
+            cursor.inner[head_height] = cursor.inner[head_height - 1];
+            head_height += 1;
+            cursor.set_height(head_height);
+        }
+
+        unsafe {
+            let prev = cursor.inner[0].node;
+            cursor.set_prev((*prev).nexts[0].node, tails[0].node);
+            cursor.set_prev(firsts[0].node, prev);
+        }
+
+        // Splice the chain in between the cursor and whatever follows it at each level.
+        for i in 0..max_height {
+            let c = &mut cursor.inner[i];
+            let prev_skip = unsafe { &mut (*c.node).nexts[i] };
+            let first = &firsts[i];
+            let tail = &tails[i];
+
+            unsafe {
+                (*tail.node).nexts[i] = SkipEntry {
+                    node: prev_skip.node,
+                    skip_chars: total.skip_chars - tail.skip_chars + prev_skip.skip_chars - c.skip_chars,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: total.skip_pairs - tail.skip_pairs + prev_skip.skip_pairs - c.skip_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: total.skip_lines - tail.skip_lines + prev_skip.skip_lines - c.skip_lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: total.skip_bytes - tail.skip_bytes + prev_skip.skip_bytes - c.skip_bytes,
+                };
+            }
+            *prev_skip = SkipEntry {
+                node: first.node,
+                skip_chars: c.skip_chars + first.skip_chars,
+                #[cfg(feature = "wchar_conversion")]
+                skip_pairs: c.skip_pairs + first.skip_pairs,
+                #[cfg(feature = "line_conversion")]
+                skip_lines: c.skip_lines + first.skip_lines,
+                #[cfg(feature = "changes")]
+                skip_bytes: c.skip_bytes + first.skip_bytes,
+            };
+            // And move the cursor to the end of the chain.
+            *c = SkipEntry {
+                node: tail.node,
+                skip_chars: total.skip_chars - tail.skip_chars,
+                #[cfg(feature = "wchar_conversion")]
+                skip_pairs: total.skip_pairs - tail.skip_pairs,
+                #[cfg(feature = "line_conversion")]
+                skip_lines: total.skip_lines - tail.skip_lines,
+                #[cfg(feature = "changes")]
+                skip_bytes: total.skip_bytes - tail.skip_bytes,
+            };
+        }
+
+        // Levels above the tallest new node simply skip over the whole chain.
+        for i in max_height..head_height {
+            unsafe {
+                (*cursor.inner[i].node).nexts[i].skip_chars += total.skip_chars;
+                #[cfg(feature = "wchar_conversion")] {
+                    (*cursor.inner[i].node).nexts[i].skip_pairs += total.skip_pairs;
+                }
+                #[cfg(feature = "line_conversion")] {
+                    (*cursor.inner[i].node).nexts[i].skip_lines += total.skip_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    (*cursor.inner[i].node).nexts[i].skip_bytes += total.skip_bytes;
+                }
+            }
+            cursor.inner[i].skip_chars += total.skip_chars;
+            #[cfg(feature = "wchar_conversion")] {
+                cursor.inner[i].skip_pairs += total.skip_pairs;
+            }
+            #[cfg(feature = "line_conversion")] {
+                cursor.inner[i].skip_lines += total.skip_lines;
+            }
+            #[cfg(feature = "changes")] {
+                cursor.inner[i].skip_bytes += total.skip_bytes;
+            }
+        }
+
+        *cursor.num_bytes += contents.len();
+    }
+
+    fn insert_at_cursor(cursor: &mut MutCursor<N, A>, contents: &str) {
         if contents.is_empty() { return; }
         // iter contains how far (in characters) into the current element to
         // skip. Figure out how much that is in bytes.
@@ -808,10 +2198,32 @@ impl JumpRope {
         // how big it is. We'll count the bytes, and also check that its valid utf8.
         let num_inserted_bytes = contents.len();
         let mut num_inserted_chars = count_chars(contents);
+        #[cfg(feature = "marks")]
+        cursor.marks.insert(cursor.global_char_pos(), num_inserted_chars);
+        #[cfg(feature = "history")]
+        if cursor.history.is_some() {
+            let pos = cursor.global_char_pos();
+            cursor.history.as_mut().unwrap().record(Edit::Insert { pos, len: num_inserted_chars });
+        }
+        #[cfg(feature = "changes")]
+        if cursor.changes.is_some() {
+            let event = ChangeEvent {
+                char_pos: cursor.global_char_pos(), chars_removed: 0, chars_inserted: num_inserted_chars,
+                byte_pos: unsafe { Self::byte_pos_at_cursor(cursor) }, bytes_removed: 0, bytes_inserted: num_inserted_bytes,
+            };
+            cursor.changes.as_mut().unwrap().push(event);
+        }
+        #[cfg(feature = "edit_log")]
+        if cursor.edit_log.is_some() {
+            let pos = cursor.global_char_pos();
+            cursor.edit_log.as_mut().unwrap().push(LogEntry { pos, deleted: String::new(), inserted: contents.into() });
+        }
         #[cfg(feature = "wchar_conversion")]
             let mut num_inserted_pairs = if num_inserted_bytes != num_inserted_chars {
             count_utf16_surrogates(contents)
         } else { 0 };
+        #[cfg(feature = "line_conversion")]
+        let mut num_inserted_lines = count_lines(contents);
 
         // Adding this short circuit makes the code about 2% faster for 1% more code
         unsafe {
@@ -819,14 +2231,14 @@ impl JumpRope {
                 // Short circuit. If we can just insert all the content right here in the gap, do so.
                 (*e).str.insert_in_gap(contents);
 
-                #[cfg(feature = "wchar_conversion")] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                }
-                #[cfg(not(feature = "wchar_conversion"))] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize);
-                }
+                cursor.update_offsets(head_height, num_inserted_chars as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize),
+                    #[cfg(feature = "changes")] (num_inserted_bytes as isize));
+                cursor.move_within_node(head_height, num_inserted_chars as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize),
+                    #[cfg(feature = "changes")] (num_inserted_bytes as isize));
 
                 *cursor.num_bytes += num_inserted_bytes;
                 return;
@@ -841,7 +2253,7 @@ impl JumpRope {
 
             // Can we insert into the current node?
             let current_len_bytes = (*e).str.len_bytes();
-            let mut insert_here = current_len_bytes + num_inserted_bytes <= NODE_STR_SIZE;
+            let mut insert_here = current_len_bytes + num_inserted_bytes <= N;
 
             // If we can't insert here, see if we can move the cursor forward and insert into the
             // subsequent node.
@@ -852,7 +2264,7 @@ impl JumpRope {
                 // - The insert would be at the start of the next node
                 // - There's room in the next node
                 if let Some(next) = (*e).first_next_mut().node.as_mut() {
-                    if next.str.len_bytes() + num_inserted_bytes <= NODE_STR_SIZE {
+                    if next.str.len_bytes() + num_inserted_bytes <= N {
                         offset_bytes = 0;
 
                         // Could do this with slice::fill but this seems slightly faster.
@@ -861,7 +2273,11 @@ impl JumpRope {
                                 node: next,
                                 skip_chars: 0,
                                 #[cfg(feature = "wchar_conversion")]
-                                skip_pairs: 0
+                                skip_pairs: 0,
+                                #[cfg(feature = "line_conversion")]
+                                skip_lines: 0,
+                                #[cfg(feature = "changes")]
+                                skip_bytes: 0,
                             };
                         }
                         e = next;
@@ -878,15 +2294,14 @@ impl JumpRope {
 
                 *cursor.num_bytes += num_inserted_bytes;
                 // .... aaaand update all the offset amounts.
-
-                #[cfg(feature = "wchar_conversion")] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                }
-                #[cfg(not(feature = "wchar_conversion"))] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize);
-                }
+                cursor.update_offsets(head_height, num_inserted_chars as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize),
+                    #[cfg(feature = "changes")] (num_inserted_bytes as isize));
+                cursor.move_within_node(head_height, num_inserted_chars as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize),
+                    #[cfg(feature = "changes")] (num_inserted_bytes as isize));
             } else {
                 // There isn't room. We'll need to add at least one new node to the rope.
 
@@ -898,6 +2313,8 @@ impl JumpRope {
                 let mut num_end_chars: usize = 0;
                 #[cfg(feature = "wchar_conversion")]
                 let mut num_end_pairs: usize = 0;
+                #[cfg(feature = "line_conversion")]
+                let mut num_end_lines: usize = 0;
 
                 // let end_str = if num_end_bytes > 0 {
                 if num_end_bytes > 0 {
@@ -910,60 +2327,70 @@ impl JumpRope {
                     #[cfg(feature = "wchar_conversion")] {
                         num_end_pairs = (*e).num_surrogate_pairs() - (*e).str.gap_start_surrogate_pairs as usize;
                         debug_assert_eq!(num_end_pairs, count_utf16_surrogates((*e).str.end_as_str()));
-                        cursor.update_offsets(head_height, -(num_end_chars as isize), -(num_end_pairs as isize));
                     }
-                    #[cfg(not(feature = "wchar_conversion"))]
-                    cursor.update_offsets(head_height, -(num_end_chars as isize));
+                    #[cfg(feature = "line_conversion")] {
+                        num_end_lines = (*e).num_lines() - (*e).str.gap_start_lines as usize;
+                        debug_assert_eq!(num_end_lines, count_lines((*e).str.end_as_str()));
+                    }
+                    cursor.update_offsets(head_height, -(num_end_chars as isize),
+                        #[cfg(feature = "wchar_conversion")] -(num_end_pairs as isize),
+                        #[cfg(feature = "line_conversion")] -(num_end_lines as isize),
+                        #[cfg(feature = "changes")] -(num_end_bytes as isize));
 
                     *cursor.num_bytes -= num_end_bytes;
                 }
 
-                // Now we insert new nodes containing the new character data. The
-                // data must be broken into pieces of with a maximum size of
-                // NODE_STR_SIZE. Node boundaries must not occur in the middle of a
-                // utf8 codepoint.
-                // let mut str_offset: usize = 0;
                 let mut remainder = contents;
-                // while !remainder.is_empty() {
-                loop {
-                    // println!(". {}", remainder);
-                    // Find the first index after STR_SIZE bytes
-
-                    if remainder.len() <= NODE_STR_SIZE {
-                        Self::insert_node_at(cursor, remainder, num_inserted_chars, true, #[cfg(feature = "wchar_conversion")] num_inserted_pairs);
-                        break;
-                    } else {
-                        // Find a suitable cut point. We should take as many characters as we can fit in
-                        // the node, without splitting any unicode codepoints.
-                        let mut byte_pos = NODE_STR_SIZE;
-                        loop { // Slide back to a character boundary.
-                            let c = remainder.as_bytes()[byte_pos];
-                            if c & 0b1100_0000 != 0b1000_0000 {
-                                break;
-                            }
-                            byte_pos -= 1;
-                        }
-
-                        let slice = &remainder.as_bytes()[..byte_pos];
-                        let char_pos = count_chars_in_bytes(slice);
-                        num_inserted_chars -= char_pos;
 
+                if num_end_bytes == 0 {
+                    // We're appending to the end of this node. Pack as much of the new content as
+                    // we can into the space left over, so we don't leave a half-empty node behind.
+                    let cut = floor_char_boundary(remainder, (*e).str.gap_len as usize);
+                    if cut > 0 {
+                        let (here, rem) = remainder.split_at(cut);
+                        let chars = count_chars(here);
                         #[cfg(feature = "wchar_conversion")]
-                        let pairs = count_utf16_surrogates_in_bytes(slice);
+                        let pairs = count_utf16_surrogates(here);
+                        #[cfg(feature = "line_conversion")]
+                        let lines = count_lines(here);
+
+                        (*e).str.insert_in_gap(here);
+                        cursor.update_offsets(head_height, chars as isize,
+                            #[cfg(feature = "wchar_conversion")] (pairs as isize),
+                            #[cfg(feature = "line_conversion")] (lines as isize),
+                            #[cfg(feature = "changes")] (here.len() as isize));
+                        cursor.move_within_node(head_height, chars as isize,
+                            #[cfg(feature = "wchar_conversion")] (pairs as isize),
+                            #[cfg(feature = "line_conversion")] (lines as isize),
+                            #[cfg(feature = "changes")] (here.len() as isize));
+                        *cursor.num_bytes += here.len();
+
+                        num_inserted_chars -= chars;
                         #[cfg(feature = "wchar_conversion")] {
                             num_inserted_pairs -= pairs;
                         }
-
-                        let (next, rem) = remainder.split_at(byte_pos);
-                        assert!(!next.is_empty());
-                        Self::insert_node_at(cursor, next, char_pos, true, #[cfg(feature = "wchar_conversion")] pairs);
+                        #[cfg(feature = "line_conversion")] {
+                            num_inserted_lines -= lines;
+                        }
                         remainder = rem;
                     }
                 }
 
+                // Now we insert new nodes containing the rest of the new character data.
+                debug_assert!(!remainder.is_empty());
+                if remainder.len() <= N {
+                    Self::insert_node_at(cursor, remainder, num_inserted_chars, true,
+                        #[cfg(feature = "wchar_conversion")] num_inserted_pairs,
+                        #[cfg(feature = "line_conversion")] num_inserted_lines);
+                } else {
+                    Self::insert_nodes_at(cursor, remainder);
+                }
+
                 if num_end_bytes > 0 {
                     let end_str = (*e).str.take_rest();
-                    Self::insert_node_at(cursor, end_str, num_end_chars, false, #[cfg(feature = "wchar_conversion")] num_end_pairs);
+                    Self::insert_node_at(cursor, end_str, num_end_chars, false,
+                        #[cfg(feature = "wchar_conversion")] num_end_pairs,
+                        #[cfg(feature = "line_conversion")] num_end_lines);
                 }
                 // if let Some(end_str) = end_str {
                 //     Self::insert_node_at(cursor, end_str, num_end_chars, false, #[cfg(feature = "wchar_conversion")] num_end_pairs);
@@ -974,8 +2401,153 @@ impl JumpRope {
         }
     }
 
-    fn del_at_cursor(cursor: &mut MutCursor, mut length: usize) {
+    /// Remove the longest run of whole nodes after the cursor which fit inside `max_length`
+    /// characters. Returns the number of characters removed.
+    ///
+    /// The cursor must point to the start of the first node to remove (from the end of the
+    /// previous node). No bytes are moved. We walk the removed nodes once, freeing them as we go,
+    /// then relink every level of the cursor past them. The first removed node's back pointer
+    /// already points to the node before them, so it's copied to the node after them.
+    unsafe fn del_whole_nodes_at_cursor(cursor: &mut MutCursor<N, A>, max_length: usize) -> usize {
+        let head_height = cursor.head_height();
+
+        // The first node at each level after the removed nodes, and its distance from the cursor.
+        // This starts as the current next node at each level. Each removed node then replaces the
+        // entries for the levels it's linked into.
+        let mut new_nexts = [SkipEntry::<N>::new(); MAX_HEIGHT + 1];
+        for (i, (c, n)) in cursor.inner[..head_height].iter().zip(&mut new_nexts[..head_height]).enumerate() {
+            let next = (*c.node).nexts[i];
+            *n = SkipEntry {
+                node: next.node,
+                skip_chars: next.skip_chars - c.skip_chars,
+                #[cfg(feature = "wchar_conversion")]
+                skip_pairs: next.skip_pairs - c.skip_pairs,
+                #[cfg(feature = "line_conversion")]
+                skip_lines: next.skip_lines - c.skip_lines,
+                #[cfg(feature = "changes")]
+                skip_bytes: next.skip_bytes - c.skip_bytes,
+            };
+        }
+
+        let first = new_nexts[0].node;
+        debug_assert!(!first.is_null());
+        let prev = (*first).prev;
+        debug_assert!(std::ptr::eq(prev, cursor.inner[0].node)
+            || (prev.is_null() && cursor.is_head(cursor.inner[0].node)));
+
+        // The size of the removed nodes so far. (The node pointer is unused.)
+        let mut removed = SkipEntry::<N>::new();
+        let mut node = first;
+        while !node.is_null() && removed.skip_chars + (*node).num_chars() <= max_length {
+            for (i, s) in (*node).nexts().iter().enumerate() {
+                new_nexts[i] = SkipEntry {
+                    node: s.node,
+                    skip_chars: removed.skip_chars + s.skip_chars,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: removed.skip_pairs + s.skip_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: removed.skip_lines + s.skip_lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: removed.skip_bytes + s.skip_bytes,
+                };
+            }
+
+            let s = *(*node).first_next();
+            removed.skip_chars += s.skip_chars;
+            #[cfg(feature = "wchar_conversion")] {
+                removed.skip_pairs += s.skip_pairs;
+            }
+            #[cfg(feature = "line_conversion")] {
+                removed.skip_lines += s.skip_lines;
+            }
+            #[cfg(feature = "changes")] {
+                removed.skip_bytes += s.skip_bytes;
+            }
+
+            *cursor.num_bytes -= (*node).str.len_bytes();
+            cursor.free_list.free(node);
+            node = s.node;
+        }
+        debug_assert!(removed.skip_chars > 0);
+
+        if !node.is_null() { (*node).prev = prev; }
+        for (i, (c, next)) in cursor.inner[..head_height].iter().zip(&new_nexts[..head_height]).enumerate() {
+            (*c.node).nexts[i] = SkipEntry {
+                node: next.node,
+                skip_chars: c.skip_chars + next.skip_chars - removed.skip_chars,
+                #[cfg(feature = "wchar_conversion")]
+                skip_pairs: c.skip_pairs + next.skip_pairs - removed.skip_pairs,
+                #[cfg(feature = "line_conversion")]
+                skip_lines: c.skip_lines + next.skip_lines - removed.skip_lines,
+                #[cfg(feature = "changes")]
+                skip_bytes: c.skip_bytes + next.skip_bytes - removed.skip_bytes,
+            };
+        }
+
+        removed.skip_chars
+    }
+
+    /// The byte offset of the cursor from the start of the rope. Bytes are only indexed with the
+    /// `changes` feature. Without it, this walks every node before the cursor.
+    #[cfg(feature = "changes")]
+    unsafe fn byte_pos_at_cursor(cursor: &MutCursor<N, A>) -> usize {
+        cursor.global_byte_pos()
+    }
+
+    #[cfg(not(feature = "changes"))]
+    unsafe fn byte_pos_at_cursor(cursor: &MutCursor<N, A>) -> usize {
+        let here = cursor.here_ptr();
+        let mut bytes = (*here).str.count_bytes(cursor.local_char_pos());
+        let mut node = cursor.inner[MAX_HEIGHT].node;
+        while node != here {
+            bytes += (*node).str.len_bytes();
+            node = (*node).first_next().node;
+        }
+        bytes
+    }
+
+    /// Copies the next len characters after the cursor.
+    #[cfg(any(feature = "history", feature = "edit_log"))]
+    unsafe fn text_at_cursor(cursor: &MutCursor<N, A>, mut len: usize) -> String {
+        let mut text = String::new();
+        let mut node = cursor.here_ptr();
+        let mut skip = cursor.local_char_pos();
+        while len > 0 {
+            let n = &*node;
+            for c in n.as_str_1().chars().chain(n.as_str_2().chars()).skip(skip).take(len) {
+                text.push(c);
+                len -= 1;
+            }
+            skip = 0;
+            node = n.first_next().node;
+        }
+        text
+    }
+
+    fn del_at_cursor(cursor: &mut MutCursor<N, A>, mut length: usize) {
         if length == 0 { return; }
+        #[cfg(feature = "marks")] {
+            let pos = cursor.global_char_pos();
+            cursor.marks.remove(pos..pos + length);
+        }
+        #[cfg(feature = "history")]
+        if cursor.history.is_some() {
+            let pos = cursor.global_char_pos();
+            let text = unsafe { Self::text_at_cursor(cursor, length) };
+            cursor.history.as_mut().unwrap().record(Edit::Delete { pos, text });
+        }
+        #[cfg(feature = "edit_log")]
+        if cursor.edit_log.is_some() {
+            let pos = cursor.global_char_pos();
+            let deleted = unsafe { Self::text_at_cursor(cursor, length) };
+            cursor.edit_log.as_mut().unwrap().push(LogEntry { pos, deleted, inserted: String::new() });
+        }
+        // The number of bytes removed is only known at the end.
+        #[cfg(feature = "changes")]
+        let change = cursor.changes.is_some().then(|| ChangeEvent {
+            char_pos: cursor.global_char_pos(), chars_removed: length, chars_inserted: 0,
+            byte_pos: unsafe { Self::byte_pos_at_cursor(cursor) }, bytes_removed: *cursor.num_bytes, bytes_inserted: 0,
+        });
         let mut offset_chars = cursor.local_char_pos();
         let mut node = cursor.here_ptr();
         unsafe {
@@ -990,6 +2562,14 @@ impl JumpRope {
                 }
 
                 let num_chars = (*node).num_chars();
+                if offset_chars == 0 && length >= num_chars && !cursor.is_head(node) {
+                    // The deleted range covers this entire node (and maybe more after it).
+                    length -= Self::del_whole_nodes_at_cursor(cursor, length);
+                    node = cursor.here_ptr();
+                    offset_chars = cursor.local_char_pos();
+                    continue;
+                }
+
                 let removed = std::cmp::min(length, num_chars - offset_chars);
                 assert!(removed > 0);
 
@@ -997,39 +2577,28 @@ impl JumpRope {
                 #[cfg(feature = "wchar_conversion")]
                     let removed_pairs = (*node).str.count_surrogate_pairs(offset_chars + removed)
                     - (*node).str.count_surrogate_pairs(offset_chars);
+                #[cfg(feature = "line_conversion")]
+                    let removed_lines = (*node).str.count_lines(offset_chars + removed)
+                    - (*node).str.count_lines(offset_chars);
 
+                // Whole nodes were removed above, so this only trims part of the node (or the
+                // head, which is never removed).
                 let height = (*node).height as usize;
-                if removed < num_chars || cursor.is_head(node) {
-                    // Just trim the node down.
-                    let s = &mut (*node).str;
-                    let removed_bytes = s.remove_chars(offset_chars, removed);
-                    *cursor.num_bytes -= removed_bytes;
-
-                    for s in (*node).nexts_mut() {
-                        s.skip_chars -= removed;
-                        #[cfg(feature = "wchar_conversion")] {
-                            s.skip_pairs -= removed_pairs;
-                        }
+                let s = &mut (*node).str;
+                let removed_bytes = s.remove_chars(offset_chars, removed);
+                *cursor.num_bytes -= removed_bytes;
+
+                for s in (*node).nexts_mut() {
+                    s.skip_chars -= removed;
+                    #[cfg(feature = "wchar_conversion")] {
+                        s.skip_pairs -= removed_pairs;
                     }
-                } else {
-                    // Remove the node from the skip list. This works because the cursor must be
-                    // pointing from the previous element to the start of this element.
-                    assert_ne!(cursor.inner[0].node, node);
-
-                    for i in 0..(*node).height as usize {
-                        let s = &mut (*cursor.inner[i].node).nexts_mut()[i];
-                        s.node = (*node).nexts[i].node;
-                        s.skip_chars += (*node).nexts[i].skip_chars - removed;
-                        #[cfg(feature = "wchar_conversion")] {
-                            s.skip_pairs += (*node).nexts[i].skip_pairs - removed_pairs;
-                        }
+                    #[cfg(feature = "line_conversion")] {
+                        s.skip_lines -= removed_lines;
+                    }
+                    #[cfg(feature = "changes")] {
+                        s.skip_bytes -= removed_bytes;
                     }
-
-                    *cursor.num_bytes -= (*node).str.len_bytes();
-                    let next = (*node).first_next().node;
-                    // Node::free(node);
-                    drop(Box::from_raw(node));
-                    node = next;
                 }
 
                 for i in height..cursor.head_height() {
@@ -1038,53 +2607,246 @@ impl JumpRope {
                     #[cfg(feature = "wchar_conversion")] {
                         s.skip_pairs -= removed_pairs;
                     }
+                    #[cfg(feature = "line_conversion")] {
+                        s.skip_lines -= removed_lines;
+                    }
+                    #[cfg(feature = "changes")] {
+                        s.skip_bytes -= removed_bytes;
+                    }
                 }
 
                 length -= removed;
             }
+
+            // Deletes can leave nodes nearly empty. Merge them with their neighbours when we can.
+            let here = cursor.here_ptr();
+            if !Self::merge_underfull(cursor, here) {
+                let next = (*here).first_next().node;
+                if !next.is_null() {
+                    Self::merge_underfull(cursor, next);
+                }
+            }
+        }
+
+        #[cfg(feature = "changes")]
+        if let Some(mut event) = change {
+            event.bytes_removed -= *cursor.num_bytes;
+            cursor.changes.as_mut().unwrap().push(event);
         }
     }
 
-    fn eq_str(&self, mut other: &str) -> bool {
-        if self.len_bytes() != other.len() { return false; }
+    /// Find the entry at the given level which points to target. The target must be after the
+    /// cursor, and usually it's only a step or two away.
+    unsafe fn entry_pointing_to(cursor: &MutCursor<N, A>, height: usize, target: *mut Node<N>) -> *mut SkipEntry<N> {
+        let mut e: *mut SkipEntry<N> = &mut (*cursor.inner[height].node).nexts[height];
+        while (*e).node != target {
+            e = &mut (*(*e).node).nexts[height];
+        }
+        e
+    }
 
-        for s in self.substrings() {
+    /// If the node has fewer than UNDERFULL_BYTES bytes, try to merge the node after it into it.
+    /// Returns true if the nodes were merged.
+    unsafe fn merge_underfull(cursor: &mut MutCursor<N, A>, node: *mut Node<N>) -> bool {
+        (*node).str.len_bytes() < Self::UNDERFULL_BYTES && Self::merge_next_into(cursor, node)
+    }
+
+    /// Move the contents of the node after this node into it, and remove the next node from the
+    /// rope. Does nothing if there's no next node, or if the contents won't fit. Returns true if
+    /// the nodes were merged.
+    ///
+    /// The node must be at or after the cursor (with no nodes in between).
+    unsafe fn merge_next_into(cursor: &mut MutCursor<N, A>, node: *mut Node<N>) -> bool {
+        let next = (*node).first_next().node;
+        if next.is_null() || (*node).str.len_bytes() + (*next).str.len_bytes() > N {
+            return false;
+        }
+
+        let n = &mut (*node).str;
+        n.move_gap(n.len_bytes());
+        n.insert_in_gap((*next).as_str_1());
+        n.insert_in_gap((*next).as_str_2());
+
+        // Anything pointing to the next node now skips over it (and over its content, which has
+        // been moved into the previous node).
+        for i in 0..(*next).height as usize {
+            let e = &mut *Self::entry_pointing_to(cursor, i, next);
+            let s = &(*next).nexts[i];
+            e.node = s.node;
+            e.skip_chars += s.skip_chars;
+            #[cfg(feature = "wchar_conversion")] {
+                e.skip_pairs += s.skip_pairs;
+            }
+            #[cfg(feature = "line_conversion")] {
+                e.skip_lines += s.skip_lines;
+            }
+            #[cfg(feature = "changes")] {
+                e.skip_bytes += s.skip_bytes;
+            }
+        }
+
+        cursor.set_prev((*next).first_next().node, node);
+        cursor.free_list.free(next);
+        true
+    }
+
+    /// Move as much content as will fit from the start of the next node into the end of this
+    /// node. The node must be at or after the cursor (with no nodes in between).
+    unsafe fn move_prefix_of_next_into(cursor: &mut MutCursor<N, A>, node: *mut Node<N>) {
+        let next = (*node).first_next().node;
+        if next.is_null() { return; }
+
+        let n = &mut (*next).str;
+        n.move_gap(n.len_bytes());
+        let s = n.start_as_str();
+        let cut = floor_char_boundary(s, N - (*node).str.len_bytes());
+        if cut == 0 { return; }
+
+        let moved = &s[..cut];
+        let moved_chars = count_chars(moved);
+        #[cfg(feature = "wchar_conversion")]
+        let moved_pairs = count_utf16_surrogates(moved);
+        #[cfg(feature = "line_conversion")]
+        let moved_lines = count_lines(moved);
+
+        let dest = &mut (*node).str;
+        dest.move_gap(dest.len_bytes());
+        dest.insert_in_gap(moved);
+        (*next).str.remove_chars(0, moved_chars);
+
+        // The next node now starts later, and is shorter by the same amount.
+        for i in 0..(*next).height as usize {
+            let e = &mut *Self::entry_pointing_to(cursor, i, next);
+            let s = &mut (*next).nexts[i];
+            e.skip_chars += moved_chars;
+            s.skip_chars -= moved_chars;
+            #[cfg(feature = "wchar_conversion")] {
+                e.skip_pairs += moved_pairs;
+                s.skip_pairs -= moved_pairs;
+            }
+            #[cfg(feature = "line_conversion")] {
+                e.skip_lines += moved_lines;
+                s.skip_lines -= moved_lines;
+            }
+            #[cfg(feature = "changes")] {
+                e.skip_bytes += moved.len();
+                s.skip_bytes -= moved.len();
+            }
+        }
+    }
+
+    fn eq_str(&self, other: &str) -> bool {
+        if self.len_bytes() != other.len() { return false; }
+
+        // Compare bytes, since a character in other can straddle the end of a node.
+        let mut other = other.as_bytes();
+        for s in self.substrings() {
             let (start, rem) = other.split_at(s.len());
-            if start != s { return false; }
+            if start != s.as_bytes() { return false; }
             other = rem;
         }
 
         true
     }
+
+    /// Remove the first chunk of content from the rope and return it. This is the head's content
+    /// if it has any. Otherwise the first node is unlinked and deallocated straight away, rather
+    /// than being kept in the free list.
+    pub(super) fn take_first_chunk(&mut self) -> Option<String> {
+        #[cfg(feature = "cow")]
+        self.make_unique();
+        self.cursor_valid = false;
+
+        if !self.head.str.is_empty() {
+            // Empty the head in place. (Deleting its content with remove() would refill the head
+            // from the next node.)
+            let s = self.head.str.to_string();
+            let num_chars = self.head.num_chars();
+            #[cfg(feature = "wchar_conversion")]
+            let num_pairs = self.head.num_surrogate_pairs();
+            #[cfg(feature = "line_conversion")]
+            let num_lines = self.head.num_lines();
+            for e in self.head.nexts_mut() {
+                e.skip_chars -= num_chars;
+                #[cfg(feature = "wchar_conversion")] {
+                    e.skip_pairs -= num_pairs;
+                }
+                #[cfg(feature = "line_conversion")] {
+                    e.skip_lines -= num_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    e.skip_bytes -= s.len();
+                }
+            }
+            self.head.str = GapBuffer::new();
+            self.num_bytes -= s.len();
+            return Some(s);
+        }
+
+        let node = self.head.first_next().node;
+        if node.is_null() { return None; }
+
+        unsafe {
+            let s = (*node).str.to_string();
+            let height = (*node).height as usize;
+            // The head is empty, so entries which skip over the node only need to skip its content.
+            for (i, e) in self.head.nexts_mut().iter_mut().enumerate() {
+                if i < height {
+                    *e = (*node).nexts[i];
+                } else {
+                    e.skip_chars -= (*node).num_chars();
+                    #[cfg(feature = "wchar_conversion")] {
+                        e.skip_pairs -= (*node).num_surrogate_pairs();
+                    }
+                    #[cfg(feature = "line_conversion")] {
+                        e.skip_lines -= (*node).num_lines();
+                    }
+                    #[cfg(feature = "changes")] {
+                        e.skip_bytes -= s.len();
+                    }
+                }
+            }
+
+            let next = (*node).first_next().node;
+            if !next.is_null() { (*next).prev = null_mut(); }
+            self.num_bytes -= (*node).str.len_bytes();
+            self.free_list.dealloc_node(node);
+            Some(s)
+        }
+    }
 }
 
-impl Default for JumpRope {
+impl<const N: usize> Default for JumpRopeSized<N> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Drop for JumpRope {
+impl<const N: usize, A: Allocator> Drop for JumpRopeSized<N, A> {
     fn drop(&mut self) {
         let mut node = self.head.first_next().node;
+        // Nodes still used by a fork are left to it.
+        #[cfg(feature = "cow")]
+        if !unsafe { self.release_nodes_on_drop() } { node = null_mut(); }
         unsafe {
             while !node.is_null() {
                 let next = (*node).first_next().node;
                 // Node::free(node);
-                drop(Box::from_raw(node));
+                self.free_list.dealloc_node(node);
                 node = next;
             }
         }
+        self.free_list.release_all();
     }
 }
 
-impl<S: AsRef<str>> From<S> for JumpRope {
+impl<S: AsRef<str>, const N: usize> From<S> for JumpRopeSized<N> {
     fn from(str: S) -> Self {
-        JumpRope::new_from_str(str.as_ref())
+        Self::new_from_str(str.as_ref())
     }
 }
 
-impl PartialEq for JumpRope {
+impl<const N: usize, A: Allocator> PartialEq for JumpRopeSized<N, A> {
     // This is quite complicated. It would be cleaner to just write a bytes
     // iterator, then iterate over the bytes of both strings comparing along the
     // way.
@@ -1094,7 +2856,7 @@ impl PartialEq for JumpRope {
     // an iterator over &str. Then the rope vs rope comparison would be trivial,
     // but also we could add comparison functions with a single &str and stuff
     // very easily.
-    fn eq(&self, other: &JumpRope) -> bool {
+    fn eq(&self, other: &Self) -> bool {
         if self.num_bytes != other.num_bytes
                 || self.len_chars() != other.len_chars() {
             return false
@@ -1129,9 +2891,9 @@ impl PartialEq for JumpRope {
         true
     }
 }
-impl Eq for JumpRope {}
+impl<const N: usize, A: Allocator> Eq for JumpRopeSized<N, A> {}
 
-impl Debug for JumpRope {
+impl<const N: usize, A: Allocator> Debug for JumpRopeSized<N, A> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_list()
             .entries(self.substrings())
@@ -1139,7 +2901,7 @@ impl Debug for JumpRope {
     }
 }
 
-impl Display for JumpRope {
+impl<const N: usize, A: Allocator> Display for JumpRopeSized<N, A> {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         for s in self.substrings() {
             f.write_str(s)?;
@@ -1150,50 +2912,177 @@ impl Display for JumpRope {
 
 // I don't know why I need all three of these, but I do.
 
-impl<T: AsRef<str>> PartialEq<T> for JumpRope {
+impl<T: AsRef<str>, const N: usize, A: Allocator> PartialEq<T> for JumpRopeSized<N, A> {
     fn eq(&self, other: &T) -> bool {
         self.eq_str(other.as_ref())
     }
 }
 
 // Needed for assert_eq!(&rope, "Hi there");
-impl PartialEq<str> for JumpRope {
+impl<const N: usize, A: Allocator> PartialEq<str> for JumpRopeSized<N, A> {
     fn eq(&self, other: &str) -> bool {
         self.eq_str(other)
     }
 }
 
 // Needed for assert_eq!(&rope, String::from("Hi there"));
-impl PartialEq<String> for &JumpRope {
+impl<const N: usize, A: Allocator> PartialEq<String> for &JumpRopeSized<N, A> {
     fn eq(&self, other: &String) -> bool {
         self.eq_str(other.as_str())
     }
 }
 
-impl<'a> Extend<&'a str> for JumpRope {
+impl<'a, const N: usize, A: Allocator> Extend<&'a str> for JumpRopeSized<N, A> {
     fn extend<T: IntoIterator<Item = &'a str>>(&mut self, iter: T) {
+        #[cfg(feature = "history")]
+        self.begin_undo_group();
         let mut cursor = self.mut_cursor_at_end();
         iter.into_iter().for_each(|s| {
             Self::insert_at_cursor(&mut cursor, s);
         });
+        #[cfg(feature = "history")]
+        self.end_undo_group();
     }
 }
 
-impl Clone for JumpRope {
+/// Cloning a rope copies every node as-is (including its height and gap position) in a single
+/// pass, without searching or recounting any characters.
+///
+/// Clones don't share nodes with the original. With the `cow` feature, `rope.fork()` makes a copy
+/// which shares them instead.
+impl<const N: usize, A: Allocator + Clone> Clone for JumpRopeSized<N, A> {
     fn clone(&self) -> Self {
-        // This method could be a little bit more efficient, but I think improving clone()
-        // performance isn't worth the extra effort.
-        let mut r = JumpRope::new();
-        let mut cursor = r.mut_cursor_at_start();
-        for node in self.node_iter_at_start() {
-            JumpRope::insert_at_cursor(&mut cursor, node.as_str_1());
-            JumpRope::insert_at_cursor(&mut cursor, node.as_str_2());
-        }
+        let mut r = self.clone_head();
+        r.copy_state_from(self);
+        unsafe { r.copy_nodes(); }
+        r
+    }
+}
+
+impl<const N: usize, A: Allocator + Clone> JumpRopeSized<N, A> {
+    /// Make a new rope with the same head (and length) as this one, without any of its marks,
+    /// history and so on. Its entries still point to this rope's nodes, so the caller must copy or
+    /// share them before the new rope is used or dropped.
+    pub(super) fn clone_head(&self) -> Self {
+        let mut r = Self::new_in(self.free_list.alloc.clone());
+        r.num_bytes = self.num_bytes;
+        r.head.str = self.head.str.clone();
+        r.head.height = self.head.height;
+        r.head.nexts = self.head.nexts;
         r
     }
+
+    /// Copy everything except the content from another rope: its marks, history, queued changes,
+    /// edit log and whether it caches hashes.
+    #[allow(unused)]
+    pub(super) fn copy_state_from(&mut self, other: &Self) {
+        #[cfg(feature = "marks")] {
+            self.marks = other.marks.clone();
+        }
+        #[cfg(feature = "history")] {
+            self.history = other.history.clone();
+        }
+        #[cfg(feature = "changes")] {
+            self.changes = other.changes.clone();
+        }
+        #[cfg(feature = "edit_log")] {
+            self.edit_log = other.edit_log.clone();
+        }
+        #[cfg(feature = "content_hash")] {
+            self.cache_hashes = other.cache_hashes;
+        }
+    }
+}
+
+impl<const N: usize, A: Allocator + Clone> JumpRopeSized<N, A> {
+    /// Save the rope's current content, so speculative edits (like IME composition or a preview)
+    /// can be reverted with [`restore`](Self::restore). See [`Checkpoint`] for what this costs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// let checkpoint = rope.checkpoint();
+    /// rope.replace(0..2, "goodbye");
+    /// rope.restore(&checkpoint);
+    /// assert_eq!(rope, "hi there");
+    /// ```
+    pub fn checkpoint(&self) -> Checkpoint<N, A> {
+        #[cfg(not(feature = "cow"))] {
+            Checkpoint { content: self.to_string().into(), len_chars: self.len_chars(), phantom: PhantomData }
+        }
+        #[cfg(feature = "cow")] {
+            Checkpoint { rope: self.share() }
+        }
+    }
+
+    /// Take an immutable snapshot of the rope's current content, to read later (like an undo stack
+    /// or a background save). This is the same as [`checkpoint`](Self::checkpoint), so a snapshot
+    /// can be restored too.
+    ///
+    /// Snapshots are cheap to clone, but they don't share the parts of the rope which haven't been
+    /// edited. Without the `cow` feature, taking a snapshot copies the content (*O(n)*). With
+    /// `cow`, taking one is *O(1)*, but the next edit to the rope copies all of its nodes. So
+    /// taking a snapshot before every edit costs *O(n)* per edit either way. Sharing just the
+    /// unedited nodes doesn't work with a skip list. See `rope.fork()` for why.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// let snapshot = rope.snapshot();
+    /// rope.remove(0..3);
+    /// assert_eq!(snapshot, "hi there");
+    /// assert_eq!(snapshot.len_chars(), 8);
+    /// ```
+    pub fn snapshot(&self) -> RopeSnapshot<N, A> {
+        self.checkpoint()
+    }
+
+    /// Replace the rope's content with the content saved in a checkpoint. Marks move, and the
+    /// history and change events record the restore, as if the whole rope was replaced with
+    /// [`set_content`](Self::set_content).
+    ///
+    /// The checkpoint can be restored any number of times, and into any rope of the same type.
+    pub fn restore(&mut self, checkpoint: &Checkpoint<N, A>) {
+        #[cfg(not(feature = "cow"))]
+        self.set_content(&checkpoint.content);
+        #[cfg(feature = "cow")]
+        self.swap_in(checkpoint.rope.share());
+    }
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Replace every node after the head with a copy, allocated from this rope's allocator. The
+    /// original nodes are left untouched (for whichever rope still uses them).
+    ///
+    /// Safety: The head's entries must point to valid nodes. They usually belong to another rope.
+    pub(super) unsafe fn copy_nodes(&mut self) {
+        // Pointers in the head are replaced as their targets are copied. Pointers which are null
+        // at the end of the list stay null.
+        let mut src = self.head.first_next().node as *const Node<N>;
+        let head: *mut Node<N> = &mut self.head;
+        // The most recently copied node at each height.
+        let mut prev = [head; MAX_HEIGHT+1];
+        while !src.is_null() {
+            let node = self.free_list.alloc_node(Node {
+                str: (*src).str.clone(),
+                height: (*src).height,
+                prev: if prev[0] == head { null_mut() } else { prev[0] },
+                nexts: (*src).nexts,
+            });
+            for (i, p) in prev[..(*node).height as usize].iter_mut().enumerate() {
+                (**p).nexts[i].node = node;
+                *p = node;
+            }
+            src = (*src).first_next().node;
+        }
+    }
 }
 
-impl JumpRope {
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
     /// Insert new content into the rope. The content is inserted at the specified unicode character
     /// offset, which is different from a byte offset for non-ASCII characters.
     ///
@@ -1213,18 +3102,163 @@ impl JumpRope {
         if contents.is_empty() { return; }
         pos = std::cmp::min(pos, self.len_chars());
 
-        let mut cursor = self.mut_cursor_at_char(pos, true);
+        let mut cursor = self.mut_cursor_at_char_cached(pos);
 
         Self::insert_at_cursor(&mut cursor, contents);
 
         debug_assert_eq!(cursor.global_char_pos(), pos + count_chars(contents));
         // dbg!(&cursor.0[..self.head.height as usize]);
+        self.cursor_valid = true;
+    }
+
+    /// Insert new content into the rope at the specified unicode character offset. Unlike
+    /// [`insert`](Self::insert), this method returns an error if the position is past the end of
+    /// the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("--");
+    /// assert_eq!(rope.try_insert(1, "hi"), Ok(()));
+    /// assert_eq!(rope.try_insert(100, "hi"), Err(RopeError::PositionOutOfBounds { pos: 100, len: 4 }));
+    /// assert_eq!(rope, "-hi-");
+    /// ```
+    pub fn try_insert(&mut self, pos: usize, contents: &str) -> Result<(), RopeError> {
+        let len = self.len_chars();
+        if pos > len {
+            return Err(RopeError::PositionOutOfBounds { pos, len });
+        }
+
+        self.insert(pos, contents);
+        Ok(())
+    }
+
+    /// Insert new content like [`try_insert`](Self::try_insert), and return the range of bytes
+    /// which now holds the inserted content. This is useful for invalidating just the changed part
+    /// of a document (like when syntax highlighting).
+    ///
+    /// The rope only indexes content by byte with the `changes` feature. Without it, finding the
+    /// byte range takes *O(n)* time (in the number of nodes before the insert).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κ--");
+    /// assert_eq!(rope.insert_reporting(2, "hi"), Ok(3..5));
+    /// assert_eq!(rope, "κ-hi-");
+    /// assert!(rope.insert_reporting(100, "hi").is_err());
+    /// ```
+    pub fn insert_reporting(&mut self, pos: usize, contents: &str) -> Result<Range<usize>, RopeError> {
+        let len = self.len_chars();
+        if pos > len {
+            return Err(RopeError::PositionOutOfBounds { pos, len });
+        }
+
+        let mut cursor = self.mut_cursor_at_char_cached(pos);
+        let start = unsafe { Self::byte_pos_at_cursor(&cursor) };
+        if !contents.is_empty() {
+            Self::insert_at_cursor(&mut cursor, contents);
+        }
+        self.cursor_valid = true;
+        Ok(start..start + contents.len())
+    }
+
+    /// Insert new content into the rope at the specified unicode character offset. If the
+    /// position is past the end of the rope, the content is inserted at the end.
+    ///
+    /// This is currently identical to [`insert`](Self::insert), but it names the lenient behaviour
+    /// explicitly. Prefer [`try_insert`](Self::try_insert) if out of range positions are a bug.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("abc");
+    /// rope.insert_clamped(usize::MAX, "def");
+    /// assert_eq!(rope, "abcdef");
+    /// ```
+    pub fn insert_clamped(&mut self, pos: usize, contents: &str) {
+        self.insert(pos, contents);
+    }
+
+    /// Append a string to the end of the rope, like [`String::push_str`].
+    ///
+    /// Successive appends reuse the cursor from the previous edit, so building a rope by pushing
+    /// to it doesn't need to search the skip list each time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// rope.push_str(" there");
+    /// assert_eq!(rope, "hi there");
+    /// ```
+    pub fn push_str(&mut self, s: &str) {
+        self.insert(self.len_chars(), s);
+    }
+
+    /// Append a character to the end of the rope, like [`String::push`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// rope.push('!');
+    /// assert_eq!(rope, "hi!");
+    /// ```
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Insert a string at the start of the rope. This is the same as `insert(0, s)`, but it starts
+    /// editing at the head of the rope instead of searching for the position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("there");
+    /// rope.prepend("hi ");
+    /// assert_eq!(rope, "hi there");
+    /// ```
+    pub fn prepend(&mut self, s: &str) {
+        if s.is_empty() { return; }
+
+        let mut cursor = self.mut_cursor_at_start();
+        Self::insert_at_cursor(&mut cursor, s);
+
+        debug_assert_eq!(cursor.global_char_pos(), count_chars(s));
+        self.cursor_valid = true;
+    }
+
+    /// Remove the last character from the rope and return it, or `None` if the rope is empty. Like
+    /// [`String::pop`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi↯");
+    /// assert_eq!(rope.pop(), Some('↯'));
+    /// assert_eq!(rope, "hi");
+    /// ```
+    pub fn pop(&mut self) -> Option<char> {
+        let pos = self.len_chars().checked_sub(1)?;
+        let c = self.char_at(pos);
+        debug_assert!(c.is_some());
+        self.remove(pos..pos + 1);
+        c
     }
 
     /// Delete a span of unicode characters from the rope. The span is specified in unicode
     /// characters, not bytes.
     ///
-    /// Any attempt to delete past the end of the rope will be silently ignored.
+    /// Any attempt to delete past the end of the rope will be silently ignored. Use
+    /// [`try_remove`](Self::try_remove) to find out when the range starts past the end.
     ///
     /// # Example
     ///
@@ -1241,10 +3275,67 @@ impl JumpRope {
         if range.start >= range.end { return; }
 
         // We need to stick_end so we can delete entries.
-        let mut cursor = self.mut_cursor_at_char(range.start, true);
+        let mut cursor = self.mut_cursor_at_char_cached(range.start);
         Self::del_at_cursor(&mut cursor, range.end - range.start);
 
         debug_assert_eq!(cursor.global_char_pos(), range.start);
+        self.cursor_valid = true;
+    }
+
+    /// Delete a span of unicode characters from the rope. Unlike [`remove`](Self::remove), this
+    /// method returns an error if the start of the range is past the end of the rope. Like
+    /// `remove`, a range which runs past the end of the rope deletes everything up to the end.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Whoa dawg!");
+    /// assert_eq!(rope.try_remove(4..9), Ok(()));
+    /// assert_eq!(rope.try_remove(6..7), Err(RopeError::PositionOutOfBounds { pos: 6, len: 5 }));
+    /// assert_eq!(rope.try_remove(4..100), Ok(()));
+    /// assert_eq!(rope, "Whoa");
+    /// ```
+    pub fn try_remove(&mut self, range: Range<usize>) -> Result<(), RopeError> {
+        let len = self.len_chars();
+        if range.start > len {
+            return Err(RopeError::PositionOutOfBounds { pos: range.start, len });
+        }
+
+        self.remove(range);
+        Ok(())
+    }
+
+    /// Delete a span of unicode characters like [`try_remove`](Self::try_remove), and return the
+    /// range of bytes which held the deleted content (from before the deletion).
+    ///
+    /// Like [`insert_reporting`](Self::insert_reporting), finding the byte range takes *O(n)* time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε!");
+    /// assert_eq!(rope.remove_reporting(1..3), Ok(2..6));
+    /// assert_eq!(rope, "κμε!");
+    /// // Ranges past the end are clamped, like remove.
+    /// assert_eq!(rope.remove_reporting(3..100), Ok(6..7));
+    /// ```
+    pub fn remove_reporting(&mut self, mut range: Range<usize>) -> Result<Range<usize>, RopeError> {
+        let len = self.len_chars();
+        if range.start > len {
+            return Err(RopeError::PositionOutOfBounds { pos: range.start, len });
+        }
+        range.end = range.end.clamp(range.start, len);
+
+        let bytes_before = self.num_bytes;
+        let mut cursor = self.mut_cursor_at_char_cached(range.start);
+        let start = unsafe { Self::byte_pos_at_cursor(&cursor) };
+        if !range.is_empty() {
+            Self::del_at_cursor(&mut cursor, range.len());
+        }
+        self.cursor_valid = true;
+        Ok(start..start + bytes_before - self.num_bytes)
     }
 
     /// Replace the specified range with new content. This is equivalent to calling
@@ -1262,8 +3353,13 @@ impl JumpRope {
         let len = self.len_chars();
         let pos = usize::min(range.start, len);
         let del_len = usize::min(range.end, len) - pos;
+        // Editors send a lot of empty replacements. Skip finding a cursor for them.
+        if del_len == 0 && content.is_empty() { return; }
 
-        let mut cursor = self.mut_cursor_at_char(pos, true);
+        // The delete and insert are undone together.
+        #[cfg(feature = "history")]
+        self.begin_undo_group();
+        let mut cursor = self.mut_cursor_at_char_cached(pos);
         if del_len > 0 {
             Self::del_at_cursor(&mut cursor, del_len);
         }
@@ -1272,100 +3368,1051 @@ impl JumpRope {
         }
 
         debug_assert_eq!(cursor.global_char_pos(), pos + count_chars(content));
+        self.cursor_valid = true;
+        #[cfg(feature = "history")]
+        self.end_undo_group();
     }
 
-    /// Get the number of bytes used for the UTF8 representation of the rope. This will always match
-    /// the .len() property of the equivalent String.
-    ///
-    /// Note: This is only useful in specific situations - like preparing a byte buffer for saving
-    /// or sending over the internet. In many cases it is preferable to use
-    /// [`len_chars`](Self::len_chars).
+    /// Shortens the rope to `new_len` unicode characters. If the rope is already shorter than that,
+    /// this does nothing.
     ///
     /// # Example
     ///
     /// ```
     /// # use jumprope::*;
-    /// let str = "κόσμε"; // "Cosmos" in ancient greek
-    /// assert_eq!(str.len(), 11); // 11 bytes over the wire
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// rope.truncate(2);
+    /// assert_eq!(rope, "κό");
+    /// ```
+    pub fn truncate(&mut self, new_len: usize) {
+        self.remove(new_len..self.len_chars());
+    }
+
+    /// Shortens the rope to `new_byte_len` bytes. If the rope is already shorter than that, this
+    /// does nothing.
+    ///
+    /// Returns an error (and leaves the rope unchanged) if `new_byte_len` is in the middle of a
+    /// character.
+    ///
+    /// # Example
     ///
-    /// let rope = JumpRope::from(str);
-    /// assert_eq!(rope.len_bytes(), str.len());
     /// ```
-    pub fn len_bytes(&self) -> usize { self.num_bytes }
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.truncate_bytes(3), Err(RopeError::InvalidCodepoint { pos: 3 }));
+    /// rope.truncate_bytes(4).unwrap();
+    /// assert_eq!(rope, "κό");
+    /// ```
+    pub fn truncate_bytes(&mut self, new_byte_len: usize) -> Result<(), RopeError> {
+        if new_byte_len < self.len_bytes() {
+            let new_len = self.byte_to_char(new_byte_len)?;
+            self.truncate(new_len);
+        }
+        Ok(())
+    }
 
-    /// Returns `true` if the rope contains no elements.
-    pub fn is_empty(&self) -> bool { self.num_bytes == 0 }
+    /// Remove all content from the rope. The rope's nodes are kept (up to a limit) and reused by
+    /// subsequent inserts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hi Mike!");
+    /// rope.clear();
+    /// assert!(rope.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        #[cfg(feature = "marks")]
+        self.marks.remove(0..self.len_chars());
+        #[cfg(feature = "history")]
+        if self.history.is_some() && !self.is_empty() {
+            let text = self.to_string();
+            self.record_edit(Edit::Delete { pos: 0, text });
+        }
+        #[cfg(feature = "changes")]
+        if !self.is_empty() {
+            self.record_change(ChangeEvent {
+                char_pos: 0, chars_removed: self.len_chars(), chars_inserted: 0,
+                byte_pos: 0, bytes_removed: self.num_bytes, bytes_inserted: 0,
+            });
+        }
+        #[cfg(feature = "edit_log")]
+        if self.edit_log.is_some() && !self.is_empty() {
+            let text = self.to_string();
+            self.log_edit(0, text, String::new());
+        }
+        #[cfg(feature = "cow")]
+        self.leave_shared_nodes();
+        let mut node = self.head.first_next().node;
+        while !node.is_null() {
+            unsafe {
+                let next = (*node).first_next().node;
+                self.free_list.free(node);
+                node = next;
+            }
+        }
 
-    pub fn check(&self) {
-        assert!(self.head.height >= 1);
-        assert!(self.head.height < MAX_HEIGHT_U8 + 1);
+        self.head = Node::new_with_height(1, "");
+        self.num_bytes = 0;
+        self.cursor_valid = false;
+    }
 
-        let skip_over = &self.head.nexts[self.head.height as usize - 1];
-        // println!("Skip over skip chars {}, num bytes {}", skip_over.skip_chars, self.num_bytes);
-        assert!(skip_over.skip_chars <= self.num_bytes as usize);
-        #[cfg(feature = "wchar_conversion")] {
-            assert!(skip_over.skip_pairs <= skip_over.skip_chars);
+    /// Replace the entire contents of the rope with `s`. The rope's existing nodes are filled with
+    /// the new content in place (keeping their heights), and nodes are only allocated or freed if
+    /// the new content needs more or fewer of them. So reloading a buffer with content of a
+    /// similar size is much cheaper than creating a new rope.
+    ///
+    /// This leaves every node (except the last) full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// rope.set_content("goodbye");
+    /// assert_eq!(rope, "goodbye");
+    /// ```
+    pub fn set_content(&mut self, s: &str) {
+        self.cursor_valid = false;
+        // Marks move (and the history records this) as if the whole rope was replaced.
+        #[cfg(feature = "marks")]
+        let mut marks = std::mem::take(&mut self.marks);
+        #[cfg(feature = "marks")] {
+            marks.remove(0..self.len_chars());
+            marks.insert(0, count_chars(s));
         }
-        assert!(skip_over.node.is_null());
+        #[cfg(feature = "history")]
+        let history = self.history.take().map(|mut h| {
+            h.begin_group();
+            if !self.is_empty() { h.record(Edit::Delete { pos: 0, text: self.to_string() }); }
+            if !s.is_empty() { h.record(Edit::Insert { pos: 0, len: count_chars(s) }); }
+            h.end_group();
+            h
+        });
+        #[cfg(feature = "changes")]
+        let changes = self.changes.take().map(|mut changes| {
+            if !self.is_empty() || !s.is_empty() {
+                changes.push(ChangeEvent {
+                    char_pos: 0, chars_removed: self.len_chars(), chars_inserted: count_chars(s),
+                    byte_pos: 0, bytes_removed: self.num_bytes, bytes_inserted: s.len(),
+                });
+            }
+            changes
+        });
+        #[cfg(feature = "edit_log")]
+        let edit_log = self.edit_log.take().map(|mut log| {
+            if !self.is_empty() || !s.is_empty() {
+                log.push(LogEntry { pos: 0, deleted: self.to_string(), inserted: s.into() });
+            }
+            log
+        });
+        // Nodes shared with a fork can't be refilled, so the content goes in new nodes instead.
+        #[cfg(feature = "cow")]
+        self.leave_shared_nodes();
+        let head_height = self.head.height as usize;
+        let head: *mut Node<N> = &mut self.head;
 
-        // The offsets store the total distance travelled since the start.
-        let mut iter = [SkipEntry::new(); MAX_HEIGHT];
-        for i in 0..self.head.height {
-            // Bleh.
-            iter[i as usize].node = &self.head as *const Node as *mut Node;
-        }
+        // For each level, the last node we've filled with that height and the number of chars (etc)
+        // before it. Same as insert_nodes_at.
+        let mut tails = [SkipEntry { node: head, ..SkipEntry::new() }; MAX_HEIGHT+1];
+        // The total size of the content so far. The node pointer is unused.
+        let mut total = SkipEntry::<N>::new();
 
-        let mut num_bytes: usize = 0;
+        let mut node = head;
+        let mut remainder = s;
+        unsafe {
+            loop {
+                let cut = floor_char_boundary(remainder, N);
+                let (chunk, rem) = remainder.split_at(cut);
+                remainder = rem;
+                (*node).str = GapBuffer::new_from_str(chunk);
+
+                if node != head {
+                    for (i, tail) in tails[..(*node).height as usize].iter_mut().enumerate() {
+                        (*tail.node).nexts[i] = SkipEntry {
+                            node,
+                            skip_chars: total.skip_chars - tail.skip_chars,
+                            #[cfg(feature = "wchar_conversion")]
+                            skip_pairs: total.skip_pairs - tail.skip_pairs,
+                            #[cfg(feature = "line_conversion")]
+                            skip_lines: total.skip_lines - tail.skip_lines,
+                            #[cfg(feature = "changes")]
+                            skip_bytes: total.skip_bytes - tail.skip_bytes,
+                        };
+                        *tail = SkipEntry { node, ..total };
+                    }
+                }
+
+                total.skip_chars += count_chars(chunk);
+                #[cfg(feature = "wchar_conversion")] {
+                    total.skip_pairs += count_utf16_surrogates(chunk);
+                }
+                #[cfg(feature = "line_conversion")] {
+                    total.skip_lines += count_lines(chunk);
+                }
+                #[cfg(feature = "changes")] {
+                    total.skip_bytes += chunk.len();
+                }
+
+                let next = (*node).first_next().node;
+                if remainder.is_empty() || next.is_null() { break; }
+                node = next;
+            }
+
+            // Any nodes we didn't need are discarded.
+            let mut next = (*node).first_next().node;
+            while !next.is_null() {
+                let n = (*next).first_next().node;
+                self.free_list.free(next);
+                next = n;
+            }
+
+            for (i, tail) in tails[..head_height].iter().enumerate() {
+                (*tail.node).nexts[i] = SkipEntry {
+                    node: null_mut(),
+                    skip_chars: total.skip_chars - tail.skip_chars,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: total.skip_pairs - tail.skip_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: total.skip_lines - tail.skip_lines,
+                    #[cfg(feature = "changes")]
+                    skip_bytes: total.skip_bytes - tail.skip_bytes,
+                };
+            }
+        }
+        self.num_bytes = s.len() - remainder.len();
+
+        // If we ran out of nodes, the rest of the content goes in new ones.
+        if !remainder.is_empty() {
+            let pos = self.len_chars();
+            self.insert(pos, remainder);
+        }
+        #[cfg(feature = "marks")] {
+            self.marks = marks;
+        }
+        #[cfg(feature = "history")] {
+            self.history = history;
+        }
+        #[cfg(feature = "changes")] {
+            self.changes = changes;
+        }
+        #[cfg(feature = "edit_log")] {
+            self.edit_log = edit_log;
+        }
+    }
+
+    /// Pack the content of the rope into as few nodes as possible. Every node except the last will
+    /// be full (or within a character of full).
+    ///
+    /// Deletes merge nearly empty nodes with their neighbours as they go, but after a long editing
+    /// session the rope will usually have a lot of partially filled nodes. This method makes
+    /// memory usage and lookups a little better at the cost of copying most of the rope's content.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hi there Mike!");
+    /// rope.remove(2..8);
+    /// rope.compact();
+    /// assert_eq!(rope, "Hi Mike!");
+    /// ```
+    pub fn compact(&mut self) {
+        let mut cursor = self.mut_cursor_at_start();
+        let head_height = cursor.head_height();
+        let mut node = cursor.here_ptr();
+        unsafe {
+            loop {
+                // Fill this node with content from the nodes after it.
+                while Self::merge_next_into(&mut cursor, node) {}
+                Self::move_prefix_of_next_into(&mut cursor, node);
+
+                let s = *(*node).first_next();
+                if s.node.is_null() { break; }
+
+                // Move the cursor to the end of this node.
+                for i in 0..head_height {
+                    if i < (*node).height as usize {
+                        cursor.inner[i] = SkipEntry { node, ..s };
+                    } else {
+                        cursor.inner[i].skip_chars += s.skip_chars;
+                        #[cfg(feature = "wchar_conversion")] {
+                            cursor.inner[i].skip_pairs += s.skip_pairs;
+                        }
+                        #[cfg(feature = "line_conversion")] {
+                            cursor.inner[i].skip_lines += s.skip_lines;
+                        }
+                        #[cfg(feature = "changes")] {
+                            cursor.inner[i].skip_bytes += s.skip_bytes;
+                        }
+                    }
+                }
+                node = s.node;
+            }
+        }
+    }
+
+    /// Lower the skip list to a height which suits the number of nodes in the rope.
+    ///
+    /// The skip list never gets shorter by itself. So after deleting most of a big rope, every
+    /// lookup still starts at the height the rope had when it was big. This method caps the height
+    /// of each node based on how many nodes are left, and lowers the head to match. Content is
+    /// never moved. Later inserts pick node heights as usual, so the rope can grow tall again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRopeSized::<16>::from("a".repeat(100_000));
+    /// rope.remove(10..100_000);
+    /// rope.rebalance();
+    /// assert_eq!(rope, "aaaaaaaaaa");
+    /// assert!(rope.current_height() <= 1);
+    /// ```
+    pub fn rebalance(&mut self) {
+        #[cfg(feature = "cow")]
+        self.make_unique();
+        // Each level has about 1/4 as many nodes as the level below it.
+        let num_nodes = self.node_count() - 1;
+        let mut max_height = 1;
+        while max_height < MAX_HEIGHT && num_nodes >> (2 * max_height) > 0 { max_height += 1; }
+        let max_height = max_height as u8;
+
+        let mut tallest = 0;
+        let mut node = self.head.first_next().node;
+        unsafe {
+            while !node.is_null() {
+                let height = (*node).height;
+                if height > max_height {
+                    // Nothing links to the node's upper levels once the head is lowered below.
+                    (&mut (*node).nexts)[max_height as usize..height as usize].fill(SkipEntry::default());
+                    (*node).height = max_height;
+                }
+                tallest = tallest.max((*node).height);
+                node = (*node).first_next().node;
+            }
+        }
+
+        // The head's top level skips over the whole rope.
+        let old_height = self.head.height as usize;
+        let total = self.head.nexts[old_height - 1];
+        let new_height = tallest as usize + 1;
+        self.head.nexts[new_height..old_height].fill(SkipEntry::default());
+        self.head.nexts[new_height - 1] = total;
+        self.head.height = new_height as u8;
+        self.cursor_valid = false;
+    }
+
+    /// Get the number of bytes used for the UTF8 representation of the rope. This will always match
+    /// the .len() property of the equivalent String.
+    ///
+    /// Note: This is only useful in specific situations - like preparing a byte buffer for saving
+    /// or sending over the internet. In many cases it is preferable to use
+    /// [`len_chars`](Self::len_chars).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let str = "κόσμε"; // "Cosmos" in ancient greek
+    /// assert_eq!(str.len(), 11); // 11 bytes over the wire
+    ///
+    /// let rope = JumpRope::from(str);
+    /// assert_eq!(rope.len_bytes(), str.len());
+    /// ```
+    #[doc(alias = "byte_len")]
+    pub fn len_bytes(&self) -> usize { self.num_bytes }
+
+    /// Returns `true` if the rope contains no elements.
+    pub fn is_empty(&self) -> bool { self.num_bytes == 0 }
+
+    /// Converts a byte offset in the rope's UTF-8 representation to a unicode character offset.
+    ///
+    /// Returns an error if the offset is past the end of the rope, or if it is in the middle of a
+    /// character.
+    ///
+    /// The rope doesn't index byte offsets, so this method walks the rope from the start. It has
+    /// time complexity *O(n)*.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.byte_to_char(4), Ok(2));
+    /// assert_eq!(rope.byte_to_char(5), Err(RopeError::InvalidCodepoint { pos: 5 }));
+    /// assert_eq!(rope.byte_to_char(11), Err(RopeError::PositionOutOfBounds { pos: 11, len: 10 }));
+    /// ```
+    pub fn byte_to_char(&self, byte_pos: usize) -> Result<usize, RopeError> {
+        if byte_pos > self.num_bytes {
+            return Err(RopeError::PositionOutOfBounds { pos: byte_pos, len: self.num_bytes });
+        }
+
+        let (s, offset, char_pos) = self.str_at_byte(byte_pos, true);
+        if s.is_char_boundary(offset) {
+            Ok(char_pos + count_chars(&s[..offset]))
+        } else {
+            Err(RopeError::InvalidCodepoint { pos: byte_pos })
+        }
+    }
+
+    /// Returns the character whose UTF-8 encoding contains the given byte offset, along with the
+    /// character's unicode character offset. Any byte of a multi-byte character returns the same
+    /// character. Returns `None` if the offset is past the end of the rope.
+    ///
+    /// Like [`byte_to_char`](Self::byte_to_char), this walks the rope from the start. It has time
+    /// complexity *O(n)*.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a↯b");
+    /// assert_eq!(rope.char_containing_byte(0), Some((0, 'a')));
+    /// assert_eq!(rope.char_containing_byte(2), Some((1, '↯')));
+    /// assert_eq!(rope.char_containing_byte(4), Some((2, 'b')));
+    /// assert_eq!(rope.char_containing_byte(5), None);
+    /// ```
+    pub fn char_containing_byte(&self, byte_pos: usize) -> Option<(usize, char)> {
+        if byte_pos >= self.num_bytes { return None; }
+
+        let (s, offset, char_pos) = self.str_at_byte(byte_pos, false);
+        let start = (0..=offset).rev().find(|b| s.is_char_boundary(*b)).unwrap();
+        Some((char_pos + count_chars(&s[..start]), s[start..].chars().next().unwrap()))
+    }
+
+    /// Insert new content at a UTF-8 byte offset in the rope. Returns an error if the offset is past
+    /// the end of the rope, or lands in the middle of a character. The rope is unchanged when an
+    /// error is returned.
+    ///
+    /// Like [`byte_to_char`](Self::byte_to_char), this walks the rope from the start.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("a↯b");
+    /// assert_eq!(rope.insert_at_byte(4, "!"), Ok(()));
+    /// assert_eq!(rope.insert_at_byte(2, "!"), Err(RopeError::InvalidCodepoint { pos: 2 }));
+    /// assert_eq!(rope, "a↯!b");
+    /// ```
+    pub fn insert_at_byte(&mut self, byte_pos: usize, contents: &str) -> Result<(), RopeError> {
+        let char_pos = self.byte_to_char(byte_pos)?;
+        self.insert(char_pos, contents);
+        Ok(())
+    }
+
+    /// Returns a copy of the content between two UTF-8 byte offsets. Returns an error if either
+    /// end of the range is past the end of the rope or in the middle of a character. A range which
+    /// starts after it ends reports its start as out of bounds, with the end as the length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a↯b");
+    /// assert_eq!(rope.slice_bytes(1..4).as_deref(), Ok("↯"));
+    /// assert_eq!(rope.slice_bytes(1..3), Err(RopeError::InvalidCodepoint { pos: 3 }));
+    /// assert_eq!(rope.slice_bytes(1..6), Err(RopeError::PositionOutOfBounds { pos: 6, len: 5 }));
+    /// ```
+    pub fn slice_bytes(&self, range: Range<usize>) -> Result<String, RopeError> {
+        let end = self.byte_to_char(range.end)?;
+        if range.start > range.end {
+            return Err(RopeError::PositionOutOfBounds { pos: range.start, len: range.end });
+        }
+        let start = self.byte_to_char(range.start)?;
+
+        let mut result = String::with_capacity(range.end - range.start);
+        self.to_string_in_range(start..end, &mut result);
+        Ok(result)
+    }
+
+    /// Copy the content between two UTF-8 byte offsets into `out`, without allocating. Returns the
+    /// number of bytes written (which is the length of the range). Returns an error if the range
+    /// is invalid (see [`slice_bytes`](Self::slice_bytes)), or if `out` is shorter than the range.
+    /// `out` is unchanged when an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a↯b");
+    /// let mut buf = [0; 10];
+    /// assert_eq!(rope.copy_bytes(1..5, &mut buf), Ok(4));
+    /// assert_eq!(&buf[..4], "↯b".as_bytes());
+    /// assert_eq!(rope.copy_bytes(0..5, &mut buf[..2]), Err(RopeError::BufferTooSmall { needed: 5, len: 2 }));
+    /// ```
+    pub fn copy_bytes(&self, range: Range<usize>, out: &mut [u8]) -> Result<usize, RopeError> {
+        let end = self.byte_to_char(range.end)?;
+        if range.start > range.end {
+            return Err(RopeError::PositionOutOfBounds { pos: range.start, len: range.end });
+        }
+        let start = self.byte_to_char(range.start)?;
+
+        let needed = range.end - range.start;
+        if out.len() < needed {
+            return Err(RopeError::BufferTooSmall { needed, len: out.len() });
+        }
+
+        let mut written = 0;
+        for s in self.slice_substrings(start..end) {
+            out[written..written + s.len()].copy_from_slice(s.as_bytes());
+            written += s.len();
+        }
+        debug_assert_eq!(written, needed);
+        Ok(written)
+    }
+
+    /// Find the string (on one side of a node's gap) containing the byte offset, walking from the
+    /// start of the rope. Returns the string, the offset within it and the character position of
+    /// the start of the string. If stick_end is true, offsets between two strings are found at the
+    /// end of the first string. Otherwise they're found at the start of the second. (So the offset
+    /// must be less than the length of the rope.)
+    fn str_at_byte(&self, byte_pos: usize, stick_end: bool) -> (&str, usize, usize) {
+        let mut offset = byte_pos;
+        let mut char_pos = 0;
+        for node in self.node_iter_at_start() {
+            let node_bytes = node.str.len_bytes();
+            if offset > node_bytes || (!stick_end && offset == node_bytes) {
+                offset -= node_bytes;
+                char_pos += node.num_chars();
+                continue;
+            }
+
+            for s in [node.as_str_1(), node.as_str_2()] {
+                if offset < s.len() || (stick_end && offset == s.len()) {
+                    return (s, offset, char_pos);
+                }
+                offset -= s.len();
+                char_pos += count_chars(s);
+            }
+        }
+        unreachable!("Byte offset is within the rope");
+    }
+
+    /// Returns the character at the given unicode character offset, or `None` if the position is
+    /// past the end of the rope.
+    ///
+    /// This method is *O(log n)*. To read a run of characters, use [`chars`](Self::chars) or
+    /// [`slice_chars`](Self::slice_chars) instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.char_at(1), Some('ό'));
+    /// assert_eq!(rope.char_at(5), None);
+    /// ```
+    pub fn char_at(&self, char_pos: usize) -> Option<char> {
+        if char_pos >= self.len_chars() { return None; }
+
+        // Not sticking to the end means the cursor will point into the node containing the char.
+        let cursor = self.read_cursor_at_char(char_pos, false);
+        let str = &cursor.node.str;
+        let gap_chars = str.gap_start_chars as usize;
+        let (s, offset) = if cursor.offset_chars < gap_chars {
+            (str.start_as_str(), cursor.offset_chars)
+        } else {
+            (str.end_as_str(), cursor.offset_chars - gap_chars)
+        };
+        s[str_chars_to_bytes(s, offset)..].chars().next()
+    }
+
+    /// Returns the character at the given unicode character offset, or `default` if the position
+    /// is past the end of the rope. This is handy for lookahead in parsers, which can use a
+    /// sentinel like `'\0'` for the end of the input.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("ab");
+    /// assert_eq!(rope.char_at_or(1, '\0'), 'b');
+    /// assert_eq!(rope.char_at_or(2, '\0'), '\0');
+    /// ```
+    pub fn char_at_or(&self, char_pos: usize, default: char) -> char {
+        self.char_at(char_pos).unwrap_or(default)
+    }
+
+    /// Returns the `len` characters starting at unicode character offset `pos`. When the range lies
+    /// within a single node (which is almost always true for short ranges), the returned string
+    /// borrows straight from the rope's internal storage. Otherwise the characters are copied into
+    /// a new string.
+    ///
+    /// Returns an error if the range extends past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi there");
+    /// assert_eq!(rope.slice_cow(3, 5).unwrap(), "there");
+    /// assert!(rope.slice_cow(3, 6).is_err());
+    /// ```
+    pub fn slice_cow(&self, pos: usize, len: usize) -> Result<Cow<'_, str>, RopeError> {
+        let len_chars = self.len_chars();
+        let end = pos.checked_add(len).filter(|end| *end <= len_chars)
+            .ok_or(RopeError::PositionOutOfBounds { pos: pos.saturating_add(len), len: len_chars })?;
+        if len == 0 { return Ok(Cow::Borrowed("")); }
+
+        let cursor = self.read_cursor_at_char(pos, false);
+        let str = &cursor.node.str;
+        let gap_chars = str.gap_start_chars as usize;
+        let start = cursor.offset_chars;
+
+        // The content is borrowable if it's all on one side of the node's gap.
+        let borrowed = if start + len <= gap_chars {
+            Some((str.start_as_str(), start))
+        } else if start >= gap_chars && start + len <= cursor.node.num_chars() {
+            Some((str.end_as_str(), start - gap_chars))
+        } else { None };
+
+        Ok(match borrowed {
+            Some((s, offset)) => {
+                let start_byte = str_chars_to_bytes(s, offset);
+                let len_bytes = str_chars_to_bytes(&s[start_byte..], len);
+                Cow::Borrowed(&s[start_byte..start_byte + len_bytes])
+            }
+            None => {
+                let mut result = String::new();
+                self.to_string_in_range(pos..end, &mut result);
+                Cow::Owned(result)
+            }
+        })
+    }
+
+    /// Returns up to `len` characters starting at unicode character offset `pos`. This uses the
+    /// same bounds policy as [`try_remove`](Self::try_remove): `pos` past the end of the rope is an
+    /// error, but a range which runs past the end is clamped to the end of the rope. Use
+    /// [`try_slice`](Self::try_slice) to treat that as an error too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi there");
+    /// assert_eq!(rope.slice(3, 100).unwrap(), "there");
+    /// assert_eq!(rope.slice(8, 1).unwrap(), "");
+    /// assert_eq!(rope.slice(9, 1), Err(RopeError::PositionOutOfBounds { pos: 9, len: 8 }));
+    /// ```
+    pub fn slice(&self, pos: usize, len: usize) -> Result<Cow<'_, str>, RopeError> {
+        let len_chars = self.len_chars();
+        if pos > len_chars {
+            return Err(RopeError::PositionOutOfBounds { pos, len: len_chars });
+        }
+        self.slice_cow(pos, len.min(len_chars - pos))
+    }
+
+    /// Returns the `len` characters starting at unicode character offset `pos`, or an error if any
+    /// part of the range is past the end of the rope. This is the same as
+    /// [`slice_cow`](Self::slice_cow), named to pair with [`slice`](Self::slice).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi there");
+    /// assert_eq!(rope.try_slice(3, 5).unwrap(), "there");
+    /// assert_eq!(rope.try_slice(3, 6), Err(RopeError::PositionOutOfBounds { pos: 9, len: 8 }));
+    /// ```
+    pub fn try_slice(&self, pos: usize, len: usize) -> Result<Cow<'_, str>, RopeError> {
+        self.slice_cow(pos, len)
+    }
+
+    /// Returns a copy of the characters within a range of unicode character offsets. Like
+    /// [`str::get`], this returns `None` instead of panicking if the range is out of bounds (or
+    /// starts after it ends).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi there");
+    /// assert_eq!(rope.get(3..).as_deref(), Some("there"));
+    /// assert_eq!(rope.get(..=1).as_deref(), Some("hi"));
+    /// assert_eq!(rope.get(3..100), None);
+    /// ```
+    pub fn get<R: RangeBounds<usize>>(&self, range: R) -> Option<String> {
+        let len = self.len_chars();
+        let range = bounds_to_range(range, len);
+        if range.start > range.end || range.end > len { return None; }
+
+        let mut result = String::new();
+        self.to_string_in_range(range, &mut result);
+        Some(result)
+    }
+
+    /// Append the characters within a range of unicode character offsets onto the end of `out`.
+    /// This is like [`get`](Self::get), but reusing one buffer across calls avoids allocating a
+    /// new string for each slice.
+    ///
+    /// Returns an error if the range extends past the end of the rope, and `out` is left
+    /// unchanged. A range which starts after it ends reports its start as out of bounds, with the
+    /// end as the length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi there");
+    /// let mut out = String::new();
+    /// rope.extend_string_with_range(3.., &mut out).unwrap();
+    /// rope.extend_string_with_range(..=1, &mut out).unwrap();
+    /// assert_eq!(out, "therehi");
+    /// assert_eq!(rope.extend_string_with_range(3..9, &mut out),
+    ///     Err(RopeError::PositionOutOfBounds { pos: 9, len: 8 }));
+    /// ```
+    pub fn extend_string_with_range<R: RangeBounds<usize>>(&self, range: R, out: &mut String) -> Result<(), RopeError> {
+        let len = self.len_chars();
+        let range = bounds_to_range(range, len);
+        if range.end > len {
+            return Err(RopeError::PositionOutOfBounds { pos: range.end, len });
+        }
+        if range.start > range.end {
+            return Err(RopeError::PositionOutOfBounds { pos: range.start, len: range.end });
+        }
+
+        self.to_string_in_range(range, out);
+        Ok(())
+    }
+
+    /// Returns the number of leading unicode characters which the rope and `other` have in common.
+    ///
+    /// Along with [`common_suffix_len`](Self::common_suffix_len), this is useful for finding the
+    /// minimal edit needed to replace the rope's content with a new string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε world");
+    /// assert_eq!(rope.common_prefix_len("κόσμε everyone"), 6);
+    /// assert_eq!(rope.common_prefix_len("hi"), 0);
+    /// ```
+    pub fn common_prefix_len(&self, other: &str) -> usize {
+        let mut other = other.as_bytes();
+        let mut num_chars = 0;
+
+        for (s, char_len) in self.substrings_with_len() {
+            let same_bytes = s.as_bytes().iter().zip(other)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if same_bytes == s.len() {
+                num_chars += char_len;
+                other = &other[same_bytes..];
+            } else {
+                // The strings differ (or other ends) inside this chunk. The differing byte might
+                // be in the middle of a character, so round down to the start of that character.
+                let same_bytes = floor_char_boundary(s, same_bytes);
+                return num_chars + count_chars(&s[..same_bytes]);
+            }
+        }
+
+        num_chars
+    }
+
+    /// Returns the number of trailing unicode characters which the rope and `other` have in
+    /// common.
+    ///
+    /// Together with [`common_prefix_len`](Self::common_prefix_len), this lets a caller replace
+    /// only the part of the rope which differs from a new string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hello κόσμε");
+    /// let new_content = "hi there κόσμε";
+    ///
+    /// let prefix = rope.common_prefix_len(new_content);
+    /// let suffix = rope.common_suffix_len(new_content);
+    /// assert_eq!((prefix, suffix), (1, 6));
+    ///
+    /// let new_len = new_content.chars().count();
+    /// let middle: String = new_content.chars().skip(prefix).take(new_len - prefix - suffix).collect();
+    /// rope.replace(prefix..rope.len_chars() - suffix, &middle);
+    /// assert_eq!(rope, new_content);
+    /// ```
+    pub fn common_suffix_len(&self, other: &str) -> usize {
+        // Nodes only link forwards, so we need to find all the chunks before we can walk backwards.
+        let chunks: Vec<(&str, usize)> = self.substrings_with_len().collect();
+        let mut other = other.as_bytes();
+        let mut num_chars = 0;
+
+        for (s, char_len) in chunks.into_iter().rev() {
+            let same_bytes = s.as_bytes().iter().rev().zip(other.iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            if same_bytes == s.len() {
+                num_chars += char_len;
+                other = &other[..other.len() - same_bytes];
+            } else {
+                // Round up to the start of the first whole character which matches.
+                let mut start = s.len() - same_bytes;
+                while !s.is_char_boundary(start) { start += 1; }
+                return num_chars + count_chars(&s[start..]);
+            }
+        }
+
+        num_chars
+    }
+
+    /// Search for `pattern`, starting at unicode character offset `start`. Returns the character
+    /// offset of the first match which starts at or after `start`, or `None` if there isn't one
+    /// (or `start` is past the end of the rope). Matches can span internal nodes.
+    ///
+    /// To find every match, call this again with the previous result plus the pattern's length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one two one");
+    /// assert_eq!(rope.find_at("one", 0), Some(0));
+    /// assert_eq!(rope.find_at("one", 1), Some(8));
+    /// assert_eq!(rope.find_at("one", 9), None);
+    /// ```
+    pub fn find_at(&self, pattern: &str, start: usize) -> Option<usize> {
+        let len = self.len_chars();
+        if start > len { return None; }
+        find_in_chunks(self.slice_substrings(start..len), pattern).map(|i| start + i)
+    }
+
+    /// The maximum number of bytes of text stored in each internal node of this rope type.
+    pub const fn max_bytes_per_node() -> usize { N }
+
+    /// The maximum height of any node in the skip list. Each level has about a quarter as many nodes
+    /// as the level below it, so the rope will become less efficient once it contains many more
+    /// than `4^max_height()` nodes.
+    pub const fn max_height() -> usize { MAX_HEIGHT }
+
+    /// The height of the tallest node currently in the skip list. This is always less than or equal
+    /// to [`max_height`](Self::max_height), and it's mostly useful for diagnostics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi");
+    /// assert!(rope.current_height() <= JumpRope::max_height());
+    /// ```
+    pub fn current_height(&self) -> usize {
+        // The head is always one taller than the tallest node.
+        self.head.height as usize - 1
+    }
+
+    /// Returns the index of the node containing `char_pos` (counting along the bottom level of
+    /// the skip list, with the head as node 0) and the position's offset within that node. A
+    /// position between two nodes is at the start of the second node, except at the end of the
+    /// rope.
+    #[cfg(test)]
+    pub(crate) fn node_index_for_char(&self, char_pos: usize) -> (usize, usize) {
+        let cursor = self.read_cursor_at_char(char_pos, char_pos == self.len_chars());
+        let mut index = 0;
+        let mut node: *const Node<N> = &self.head;
+        while !std::ptr::eq(node, cursor.node) {
+            node = unsafe { (*node).first_next().node };
+            index += 1;
+        }
+        (index, cursor.offset_chars)
+    }
+
+    /// Checks the rope's internal invariants, and panics if any of them don't hold. This walks
+    /// every node, so it takes *O(n)* time. It's meant for tests and fuzzers.
+    ///
+    /// This checks that skip entries at every level add up to the distance between the nodes they
+    /// link, that the totals match the rope's length, that node heights are in range, that no node
+    /// (except the head) is empty, and that each node's content is valid UTF-8.
+    pub fn check(&self) {
+        assert!(self.head.height >= 1);
+        // The head is one taller than the tallest node, and nodes can be MAX_HEIGHT tall.
+        assert!(self.head.height <= MAX_HEIGHT_U8 + 1);
+
+        let skip_over = &self.head.nexts[self.head.height as usize - 1];
+        // println!("Skip over skip chars {}, num bytes {}", skip_over.skip_chars, self.num_bytes);
+        assert!(skip_over.skip_chars <= self.num_bytes as usize);
+        #[cfg(feature = "wchar_conversion")] {
+            assert!(skip_over.skip_pairs <= skip_over.skip_chars);
+        }
+        #[cfg(feature = "line_conversion")] {
+            assert!(skip_over.skip_lines <= skip_over.skip_chars);
+        }
+        #[cfg(feature = "changes")] {
+            assert_eq!(skip_over.skip_bytes, self.num_bytes);
+        }
+        assert!(skip_over.node.is_null());
+
+        // The offsets store the total distance travelled since the start.
+        let mut iter = [SkipEntry::<N>::new(); MAX_HEIGHT+1];
+        for i in 0..self.head.height {
+            // Bleh.
+            iter[i as usize].node = &self.head as *const Node<N> as *mut Node<N>;
+        }
+
+        let mut num_bytes: usize = 0;
         let mut num_chars = 0;
         #[cfg(feature = "wchar_conversion")]
         let mut num_pairs = 0;
+        #[cfg(feature = "line_conversion")]
+        let mut num_lines = 0;
+
+        // The previous node in the list. Nodes directly after the head have a null prev pointer.
+        let mut prev: *const Node<N> = null_mut();
 
         for n in self.node_iter_at_start() {
             // println!("visiting {:?}", n.as_str());
-            assert!(!n.str.is_empty() || std::ptr::eq(n, &self.head));
-            assert!(n.height <= MAX_HEIGHT_U8);
+            let expected_prev = if std::ptr::eq(prev, &self.head) { null_mut() } else { prev };
+            assert_eq!(n.prev as *const Node<N>, expected_prev);
+            prev = n;
+
+            // Only the head may be empty. Edits unlink nodes as soon as they're emptied, and
+            // navigation relies on never stopping in an empty node.
+            assert!(!n.str.is_empty() || std::ptr::eq(n, &self.head), "Empty node in the list");
+            assert!(n.height <= MAX_HEIGHT_U8 || std::ptr::eq(n, &self.head));
             assert!(n.height >= 1);
             n.str.check();
 
             assert_eq!(count_chars(n.as_str_1()) + count_chars(n.as_str_2()), n.num_chars());
             for (i, entry) in iter[0..n.height as usize].iter_mut().enumerate() {
-                assert_eq!(entry.node as *const Node, n as *const Node);
+                assert_eq!(entry.node as *const Node<N>, n as *const Node<N>);
                 assert_eq!(entry.skip_chars, num_chars);
                 #[cfg(feature = "wchar_conversion")] {
                     assert_eq!(entry.skip_pairs, num_pairs);
                 }
+                #[cfg(feature = "line_conversion")] {
+                    assert_eq!(entry.skip_lines, num_lines);
+                }
+                #[cfg(feature = "changes")] {
+                    assert_eq!(entry.skip_bytes, num_bytes);
+                }
+
+                // println!("replacing entry {:?} with {:?}", entry, n.nexts()[i].node);
+                entry.node = n.nexts[i].node;
+                entry.skip_chars += n.nexts[i].skip_chars;
+                #[cfg(feature = "wchar_conversion")] {
+                    entry.skip_pairs += n.nexts[i].skip_pairs;
+                }
+                #[cfg(feature = "line_conversion")] {
+                    entry.skip_lines += n.nexts[i].skip_lines;
+                }
+                #[cfg(feature = "changes")] {
+                    entry.skip_bytes += n.nexts[i].skip_bytes;
+                }
+            }
+
+            num_bytes += n.str.len_bytes();
+            num_chars += n.num_chars();
+
+            #[cfg(feature = "wchar_conversion")] {
+                assert_eq!(n.num_surrogate_pairs(), n.str.count_surrogate_pairs(n.num_chars()));
+                num_pairs += n.num_surrogate_pairs();
+            }
+
+            #[cfg(feature = "line_conversion")] {
+                // Recount from the content itself, so this doesn't rely on the gap buffer's cached
+                // line count either.
+                let lines = count_lines(n.as_str_1()) + count_lines(n.as_str_2());
+                assert_eq!(n.num_lines(), lines);
+                assert_eq!(n.str.count_lines(n.num_chars()), lines);
+                num_lines += n.num_lines();
+            }
+        }
+
+        for entry in iter[0..self.head.height as usize].iter() {
+            // println!("{:?}", entry);
+            assert!(entry.node.is_null());
+            assert_eq!(entry.skip_chars, num_chars);
+            #[cfg(feature = "wchar_conversion")] {
+                assert_eq!(entry.skip_pairs, num_pairs);
+            }
+            #[cfg(feature = "line_conversion")] {
+                assert_eq!(entry.skip_lines, num_lines);
+            }
+            #[cfg(feature = "changes")] {
+                assert_eq!(entry.skip_bytes, num_bytes);
+            }
+        }
+
+        // println!("self bytes: {}, count bytes {}", self.num_bytes, num_bytes);
+        assert_eq!(self.num_bytes, num_bytes);
+        assert_eq!(self.len_chars(), num_chars);
+        #[cfg(feature = "wchar_conversion")] {
+            assert_eq!(self.len_wchars(), num_chars + num_pairs);
+        }
+        #[cfg(feature = "line_conversion")] {
+            assert_eq!(self.len_lines(), num_lines + 1);
+        }
+    }
+
+    /// Recount the rope's length (and every skip entry) from the content of its nodes.
+    ///
+    /// The rope caches the number of bytes, characters (and UTF-16 code units and lines, when
+    /// enabled) between each node. If those counts ever get out of sync with the content because
+    /// of a bug, [`check`](Self::check) will panic. This method repairs them instead. It walks
+    /// every node, so it takes *O(n)* time.
+    ///
+    /// The rope's content and structure are unchanged.
+    pub fn recompute_len(&mut self) {
+        #[cfg(feature = "cow")]
+        self.make_unique();
+        self.cursor_valid = false;
+        let head_height = self.head.height as usize;
+        let head: *mut Node<N> = &mut self.head;
+
+        // For each level, the last node with an entry at that level, and the totals at its start.
+        let mut last = [SkipEntry { node: head, ..SkipEntry::new() }; MAX_HEIGHT+1];
+        // The totals so far.
+        let mut pos = SkipEntry::<N>::new();
+        let mut num_bytes = 0;
+
+        unsafe {
+            let mut node = head;
+            loop {
+                if node != head {
+                    for (i, l) in last[..(*node).height as usize].iter_mut().enumerate() {
+                        Self::set_skip(&mut (*l.node).nexts[i], l, &pos);
+                        *l = SkipEntry { node, ..pos };
+                    }
+                }
 
-                // println!("replacing entry {:?} with {:?}", entry, n.nexts()[i].node);
-                entry.node = n.nexts[i].node;
-                entry.skip_chars += n.nexts[i].skip_chars;
+                let n = &*node;
+                num_bytes += n.str.len_bytes();
+                let (s1, s2) = (n.as_str_1(), n.as_str_2());
+                pos.skip_chars += count_chars(s1) + count_chars(s2);
                 #[cfg(feature = "wchar_conversion")] {
-                    entry.skip_pairs += n.nexts[i].skip_pairs;
+                    pos.skip_pairs += count_utf16_surrogates(s1) + count_utf16_surrogates(s2);
+                }
+                #[cfg(feature = "line_conversion")] {
+                    pos.skip_lines += count_lines(s1) + count_lines(s2);
+                }
+                #[cfg(feature = "changes")] {
+                    pos.skip_bytes += n.str.len_bytes();
                 }
-            }
-
-            num_bytes += n.str.len_bytes();
-            num_chars += n.num_chars();
 
-            #[cfg(feature = "wchar_conversion")] {
-                assert_eq!(n.num_surrogate_pairs(), n.str.count_surrogate_pairs(n.num_chars()));
-                num_pairs += n.num_surrogate_pairs();
+                node = n.nexts[0].node;
+                if node.is_null() { break; }
             }
-        }
 
-        for entry in iter[0..self.head.height as usize].iter() {
-            // println!("{:?}", entry);
-            assert!(entry.node.is_null());
-            assert_eq!(entry.skip_chars, num_chars);
-            #[cfg(feature = "wchar_conversion")] {
-                assert_eq!(entry.skip_pairs, num_pairs);
+            for (i, l) in last[..head_height].iter().enumerate() {
+                Self::set_skip(&mut (*l.node).nexts[i], l, &pos);
             }
         }
+        self.num_bytes = num_bytes;
+    }
 
-        // println!("self bytes: {}, count bytes {}", self.num_bytes, num_bytes);
-        assert_eq!(self.num_bytes, num_bytes);
-        assert_eq!(self.len_chars(), num_chars);
+    /// Set the counts in entry to the distance from start to end. The entry's node is unchanged.
+    fn set_skip(entry: &mut SkipEntry<N>, start: &SkipEntry<N>, end: &SkipEntry<N>) {
+        entry.skip_chars = end.skip_chars - start.skip_chars;
         #[cfg(feature = "wchar_conversion")] {
-            assert_eq!(self.len_wchars(), num_chars + num_pairs);
+            entry.skip_pairs = end.skip_pairs - start.skip_pairs;
+        }
+        #[cfg(feature = "line_conversion")] {
+            entry.skip_lines = end.skip_lines - start.skip_lines;
+        }
+        #[cfg(feature = "changes")] {
+            entry.skip_bytes = end.skip_bytes - start.skip_bytes;
         }
     }
 
@@ -1389,15 +4436,103 @@ impl JumpRope {
         for _n in nodes {
             // let layout = Node::layout_with_height(n.height);
             // size += layout.size();
-            size += std::mem::size_of::<Node>();
+            size += std::mem::size_of::<Node<N>>();
         }
 
         size
     }
 
-    #[allow(unused)]
-    // pub fn print(&self) {
-    pub(crate) fn print(&self) {
+    /// Returns the number of bytes allocated on the heap for the rope's nodes. This includes
+    /// unused nodes kept around for reuse, but not the first node, which is stored inline in the
+    /// rope itself.
+    ///
+    /// This method walks the rope. It has time complexity O(n).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::new();
+    /// assert_eq!(rope.mem_usage(), 0);
+    /// rope.insert(0, &"hi there".repeat(1000));
+    /// assert!(rope.mem_usage() > 8000);
+    /// ```
+    pub fn mem_usage(&self) -> usize {
+        let num_nodes = self.node_iter_at_start().count() - 1 + self.free_list.len;
+        num_nodes * std::mem::size_of::<Node<N>>()
+    }
+
+    /// Returns statistics about the internal structure of the rope. This is useful for debugging
+    /// and for monitoring the health of long lived ropes.
+    ///
+    /// This method walks the rope. It has time complexity O(n).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from(&"hi there".repeat(1000));
+    /// let stats = rope.stats();
+    /// assert_eq!(stats.used_bytes, 8000);
+    /// assert!(stats.fill_factor > 0.9);
+    /// ```
+    pub fn stats(&self) -> RopeStats {
+        let mut stats = RopeStats {
+            num_nodes: 0,
+            num_free_nodes: self.free_list.len,
+            capacity_bytes: 0,
+            used_bytes: 0,
+            fill_factor: 0.0,
+            height_histogram: [0; MAX_HEIGHT],
+        };
+
+        for node in self.node_iter_at_start() {
+            stats.num_nodes += 1;
+            stats.used_bytes += node.str.len_bytes();
+        }
+        stats.capacity_bytes = stats.num_nodes * N;
+        stats.fill_factor = stats.used_bytes as f64 / stats.capacity_bytes as f64;
+        stats.height_histogram = self.height_histogram();
+        stats
+    }
+
+    /// Returns the number of nodes storing the rope's content, including the first node (which is
+    /// stored inline in the rope itself).
+    ///
+    /// This method walks the rope. It has time complexity O(n).
+    pub fn node_count(&self) -> usize {
+        self.node_iter_at_start().count()
+    }
+
+    /// Returns how many nodes there are of each height. `height_histogram()[h]` is the number of
+    /// nodes of height `h + 1`. Node heights are random, so this should decay geometrically: each
+    /// height has about 1/4 as many nodes as the height below it.
+    ///
+    /// The first node is not counted, because its height is always the height of the whole rope.
+    /// So the histogram sums to `node_count() - 1`.
+    ///
+    /// This method walks the rope. It has time complexity O(n).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from(&"hi there".repeat(1000));
+    /// let histogram = rope.height_histogram();
+    /// assert_eq!(histogram.iter().sum::<usize>(), rope.node_count() - 1);
+    /// ```
+    pub fn height_histogram(&self) -> [usize; MAX_HEIGHT] {
+        let mut histogram = [0; MAX_HEIGHT];
+        for node in self.node_iter_at_start().skip(1) {
+            histogram[node.height as usize - 1] += 1;
+        }
+        histogram
+    }
+
+    /// Prints the rope's internal structure to stdout: the skip entries of the head and each node,
+    /// along with each node's content (on either side of its gap). This is for debugging, and the
+    /// format may change at any time.
+    pub fn print(&self) {
         println!("chars: {}\tbytes: {}\theight: {}", self.len_chars(), self.num_bytes, self.head.height);
 
         print!("HEAD:");
@@ -1428,7 +4563,7 @@ impl JumpRope {
 /// These methods are only available if the `wchar_conversion` feature is enabled.
 #[cfg_attr(doc_cfg, doc(cfg(feature = "wchar_conversion")))]
 #[cfg(feature = "wchar_conversion")]
-impl JumpRope {
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
     /// Convert from a unicode character count to a wchar index, like what you'd use in Javascript,
     /// Java or C#.
     pub fn chars_to_wchars(&self, chars: usize) -> usize {
@@ -1516,4 +4651,554 @@ impl JumpRope {
             self.insert_at_wchar(range.start, content);
         }
     }
-}
\ No newline at end of file
+}
+#[cfg_attr(doc_cfg, doc(cfg(feature = "line_conversion")))]
+#[cfg(feature = "line_conversion")]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    fn num_newlines(&self) -> usize {
+        self.head.nexts[self.head.height as usize - 1].skip_lines
+    }
+
+    /// Return the number of lines in the rope. This is always one more than the number of `\n`
+    /// characters, so an empty rope has 1 line. (`\r` characters are not treated specially.)
+    ///
+    /// This method returns the length in constant-time (*O(1)*).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// assert_eq!(JumpRope::new().len_lines(), 1);
+    /// assert_eq!(JumpRope::from("one\ntwo\n").len_lines(), 3);
+    /// ```
+    pub fn len_lines(&self) -> usize {
+        self.num_newlines() + 1
+    }
+
+    /// Convert a line number (0-based) into the unicode character offset of the start of that
+    /// line. Line numbers past the end of the rope return [`len_chars`](Self::len_chars).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one\ntwo\n");
+    /// assert_eq!(rope.line_to_char(0), 0);
+    /// assert_eq!(rope.line_to_char(1), 4);
+    /// assert_eq!(rope.line_to_char(2), 8);
+    /// ```
+    pub fn line_to_char(&self, line: usize) -> usize {
+        if line > self.num_newlines() { return self.len_chars(); }
+
+        let mut e: *const Node<N> = &self.head;
+        let mut height = self.head.height as usize - 1;
+
+        let mut offset = line; // How many more newlines to skip
+        let mut char_pos = 0;
+
+        loop {
+            let en = unsafe { &*e };
+            let next = en.nexts[height];
+            if offset > next.skip_lines {
+                // Go right.
+                offset -= next.skip_lines;
+                char_pos += next.skip_chars;
+                e = next.node;
+                debug_assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+            } else if height != 0 {
+                // Go down.
+                height -= 1;
+            } else {
+                return char_pos + en.str.line_to_char(offset);
+            }
+        }
+    }
+
+    /// Convert a unicode character offset into the (0-based) line number containing that
+    /// character. Offsets past the end of the rope are clamped to the end.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one\ntwo\n");
+    /// assert_eq!(rope.char_to_line(0), 0);
+    /// assert_eq!(rope.char_to_line(3), 0); // The newline itself is part of the first line
+    /// assert_eq!(rope.char_to_line(4), 1);
+    /// assert_eq!(rope.char_to_line(8), 2);
+    /// ```
+    pub fn char_to_line(&self, char_pos: usize) -> usize {
+        let char_pos = char_pos.min(self.len_chars());
+
+        let mut e: *const Node<N> = &self.head;
+        let mut height = self.head.height as usize - 1;
+
+        let mut offset = char_pos; // How many more chars to skip
+        let mut lines = 0;
+
+        loop {
+            let en = unsafe { &*e };
+            let next = en.nexts[height];
+            if offset > next.skip_chars {
+                // Go right.
+                offset -= next.skip_chars;
+                lines += next.skip_lines;
+                e = next.node;
+                debug_assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+            } else if height != 0 {
+                // Go down.
+                height -= 1;
+            } else {
+                return lines + en.str.count_lines(offset);
+            }
+        }
+    }
+
+    /// Return the number of unicode characters on the named line, not including the trailing
+    /// newline (if any). Returns `None` if the line does not exist.
+    ///
+    /// This is useful for clamping a cursor's column to the length of a line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one\n\nthree");
+    /// assert_eq!(rope.line_char_len(0), Some(3));
+    /// assert_eq!(rope.line_char_len(1), Some(0));
+    /// assert_eq!(rope.line_char_len(2), Some(5));
+    /// assert_eq!(rope.line_char_len(3), None);
+    /// ```
+    pub fn line_char_len(&self, line: usize) -> Option<usize> {
+        let num_newlines = self.num_newlines();
+        if line > num_newlines { return None; }
+
+        let start = self.line_to_char(line);
+        let end = if line < num_newlines {
+            // Don't count the newline character itself.
+            self.line_to_char(line + 1) - 1
+        } else {
+            self.len_chars()
+        };
+        Some(end - start)
+    }
+
+    /// Returns a copy of the named (0-based) line, without its line ending (`\n` or `\r\n`).
+    /// Returns `None` if the line does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one\r\ntwo\n");
+    /// assert_eq!(rope.line(0).as_deref(), Some("one"));
+    /// assert_eq!(rope.line(1).as_deref(), Some("two"));
+    /// assert_eq!(rope.line(2).as_deref(), Some(""));
+    /// assert_eq!(rope.line(3), None);
+    /// ```
+    pub fn line(&self, line: usize) -> Option<String> {
+        let start = self.line_to_char(line);
+        let len = self.line_char_len(line)?;
+        let mut result: String = self.chars_in_range(start..start + len).collect();
+        if result.ends_with('\r') && line < self.num_newlines() { result.pop(); }
+        Some(result)
+    }
+
+    /// Insert new content at the given column (in unicode characters) of the given line. `col`
+    /// may be equal to the length of the line, which inserts at the end of the line (before its
+    /// trailing newline).
+    ///
+    /// Returns an error if the line does not exist, or if the column is past the end of the line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one\nthree");
+    /// assert_eq!(rope.insert_at_line_col(1, 0, "two\n"), Ok(()));
+    /// assert_eq!(rope, "one\ntwo\nthree");
+    /// assert_eq!(rope.insert_at_line_col(0, 4, "!"), Err(RopeError::PositionOutOfBounds { pos: 4, len: 3 }));
+    /// ```
+    pub fn insert_at_line_col(&mut self, line: usize, col: usize, text: &str) -> Result<(), RopeError> {
+        let line_len = self.line_char_len(line).ok_or(RopeError::PositionOutOfBounds {
+            pos: line,
+            len: self.len_lines(),
+        })?;
+        if col > line_len {
+            return Err(RopeError::PositionOutOfBounds { pos: col, len: line_len });
+        }
+
+        let pos = self.line_to_char(line) + col;
+        self.insert(pos, text);
+        Ok(())
+    }
+
+    /// Returns the character at the given (0-based) line and column, or `None` if there is no
+    /// character there. Columns are counted in unicode characters. The newline at the end of a
+    /// line is at the column equal to the line's length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one\nκόσμε");
+    /// assert_eq!(rope.char_at_line_col(1, 1), Some('ό'));
+    /// assert_eq!(rope.char_at_line_col(0, 3), Some('\n'));
+    /// assert_eq!(rope.char_at_line_col(0, 4), None);
+    /// ```
+    pub fn char_at_line_col(&self, line: usize, col: usize) -> Option<char> {
+        let line_len = self.line_char_len(line)?;
+        // Allow reading the trailing newline, if there is one.
+        if col > line_len { return None; }
+        self.char_at(self.line_to_char(line) + col)
+    }
+
+    /// Insert `text` as a new line before the given (0-based) line, shifting that line and every
+    /// line after it down. A `\n` is added to the end of `text` if it doesn't already end with
+    /// one.
+    ///
+    /// `line` may be equal to [`len_lines`](Self::len_lines), which adds a new line after the last
+    /// line. (The last line never ends in a newline, so one is inserted before `text`.)
+    ///
+    /// Returns an error if `line` is greater than `len_lines()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one\nthree");
+    /// assert_eq!(rope.insert_line(1, "two"), Ok(()));
+    /// assert_eq!(rope, "one\ntwo\nthree");
+    /// assert_eq!(rope.insert_line(3, "four"), Ok(()));
+    /// assert_eq!(rope, "one\ntwo\nthree\nfour\n");
+    /// assert_eq!(rope.insert_line(6, "!"), Err(RopeError::PositionOutOfBounds { pos: 6, len: 5 }));
+    /// ```
+    pub fn insert_line(&mut self, line: usize, text: &str) -> Result<(), RopeError> {
+        let len_lines = self.len_lines();
+        if line > len_lines {
+            return Err(RopeError::PositionOutOfBounds { pos: line, len: len_lines });
+        }
+
+        let mut content = String::with_capacity(text.len() + 2);
+        if line == len_lines { content.push('\n'); }
+        content.push_str(text);
+        if !text.ends_with('\n') { content.push('\n'); }
+
+        let pos = self.line_to_char(line);
+        self.insert(pos, &content);
+        Ok(())
+    }
+
+    /// Delete the given (0-based) line, along with the newline at the end of it. The last line
+    /// doesn't end in a newline, so deleting it removes the newline before it instead. Either way
+    /// the lines after it move up by one, and every other line is unchanged.
+    ///
+    /// Deleting the only line leaves the rope empty.
+    ///
+    /// Returns an error if `line` is not less than [`len_lines`](Self::len_lines).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one\ntwo\nthree");
+    /// assert_eq!(rope.delete_line(1), Ok(()));
+    /// assert_eq!(rope, "one\nthree");
+    /// assert_eq!(rope.delete_line(1), Ok(()));
+    /// assert_eq!(rope, "one");
+    /// assert_eq!(rope.delete_line(1), Err(RopeError::PositionOutOfBounds { pos: 1, len: 1 }));
+    /// ```
+    pub fn delete_line(&mut self, line: usize) -> Result<(), RopeError> {
+        let len_lines = self.len_lines();
+        if line >= len_lines {
+            return Err(RopeError::PositionOutOfBounds { pos: line, len: len_lines });
+        }
+
+        let range = if line + 1 < len_lines {
+            self.line_to_char(line)..self.line_to_char(line + 1)
+        } else {
+            // The last line. Take the newline before it, if there is one.
+            self.line_to_char(line).saturating_sub(1)..self.len_chars()
+        };
+        self.remove(range);
+        Ok(())
+    }
+
+    /// Delete the given (0-based) line like [`delete_line`](Self::delete_line), and return its
+    /// content (without its line ending). Returns `None` if the line does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one\ntwo\nthree");
+    /// assert_eq!(rope.remove_line(1).as_deref(), Some("two"));
+    /// assert_eq!(rope, "one\nthree");
+    /// assert_eq!(rope.remove_line(2), None);
+    /// ```
+    pub fn remove_line(&mut self, line: usize) -> Option<String> {
+        let content = self.line(line)?;
+        self.delete_line(line).ok()?;
+        Some(content)
+    }
+
+    /// Swap the content of two (0-based) lines. Each line keeps its line ending, so swapping the
+    /// last line (which has no newline) with another line works as expected. With the `history`
+    /// feature, this is a single undo step.
+    ///
+    /// Returns an error if either line does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one\ntwo\nthree");
+    /// assert_eq!(rope.swap_lines(0, 2), Ok(()));
+    /// assert_eq!(rope, "three\ntwo\none");
+    /// assert_eq!(rope.swap_lines(1, 3), Err(RopeError::PositionOutOfBounds { pos: 3, len: 3 }));
+    /// ```
+    pub fn swap_lines(&mut self, a: usize, b: usize) -> Result<(), RopeError> {
+        let len_lines = self.len_lines();
+        for line in [a, b] {
+            if line >= len_lines {
+                return Err(RopeError::PositionOutOfBounds { pos: line, len: len_lines });
+            }
+        }
+        if a == b { return Ok(()); }
+
+        let (a, b) = (a.min(b), a.max(b));
+        let (a_content, b_content) = (self.line(a).unwrap(), self.line(b).unwrap());
+        let (a_start, b_start) = (self.line_to_char(a), self.line_to_char(b));
+
+        #[cfg(feature = "history")]
+        self.begin_undo_group();
+        // Replace the later line first, so the earlier line doesn't move.
+        self.replace(b_start..b_start + count_chars(&b_content), &a_content);
+        self.replace(a_start..a_start + count_chars(&a_content), &b_content);
+        #[cfg(feature = "history")]
+        self.end_undo_group();
+        Ok(())
+    }
+
+    /// Insert `prefix` at the start of every line in `lines` (in 0-based line numbers), like when
+    /// indenting or commenting out a block of text. Returns the number of lines changed.
+    ///
+    /// If `skip_empty` is true, empty lines (including lines which only contain `\r`) are left
+    /// alone. Lines past the end of the rope are ignored.
+    ///
+    /// The lines are found in a single pass over the rope. Then a single cursor moves forward from
+    /// line to line inserting the prefixes, so nearby lines don't each need a search from the start
+    /// of the rope. With the `history` feature, this is a single undo step.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("fn main() {\n\nfoo();\n}");
+    /// assert_eq!(rope.prefix_lines(1..3, "    ", true), 1);
+    /// assert_eq!(rope, "fn main() {\n\n    foo();\n}");
+    /// assert_eq!(rope.prefix_lines(0..10, "// ", false), 4);
+    /// assert_eq!(rope, "// fn main() {\n// \n//     foo();\n// }");
+    /// ```
+    pub fn prefix_lines(&mut self, lines: Range<usize>, prefix: &str, skip_empty: bool) -> usize {
+        if prefix.is_empty() { return 0; }
+        let starts: Vec<usize> = self.line_heads(lines, 2).into_iter()
+            .filter(|(_, head)| !skip_empty || !(head.is_empty() || head == "\r"))
+            .map(|(pos, _)| pos)
+            .collect();
+        if starts.is_empty() { return 0; }
+
+        #[cfg(feature = "history")]
+        self.begin_undo_group();
+        let mut cursor = self.mut_cursor_at_start();
+        // After each insert the cursor is at the end of the prefix, which is the start of the line
+        // in the original content.
+        let mut last = 0;
+        for &pos in &starts {
+            Self::advance_cursor(&mut cursor, pos - last);
+            Self::insert_at_cursor(&mut cursor, prefix);
+            last = pos;
+        }
+        self.cursor_valid = true;
+        #[cfg(feature = "history")]
+        self.end_undo_group();
+        starts.len()
+    }
+
+    /// Remove `prefix` from the start of every line in `lines` which starts with it, like when
+    /// dedenting or uncommenting a block of text. Returns the number of lines changed.
+    ///
+    /// This is the opposite of [`prefix_lines`](Self::prefix_lines), and the lines are edited the
+    /// same way. Lines past the end of the rope are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("    a\n  b\n    c");
+    /// assert_eq!(rope.unprefix_lines(0..3, "    "), 2);
+    /// assert_eq!(rope, "a\n  b\nc");
+    /// ```
+    pub fn unprefix_lines(&mut self, lines: Range<usize>, prefix: &str) -> usize {
+        if prefix.is_empty() { return 0; }
+        let prefix_len = count_chars(prefix);
+
+        let starts: Vec<usize> = self.line_heads(lines, prefix_len).into_iter()
+            .filter(|(_, head)| head == prefix)
+            .map(|(pos, _)| pos)
+            .collect();
+        if starts.is_empty() { return 0; }
+
+        #[cfg(feature = "history")]
+        self.begin_undo_group();
+        let mut cursor = self.mut_cursor_at_start();
+        // After each delete the cursor is at the end of the prefix in the original content.
+        let mut last = 0;
+        for &pos in &starts {
+            Self::advance_cursor(&mut cursor, pos - last);
+            Self::del_at_cursor(&mut cursor, prefix_len);
+            last = pos + prefix_len;
+        }
+        self.cursor_valid = true;
+        #[cfg(feature = "history")]
+        self.end_undo_group();
+        starts.len()
+    }
+
+    /// Returns a 64 bit hash of each line in `lines` (in 0-based line numbers), not including
+    /// the `\n` at the end of the line. Lines past the end of the rope are ignored, so the result
+    /// has one hash for each line which exists.
+    ///
+    /// Peers can compare line hashes (along with [`len_lines`](Self::len_lines)) to find which
+    /// lines differ without sending the lines themselves. Hashes don't depend on how the rope is
+    /// split into nodes, and they're stable between runs and versions of this crate (they use
+    /// 64 bit FNV-1a). But they aren't cryptographic, so don't rely on them to detect changes made
+    /// by an adversary.
+    ///
+    /// The lines are hashed straight out of the rope's chunks, in a single pass.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let a = JumpRope::from("one\ntwo\nthree");
+    /// let b = JumpRope::from("one\n2\nthree");
+    /// let (ha, hb) = (a.line_hashes(0..3), b.line_hashes(0..3));
+    /// assert_eq!(ha.len(), 3);
+    /// assert_eq!(ha[0], hb[0]);
+    /// assert_ne!(ha[1], hb[1]);
+    /// assert_eq!(ha[2], hb[2]);
+    /// ```
+    pub fn line_hashes(&self, lines: Range<usize>) -> Vec<u64> {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        let fnv = |mut hash: u64, s: &str| {
+            for b in s.bytes() {
+                hash = (hash ^ b as u64).wrapping_mul(FNV_PRIME);
+            }
+            hash
+        };
+
+        let end = lines.end.min(self.len_lines());
+        if lines.start >= end { return Vec::new(); }
+        let count = end - lines.start;
+        let mut result = Vec::with_capacity(count);
+
+        let start = self.line_to_char(lines.start);
+        let mut hash = FNV_OFFSET;
+        for chunk in self.slice_substrings(start..self.len_chars()) {
+            let mut parts = chunk.split('\n');
+            // Every part but the last ends a line.
+            let mut part = parts.next().unwrap();
+            for next in parts {
+                result.push(fnv(hash, part));
+                if result.len() == count { return result; }
+                hash = FNV_OFFSET;
+                part = next;
+            }
+            hash = fnv(hash, part);
+        }
+        // The last line in the rope doesn't end in a newline.
+        result.push(hash);
+        result
+    }
+
+    /// The position of the start of each line in `lines`, and up to the first `head_len`
+    /// characters of the line (not including the newline).
+    fn line_heads(&self, lines: Range<usize>, head_len: usize) -> Vec<(usize, String)> {
+        let end = lines.end.min(self.len_lines());
+        let mut result = Vec::with_capacity(end.saturating_sub(lines.start));
+        if lines.start >= end { return result; }
+
+        let mut pos = self.line_to_char(lines.start);
+        let mut line_start = pos;
+        let mut head = String::new();
+        let mut head_chars = 0;
+        for c in self.chars_in_range(pos..) {
+            pos += 1;
+            if c == '\n' {
+                result.push((line_start, std::mem::take(&mut head)));
+                if result.len() == end - lines.start { return result; }
+                line_start = pos;
+                head_chars = 0;
+            } else if head_chars < head_len {
+                head.push(c);
+                head_chars += 1;
+            }
+        }
+        // The last line in the rope doesn't end in a newline.
+        result.push((line_start, head));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRopeSized;
+
+    #[test]
+    fn node_index_for_char() {
+        // Creating a rope fills each node in turn.
+        let mut rope = JumpRopeSized::<10>::from("aaaaaaaaaabbbbbbbbbbcc");
+        assert_eq!(rope.node_index_for_char(0), (0, 0));
+        assert_eq!(rope.node_index_for_char(9), (0, 9));
+        assert_eq!(rope.node_index_for_char(10), (1, 0));
+        assert_eq!(rope.node_index_for_char(21), (2, 1));
+        assert_eq!(rope.node_index_for_char(22), (2, 2));
+
+        // Inserting a full node's worth into the middle of a full node splits it around a new node.
+        rope.insert(3, "xxxxxxxxxx");
+        assert_eq!(rope.node_index_for_char(2), (0, 2));
+        assert_eq!(rope.node_index_for_char(3), (1, 0));
+        assert_eq!(rope.node_index_for_char(12), (1, 9));
+        assert_eq!(rope.node_index_for_char(13), (2, 0));
+        assert_eq!(rope.node_index_for_char(20), (3, 0));
+        assert_eq!(rope.node_index_for_char(32), (4, 2));
+        rope.check();
+
+        // Short inserts go into the node with room for them.
+        rope.insert(1, "yy");
+        assert_eq!(rope.node_index_for_char(4), (0, 4));
+        assert_eq!(rope.node_index_for_char(5), (1, 0));
+
+        let rope = JumpRopeSized::<10>::new();
+        assert_eq!(rope.node_index_for_char(0), (0, 0));
+    }
+    #[test]
+    fn advance_cursor_matches_search() {
+        let content = "κόσμε\nhi there\n𐆚𐆚 and some more text\n".repeat(8);
+        let mut rope = JumpRopeSized::<10>::from(content.as_str());
+        let len = rope.len_chars();
+        let height = rope.head.height as usize;
+        for start in (0..=len).step_by(7) {
+            for by in [0, 1, 2, 9, 10, 11, 40, len - start] {
+                if start + by > len { continue; }
+                let mut cursor = rope.mut_cursor_at_char(start, true);
+                JumpRopeSized::advance_cursor(&mut cursor, by);
+                let moved = rope.cursor;
+                rope.mut_cursor_at_char(start + by, true);
+                assert_eq!(&moved[..height], &rope.cursor[..height], "moving from {} by {}", start, by);
+            }
+        }
+    }
+}
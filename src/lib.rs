@@ -126,8 +126,58 @@ mod gapbuffer;
 mod utils;
 mod iter;
 mod fast_str_tools;
+mod error;
+mod allocator;
 
-pub use crate::jumprope::JumpRope;
+pub use crate::jumprope::{Checkpoint, JumpRope, JumpRopeSized, RopeSnapshot, RopeStats};
+pub use crate::error::RopeError;
+#[cfg(feature = "allocator-api2")]
+pub use crate::allocator::{Allocator, Global};
 
 mod buffered;
-pub use crate::buffered::JumpRopeBuf;
\ No newline at end of file
+pub use crate::buffered::JumpRopeBuf;
+
+mod diff;
+pub use crate::diff::{diff, Edit};
+
+pub mod ot;
+
+mod char_range;
+pub use crate::char_range::{CharRange, EditSpan};
+
+mod word;
+
+mod frozen;
+pub use crate::frozen::FrozenRope;
+
+#[cfg(feature = "fuzzing")]
+mod fuzz;
+#[cfg(feature = "fuzzing")]
+pub use crate::fuzz::FuzzOp;
+
+#[cfg(feature = "marks")]
+mod marks;
+#[cfg(feature = "marks")]
+pub use crate::marks::{Affinity, MarkId};
+
+#[cfg(feature = "history")]
+mod history;
+
+#[cfg(feature = "changes")]
+mod changes;
+#[cfg(feature = "changes")]
+pub use crate::changes::ChangeEvent;
+
+#[cfg(feature = "regex")]
+mod regex_replace;
+
+#[cfg(feature = "content_hash")]
+mod content_hash;
+
+#[cfg(feature = "edit_log")]
+mod edit_log;
+#[cfg(feature = "edit_log")]
+pub use crate::edit_log::{EditLog, LogEntry};
+
+#[cfg(feature = "cow")]
+mod cow;
\ No newline at end of file
@@ -0,0 +1,116 @@
+//! Marks are positions in a rope which move as the rope is edited. These are only available with
+//! the `marks` feature.
+
+use std::ops::Range;
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+/// Identifies a mark added with [`JumpRopeSized::add_mark`]. Ids are never reused, so an id for a
+/// removed mark never refers to a different mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MarkId(usize);
+
+/// Which way a mark moves when text is inserted exactly at its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    /// The mark stays before the inserted text. (Its position doesn't change.)
+    Before,
+    /// The mark moves to after the inserted text, like a text cursor.
+    After,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Mark {
+    id: MarkId,
+    pos: usize,
+    affinity: Affinity,
+}
+
+/// The marks in a rope. Every edit visits every mark, which is cheap enough for thousands of
+/// marks. Marks are kept sorted by id (since ids are handed out in order), so lookups are a
+/// binary search.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MarkSet {
+    marks: Vec<Mark>,
+    next_id: usize,
+}
+
+impl MarkSet {
+    fn index_of(&self, id: MarkId) -> Option<usize> {
+        self.marks.binary_search_by_key(&id, |m| m.id).ok()
+    }
+
+    /// Updates the marks for an insert of len chars at pos.
+    pub(crate) fn insert(&mut self, pos: usize, len: usize) {
+        for m in &mut self.marks {
+            if m.pos > pos || (m.pos == pos && m.affinity == Affinity::After) {
+                m.pos += len;
+            }
+        }
+    }
+
+    /// Updates the marks for a delete of the chars in range. Marks inside the range end up at its
+    /// start.
+    pub(crate) fn remove(&mut self, range: Range<usize>) {
+        for m in &mut self.marks {
+            if m.pos >= range.end {
+                m.pos -= range.end - range.start;
+            } else if m.pos > range.start {
+                m.pos = range.start;
+            }
+        }
+    }
+}
+
+/// These methods are only available if the `marks` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "marks")))]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Adds a mark at the given unicode character position. Marks move as the rope is edited:
+    ///
+    /// - Inserts and deletes before a mark shift it along.
+    /// - If the characters around a mark are deleted, the mark ends up at the start of the deleted
+    ///   range.
+    /// - When text is inserted exactly at a mark, `affinity` decides whether the mark ends up
+    ///   before or after it.
+    ///
+    /// Positions past the end of the rope are truncated to the end.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// let cursor = rope.add_mark(2, Affinity::After);
+    /// let start = rope.add_mark(2, Affinity::Before);
+    /// rope.insert(2, " you");
+    /// assert_eq!(rope.mark_pos(cursor), Some(6));
+    /// assert_eq!(rope.mark_pos(start), Some(2));
+    ///
+    /// rope.remove(0..4);
+    /// assert_eq!(rope.mark_pos(cursor), Some(2));
+    /// assert_eq!(rope.mark_pos(start), Some(0));
+    /// ```
+    pub fn add_mark(&mut self, pos: usize, affinity: Affinity) -> MarkId {
+        let id = MarkId(self.marks.next_id);
+        self.marks.next_id += 1;
+        self.marks.marks.push(Mark { id, pos: pos.min(self.len_chars()), affinity });
+        id
+    }
+
+    /// Returns the current position of a mark, in unicode characters. Returns `None` if the mark
+    /// has been removed.
+    pub fn mark_pos(&self, id: MarkId) -> Option<usize> {
+        self.marks.index_of(id).map(|i| self.marks.marks[i].pos)
+    }
+
+    /// Removes a mark, returning its final position. Returns `None` if the mark had already been
+    /// removed.
+    pub fn remove_mark(&mut self, id: MarkId) -> Option<usize> {
+        self.marks.index_of(id).map(|i| self.marks.marks.remove(i).pos)
+    }
+
+    /// The number of marks in the rope.
+    pub fn num_marks(&self) -> usize {
+        self.marks.marks.len()
+    }
+}
@@ -0,0 +1,371 @@
+//! Operational transform primitives, for building collaborative editing on top of a rope.
+//!
+//! An [`Op`] describes a change to a whole document as a list of runs. Each run retains
+//! (skips over), inserts or deletes characters. All lengths are in unicode characters.
+//!
+//! When two peers make concurrent ops `a` and `b` to the same document, [`Op::transform`] adjusts
+//! each op so it can be applied after the other. Both peers end up with the same document:
+//!
+//! ```
+//! # use jumprope::*;
+//! use jumprope::ot::{Op, Side};
+//!
+//! let doc = JumpRope::from("hello");
+//! let a = Op::from_edit(0, 1, "j", 5); // "jello"
+//! let b = Op::from_edit(5, 0, "!", 5); // "hello!"
+//!
+//! let mut doc_a = doc.clone();
+//! doc_a.apply_op(&a);
+//! doc_a.apply_op(&Op::transform(&b, &a, Side::Right));
+//!
+//! let mut doc_b = doc.clone();
+//! doc_b.apply_op(&b);
+//! doc_b.apply_op(&Op::transform(&a, &b, Side::Left));
+//!
+//! assert_eq!(doc_a, "jello!");
+//! assert_eq!(doc_a, doc_b);
+//! ```
+
+use crate::diff::{common_prefix, common_suffix};
+use crate::fast_str_tools::{char_to_byte_idx, count_chars};
+use crate::{Edit, JumpRopeSized};
+use crate::allocator::Allocator;
+
+/// One run in an [`Op`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Component {
+    /// Skip over this many characters, leaving them unchanged.
+    Retain(usize),
+    /// Insert this text.
+    Insert(String),
+    /// Delete this many characters.
+    Delete(usize),
+}
+
+/// Which op goes first when two concurrent ops insert at the same position. See
+/// [`Op::transform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The op being transformed inserts its text first.
+    Left,
+    /// The other op's text goes first.
+    Right,
+}
+
+/// A change to a whole document, made of retain, insert and delete runs.
+///
+/// Build ops with [`retain`](Op::retain), [`insert`](Op::insert) and [`delete`](Op::delete).
+/// These merge adjacent runs of the same kind, and put inserts before deletes at the same
+/// position, so two ops which make the same change compare equal.
+///
+/// An op must cover the whole document it's applied to. Trailing unchanged text needs to be
+/// retained.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Op {
+    components: Vec<Component>,
+    base_len: usize,
+    target_len: usize,
+}
+
+impl Op {
+    /// Create an empty op. This only applies to an empty document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an op for a single edit to a document of `doc_len` characters: `del` characters
+    /// at `pos` are replaced with `ins`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the deleted range goes past the end of the document.
+    pub fn from_edit(pos: usize, del: usize, ins: &str, doc_len: usize) -> Self {
+        assert!(pos + del <= doc_len, "Edit is past the end of the document");
+        let mut op = Self::new();
+        op.retain(pos).delete(del).insert(ins).retain(doc_len - pos - del);
+        op
+    }
+
+    /// The runs which make up this op.
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// The length of the document this op applies to, in characters.
+    pub fn base_len(&self) -> usize { self.base_len }
+
+    /// The length of the document after this op is applied, in characters.
+    pub fn target_len(&self) -> usize { self.target_len }
+
+    /// Returns true if applying this op doesn't change the document.
+    pub fn is_noop(&self) -> bool {
+        self.components.iter().all(|c| matches!(c, Component::Retain(_)))
+    }
+
+    /// Skip over `n` characters.
+    pub fn retain(&mut self, n: usize) -> &mut Self {
+        if n == 0 { return self; }
+        self.base_len += n;
+        self.target_len += n;
+        if let Some(Component::Retain(last)) = self.components.last_mut() {
+            *last += n;
+        } else {
+            self.components.push(Component::Retain(n));
+        }
+        self
+    }
+
+    /// Insert `s` at the current position.
+    pub fn insert(&mut self, s: &str) -> &mut Self {
+        if s.is_empty() { return self; }
+        self.target_len += count_chars(s);
+
+        let len = self.components.len();
+        // Inserts always go before a delete at the same position.
+        let before_delete = matches!(self.components.last(), Some(Component::Delete(_)));
+        let prev = if before_delete { len.checked_sub(2) } else { len.checked_sub(1) };
+        match prev.map(|i| &mut self.components[i]) {
+            Some(Component::Insert(prev)) => prev.push_str(s),
+            _ if before_delete => self.components.insert(len - 1, Component::Insert(s.into())),
+            _ => self.components.push(Component::Insert(s.into())),
+        }
+        self
+    }
+
+    /// Delete the next `n` characters.
+    pub fn delete(&mut self, n: usize) -> &mut Self {
+        if n == 0 { return self; }
+        self.base_len += n;
+        if let Some(Component::Delete(last)) = self.components.last_mut() {
+            *last += n;
+        } else {
+            self.components.push(Component::Delete(n));
+        }
+        self
+    }
+
+    /// Transform `a` so it can be applied after `b`, where `a` and `b` are concurrent ops on the
+    /// same document. When both ops insert at the same position, `side` says whose text goes
+    /// first.
+    ///
+    /// Transforming both ways (with opposite sides) converges:
+    /// `apply(apply(doc, a), transform(b, a, Right)) == apply(apply(doc, b), transform(a, b, Left))`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ops have different base lengths.
+    pub fn transform(a: &Op, b: &Op, side: Side) -> Op {
+        assert_eq!(a.base_len, b.base_len, "Transformed ops must apply to the same document");
+        let mut result = Op::new();
+        let (mut pa, mut pb) = (Parts::new(a), Parts::new(b));
+        loop {
+            match (pa.peek(), pb.peek()) {
+                (None, None) => break,
+                (Some(Part::Insert(s)), next_b)
+                    if side == Side::Left || !matches!(next_b, Some(Part::Insert(_))) => {
+                    result.insert(s);
+                    pa.advance();
+                }
+                (_, Some(Part::Insert(s))) => {
+                    // Skip over b's insert.
+                    result.retain(count_chars(s));
+                    pb.advance();
+                }
+                (Some(x), Some(y)) => {
+                    let n = x.len().min(y.len());
+                    match (x, y) {
+                        (Part::Retain(_), Part::Retain(_)) => { result.retain(n); }
+                        (Part::Delete(_), Part::Retain(_)) => { result.delete(n); }
+                        // b already deleted this text.
+                        (_, Part::Delete(_)) => {}
+                        _ => unreachable!(),
+                    }
+                    pa.take(n);
+                    pb.take(n);
+                }
+                _ => unreachable!("Ops have the same base length"),
+            }
+        }
+        result
+    }
+
+    /// Combine two consecutive ops into one op with the same effect as applying `a` then `b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `b` doesn't apply to the document made by `a` (that is, if `a.target_len()` and
+    /// `b.base_len()` differ).
+    pub fn compose(a: &Op, b: &Op) -> Op {
+        assert_eq!(a.target_len, b.base_len, "Composed ops must be consecutive");
+        let mut result = Op::new();
+        let (mut pa, mut pb) = (Parts::new(a), Parts::new(b));
+        loop {
+            match (pa.peek(), pb.peek()) {
+                (None, None) => break,
+                (Some(Part::Delete(n)), _) => {
+                    result.delete(n);
+                    pa.advance();
+                }
+                (_, Some(Part::Insert(s))) => {
+                    result.insert(s);
+                    pb.advance();
+                }
+                (Some(x), Some(y)) => {
+                    let n = x.len().min(y.len());
+                    match (x, y) {
+                        (Part::Retain(_), Part::Retain(_)) => { result.retain(n); }
+                        (Part::Retain(_), Part::Delete(_)) => { result.delete(n); }
+                        (Part::Insert(s), Part::Retain(_)) => { result.insert(&s[..char_to_byte_idx(s, n)]); }
+                        // Text inserted by a and deleted by b.
+                        (Part::Insert(_), Part::Delete(_)) => {}
+                        _ => unreachable!(),
+                    }
+                    pa.take(n);
+                    pb.take(n);
+                }
+                _ => unreachable!("a's target length matches b's base length"),
+            }
+        }
+        result
+    }
+
+    /// The edits this op makes, with positions in the base document.
+    fn to_edits(&self) -> Vec<Edit> {
+        let mut edits: Vec<Edit> = Vec::new();
+        let mut pos = 0;
+        for c in &self.components {
+            match c {
+                Component::Retain(n) => pos += n,
+                Component::Insert(s) => edits.push(Edit { range: pos..pos, content: s.clone() }),
+                Component::Delete(n) => {
+                    match edits.last_mut() {
+                        // Merge with the insert just before this delete.
+                        Some(e) if e.range.end == pos => e.range.end += n,
+                        _ => edits.push(Edit { range: pos..pos + n, content: String::new() }),
+                    }
+                    pos += n;
+                }
+            }
+        }
+        edits
+    }
+}
+
+/// The rest of a component, while walking through an op.
+#[derive(Debug, Clone, Copy)]
+enum Part<'a> {
+    Retain(usize),
+    Insert(&'a str),
+    Delete(usize),
+}
+
+impl Part<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Part::Retain(n) | Part::Delete(n) => *n,
+            Part::Insert(s) => count_chars(s),
+        }
+    }
+}
+
+/// Walks through the components of an op. Components can be partly consumed.
+struct Parts<'a> {
+    components: std::slice::Iter<'a, Component>,
+    current: Option<Part<'a>>,
+}
+
+impl<'a> Parts<'a> {
+    fn new(op: &'a Op) -> Self {
+        let mut parts = Parts { components: op.components.iter(), current: None };
+        parts.advance();
+        parts
+    }
+
+    fn peek(&self) -> Option<Part<'a>> {
+        self.current
+    }
+
+    /// Move to the next component, skipping the rest of the current one.
+    fn advance(&mut self) {
+        self.current = self.components.next().map(|c| match c {
+            Component::Retain(n) => Part::Retain(*n),
+            Component::Insert(s) => Part::Insert(s),
+            Component::Delete(n) => Part::Delete(*n),
+        });
+    }
+
+    /// Consume n characters of the current component.
+    fn take(&mut self, n: usize) {
+        self.current = match self.current {
+            Some(Part::Retain(m)) if m > n => Some(Part::Retain(m - n)),
+            Some(Part::Delete(m)) if m > n => Some(Part::Delete(m - n)),
+            Some(Part::Insert(s)) if char_to_byte_idx(s, n) < s.len() => {
+                Some(Part::Insert(&s[char_to_byte_idx(s, n)..]))
+            }
+            _ => None,
+        };
+        if self.current.is_none() { self.advance(); }
+    }
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Apply an operational transform [`Op`] to the rope. The op's edits are applied in a single
+    /// batch (see [`apply_edits`](Self::apply_edits)).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the op's base length isn't the length of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use jumprope::ot::Op;
+    ///
+    /// let mut rope = JumpRope::from("hi there");
+    /// let mut op = Op::new();
+    /// op.retain(3).delete(5).insert("you");
+    /// rope.apply_op(&op);
+    /// assert_eq!(rope, "hi you");
+    /// ```
+    pub fn apply_op(&mut self, op: &Op) {
+        assert_eq!(op.base_len, self.len_chars(), "Op doesn't apply to a rope of this length");
+        self.apply_edits(&op.to_edits());
+    }
+
+    /// Returns an op which turns the rope's content into `other`.
+    ///
+    /// The op keeps the common prefix and suffix of the two documents, and replaces everything
+    /// between them. This is cheap to compute (the rope isn't copied), but the op isn't minimal
+    /// when there are several separate changes. Use [`diff`](crate::diff()) for a minimal set of
+    /// edits between two ropes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use jumprope::ot::Component;
+    ///
+    /// let mut rope = JumpRope::from("hello world");
+    /// let op = rope.diff("hello there world");
+    /// assert_eq!(op.components(), [
+    ///     Component::Retain(6),
+    ///     Component::Insert("there ".into()),
+    ///     Component::Retain(5),
+    /// ]);
+    ///
+    /// rope.apply_op(&op);
+    /// assert_eq!(rope, "hello there world");
+    /// ```
+    pub fn diff(&self, other: &str) -> Op {
+        let (len, other_len) = (self.len_chars(), count_chars(other));
+        let prefix = common_prefix(self.substrings(), std::iter::once(other));
+        let suffix = common_suffix(self.substrings().rev(), std::iter::once(other))
+            .min(len.min(other_len) - prefix);
+
+        let inserted = &other[char_to_byte_idx(other, prefix)..char_to_byte_idx(other, other_len - suffix)];
+        let mut op = Op::new();
+        op.retain(prefix).delete(len - prefix - suffix).insert(inserted).retain(suffix);
+        op
+    }
+}
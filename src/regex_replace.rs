@@ -0,0 +1,58 @@
+//! Search and replace with regular expressions. This is only available with the `regex` feature.
+
+use regex::Regex;
+use crate::fast_str_tools::count_chars;
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+/// These methods are only available if the `regex` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "regex")))]
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Replace every match of `re` in the rope with `replacement`, and return the number of
+    /// matches replaced. The result is the same as
+    /// [`Regex::replace_all`](regex::Regex::replace_all) on the rope's content, so `replacement`
+    /// can refer to capture groups with `$1` or `${name}` (and `$$` is a literal `$`).
+    ///
+    /// Regexes need contiguous text to search, so this copies the rope's content into a string
+    /// first. (This means matches can span any number of nodes.) The matches are then replaced
+    /// from the start of the rope to the end. With the `history` feature, this is a single undo
+    /// step.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use regex::Regex;
+    ///
+    /// let mut rope = JumpRope::from("let a = 1; let b = 2;");
+    /// let re = Regex::new(r"let (\w+)").unwrap();
+    /// assert_eq!(rope.regex_replace_all(&re, "const ${1}_x"), 2);
+    /// assert_eq!(rope, "const a_x = 1; const b_x = 2;");
+    /// ```
+    pub fn regex_replace_all(&mut self, re: &Regex, replacement: &str) -> usize {
+        let text = self.to_string();
+
+        #[cfg(feature = "history")]
+        self.begin_undo_group();
+        let mut count = 0;
+        // The position in the rope of text[last_end..].
+        let mut pos = 0;
+        let mut last_end = 0;
+        let mut expanded = String::new();
+        for caps in re.captures_iter(&text) {
+            let m = caps.get(0).unwrap();
+            pos += count_chars(&text[last_end..m.start()]);
+
+            expanded.clear();
+            caps.expand(replacement, &mut expanded);
+            self.replace(pos..pos + count_chars(m.as_str()), &expanded);
+
+            pos += count_chars(&expanded);
+            last_end = m.end();
+            count += 1;
+        }
+        #[cfg(feature = "history")]
+        self.end_undo_group();
+        count
+    }
+}
@@ -1,3 +1,4 @@
+use std::ops::{Bound, Range, RangeBounds};
 use crate::fast_str_tools::*;
 
 // Get the byte offset after char_pos utf8 characters
@@ -50,10 +51,97 @@ pub(crate) fn str_chars_to_bytes_rev(s: &str, char_len: usize) -> usize {
 //         .sum()
 // }
 
+/// Returns the largest byte offset <= max_bytes which falls on a character boundary in s.
+pub(crate) fn floor_char_boundary(s: &str, max_bytes: usize) -> usize {
+    if max_bytes >= s.len() { return s.len(); }
+    let mut byte_pos = max_bytes;
+    // Slide back to a character boundary.
+    while s.as_bytes()[byte_pos] & 0b1100_0000 == 0b1000_0000 {
+        byte_pos -= 1;
+    }
+    byte_pos
+}
+
 #[cfg(feature = "line_conversion")]
 pub(crate) fn count_lines(s: &str) -> usize {
-    // I'm sure there's faster implementations of this but this will do for now.
-    s.as_bytes().iter().filter(|b| **b == ('\n' as u8)).count()
+    #[cfg(feature = "memchr")]
+    { memchr::memchr_iter(b'\n', s.as_bytes()).count() }
+    #[cfg(not(feature = "memchr"))]
+    { count_lines_scalar(s) }
+}
+
+#[cfg(all(feature = "line_conversion", any(test, not(feature = "memchr"))))]
+fn count_lines_scalar(s: &str) -> usize {
+    s.as_bytes().iter().filter(|b| **b == b'\n').count()
+}
+
+/// Returns the byte offset of the start of the given line in s - which is the offset just after
+/// the line'th newline. If s has fewer lines than that, returns s.len().
+#[cfg(feature = "line_conversion")]
+pub(crate) fn line_start_byte(s: &str, line: usize) -> usize {
+    if line == 0 { return 0; }
+    #[cfg(feature = "memchr")]
+    let newline = memchr::memchr_iter(b'\n', s.as_bytes()).nth(line - 1);
+    #[cfg(not(feature = "memchr"))]
+    let newline = newline_positions_scalar(s).nth(line - 1);
+    newline.map_or(s.len(), |i| i + 1)
+}
+
+#[cfg(all(feature = "line_conversion", any(test, not(feature = "memchr"))))]
+fn newline_positions_scalar(s: &str) -> impl Iterator<Item = usize> + '_ {
+    s.bytes().enumerate()
+        .filter(|(_, b)| *b == b'\n')
+        .map(|(i, _)| i)
+}
+
+/// Converts any range of positions into a plain start..end range, where unbounded ends are 0 and
+/// len. The result isn't checked against len. Bounds of usize::MAX saturate instead of
+/// overflowing, so they're still out of range.
+/// Search for `pattern` in the concatenation of `chunks`, returning the character offset of the
+/// first match. Matches can span chunks.
+pub(crate) fn find_in_chunks<'a, I: Iterator<Item = &'a str>>(chunks: I, pattern: &str) -> Option<usize> {
+    if pattern.is_empty() { return Some(0); }
+
+    // The end of the content searched so far, which could hold the start of a match spanning
+    // into the next chunk. This is always shorter than the pattern.
+    let mut window = String::new();
+    let mut window_pos = 0;
+
+    for s in chunks {
+        window.push_str(s);
+        if let Some(i) = window.find(pattern) {
+            return Some(window_pos + count_chars(&window[..i]));
+        }
+
+        let mut keep = window.len().saturating_sub(pattern.len() - 1);
+        while !window.is_char_boundary(keep) { keep += 1; }
+        window_pos += count_chars(&window[..keep]);
+        window.drain(..keep);
+    }
+
+    None
+}
+
+pub(crate) fn bounds_to_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.saturating_add(1),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    start..end
+}
+
+/// Adds a signed amount to a count. Counts can never go negative (or overflow), so in debug builds
+/// this checks the result is in range.
+#[inline]
+pub(crate) fn add_signed(n: usize, by: isize) -> usize {
+    debug_assert!(n.checked_add_signed(by).is_some(), "{} + {} is out of range", n, by);
+    n.wrapping_add_signed(by)
 }
 
 #[cfg(test)]
@@ -86,4 +174,74 @@ mod tests {
         assert_eq!(count_lines("\n"), 1);
         assert_eq!(count_lines("fop\n\n"), 2);
     }
+
+    #[test]
+    fn floor_char_boundary_tests() {
+        assert_eq!(floor_char_boundary("", 10), 0);
+        assert_eq!(floor_char_boundary("abc", 2), 2);
+        assert_eq!(floor_char_boundary("abc", 10), 3);
+        // 'ό' is 2 bytes, '↯' is 3 bytes.
+        assert_eq!(floor_char_boundary("aό", 2), 1);
+        assert_eq!(floor_char_boundary("↯↯", 5), 3);
+        assert_eq!(floor_char_boundary("↯↯", 6), 6);
+    }
+
+    #[test]
+    #[cfg(feature = "line_conversion")]
+    fn line_start_byte_tests() {
+        assert_eq!(line_start_byte("", 0), 0);
+        assert_eq!(line_start_byte("", 1), 0);
+        assert_eq!(line_start_byte("ab\ncd\n", 1), 3);
+        assert_eq!(line_start_byte("ab\ncd\n", 2), 6);
+        assert_eq!(line_start_byte("ab\ncd\n", 3), 6);
+    }
+
+    // Checks the memchr scan against the scalar one over every chunk of a big rope.
+    #[test]
+    #[cfg(all(feature = "line_conversion", feature = "memchr"))]
+    #[cfg_attr(miri, ignore)] // Far too slow under miri.
+    fn memchr_matches_scalar() {
+        let line = "Some ascii text, then κόσμε 😘 and a line break\n";
+        let mut content = String::new();
+        while content.len() < 4_000_000 {
+            content.push_str(&line[..content.len() % line.len()]);
+            content.push_str(line);
+        }
+        let rope = crate::JumpRope::from(content.as_str());
+
+        let mut lines = 0;
+        for chunk in rope.substrings() {
+            let n = count_lines(chunk);
+            assert_eq!(n, count_lines_scalar(chunk));
+            for l in 0..=n + 1 {
+                let expect = if l == 0 { 0 } else {
+                    newline_positions_scalar(chunk).nth(l - 1).map_or(chunk.len(), |i| i + 1)
+                };
+                assert_eq!(line_start_byte(chunk, l), expect);
+            }
+            lines += n;
+        }
+        assert_eq!(lines, count_lines_scalar(&content));
+        assert_eq!(rope.len_lines(), lines + 1);
+
+        for line in [1, 1000, lines / 2, lines] {
+            let byte = newline_positions_scalar(&content).nth(line - 1).unwrap() + 1;
+            assert_eq!(rope.line_to_char(line), content[..byte].chars().count());
+        }
+    }
+
+    #[test]
+    fn add_signed_tests() {
+        assert_eq!(add_signed(10, 5), 15);
+        assert_eq!(add_signed(10, -10), 0);
+        assert_eq!(add_signed(usize::MAX - 1, 1), usize::MAX);
+        assert_eq!(add_signed(usize::MAX, isize::MIN), usize::MAX / 2);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn add_signed_underflow() {
+        add_signed(3, -4);
+    }
 }
@@ -0,0 +1,161 @@
+//! Deleting by word, for editor commands like ctrl+backspace and ctrl+delete.
+//!
+//! Word boundaries are found with simple character classes rather than the unicode word
+//! segmentation rules. (Like grapheme clusters, those rules need large tables which change between
+//! unicode versions.) See [`JumpRopeSized::delete_word_backward`] for the details.
+
+use crate::JumpRopeSized;
+use crate::allocator::Allocator;
+
+const ZWJ: char = '\u{200D}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Newline,
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn class(c: char) -> Class {
+    if c == '\n' { Class::Newline }
+    else if c.is_whitespace() { Class::Whitespace }
+    else if c.is_alphanumeric() || c == '_' { Class::Word }
+    else { Class::Punctuation }
+}
+
+/// Characters which attach to the character before them: combining marks, variation selectors,
+/// emoji modifiers and tags, and the zero width joiner.
+fn is_extend(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}' | '\u{FE20}'..='\u{FE2F}' | '\u{FE00}'..='\u{FE0F}'
+        | '\u{1F3FB}'..='\u{1F3FF}' | '\u{E0020}'..='\u{E007F}' | '\u{E0100}'..='\u{E01EF}'
+        | ZWJ)
+}
+
+impl<const N: usize, A: Allocator> JumpRopeSized<N, A> {
+    /// Returns true if a word deletion can stop at this position. Positions between a character
+    /// and a combining mark (or either side of a zero width joiner) aren't boundaries.
+    fn is_cluster_boundary(&self, pos: usize) -> bool {
+        if pos == 0 || pos >= self.len_chars() { return true; }
+        !is_extend(self.char_at(pos).unwrap()) && self.char_at(pos - 1) != Some(ZWJ)
+    }
+
+    /// The start of the cluster which ends at pos.
+    fn prev_cluster(&self, pos: usize) -> (usize, Class) {
+        let mut start = pos - 1;
+        while !self.is_cluster_boundary(start) { start -= 1; }
+        (start, class(self.char_at(start).unwrap()))
+    }
+
+    /// The end of the cluster which starts at pos.
+    fn next_cluster(&self, pos: usize) -> (usize, Class) {
+        let c = class(self.char_at(pos).unwrap());
+        let mut end = pos + 1;
+        while !self.is_cluster_boundary(end) { end += 1; }
+        (end, c)
+    }
+
+    /// Delete from `pos` back to the start of the previous word, like ctrl+backspace in most
+    /// editors. Returns the position the deletion started from (which is where the cursor should
+    /// go) and the deleted text. Nothing is deleted at the start of the rope.
+    ///
+    /// Characters are grouped into words (letters, digits and `_`), runs of punctuation and runs
+    /// of whitespace. Any whitespace just before `pos` is deleted along with the word, punctuation
+    /// or newline before it. Newlines aren't grouped, so this deletes at most one newline (joining
+    /// the line onto the line above), and never any text on the line above.
+    ///
+    /// Combining marks, variation selectors and zero width joiners stay attached to the character
+    /// before them, so (for example) an emoji sequence is never split.
+    ///
+    /// The text is deleted with [`remove`](Self::remove), so (with the `history` feature) it can
+    /// be undone like any other edit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("let x = foo.bar  ");
+    /// assert_eq!(rope.delete_word_backward(17), (12, "bar  ".into()));
+    /// assert_eq!(rope.delete_word_backward(12), (11, ".".into()));
+    /// assert_eq!(rope, "let x = foo");
+    /// ```
+    pub fn delete_word_backward(&mut self, pos: usize) -> (usize, String) {
+        assert!(pos <= self.len_chars(), "Position {} is past the end of the rope", pos);
+        let mut start = pos;
+        while start > 0 {
+            let (prev, c) = self.prev_cluster(start);
+            if c != Class::Whitespace { break; }
+            start = prev;
+        }
+
+        if start > 0 {
+            let (prev, kind) = self.prev_cluster(start);
+            start = prev;
+            if kind != Class::Newline {
+                while start > 0 {
+                    let (prev, c) = self.prev_cluster(start);
+                    if c != kind { break; }
+                    start = prev;
+                }
+            }
+        }
+
+        let deleted: String = self.chars_in_range(start..pos).collect();
+        self.remove(start..pos);
+        (start, deleted)
+    }
+
+    /// Delete from `pos` forward to the end of the next word, like ctrl+delete in most editors.
+    /// Returns `pos` (the cursor doesn't move) and the deleted text. Nothing is deleted at the end
+    /// of the rope.
+    ///
+    /// Words are found in the same way as [`delete_word_backward`](Self::delete_word_backward).
+    /// Any whitespace at `pos` is deleted along with the word, punctuation or newline after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("foo  bar()");
+    /// assert_eq!(rope.delete_word_forward(3), (3, "  bar".into()));
+    /// assert_eq!(rope.delete_word_forward(3), (3, "()".into()));
+    /// assert_eq!(rope.delete_word_forward(3), (3, "".into()));
+    /// assert_eq!(rope, "foo");
+    /// ```
+    pub fn delete_word_forward(&mut self, pos: usize) -> (usize, String) {
+        let len = self.len_chars();
+        assert!(pos <= len, "Position {} is past the end of the rope", pos);
+        let mut end = pos;
+        while end < len {
+            let (next, c) = self.next_cluster(end);
+            if c != Class::Whitespace { break; }
+            end = next;
+        }
+
+        if end < len {
+            let (next, kind) = self.next_cluster(end);
+            end = next;
+            if kind != Class::Newline {
+                while end < len {
+                    let (next, c) = self.next_cluster(end);
+                    if c != kind { break; }
+                    end = next;
+                }
+            }
+        }
+
+        let deleted: String = self.chars_in_range(pos..end).collect();
+        self.remove(pos..end);
+        (pos, deleted)
+    }
+}
@@ -0,0 +1,87 @@
+// Count allocations made by the rope. This lives in its own test binary because it replaces the
+// global allocator. Allocations are counted per thread, because the test harness allocates from
+// other threads while tests run.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use jumprope::JumpRope;
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|a| a.get())
+}
+
+// Simulate someone typing a paragraph, then deleting it and typing it again. Once the first
+// paragraph has been typed, the rope should have enough spare nodes to never need the allocator
+// again.
+#[test]
+fn typing_reuses_nodes() {
+    let text = "Hello there! This is some text which spans several nodes in the rope. ";
+    let mut rope = JumpRope::new();
+
+    let mut run = |rope: &mut JumpRope| {
+        for (i, c) in text.chars().enumerate() {
+            let mut buf = [0; 4];
+            rope.insert(i, c.encode_utf8(&mut buf));
+        }
+        // Backspace over half of it, then select and delete the rest.
+        for _ in 0..text.len() / 2 {
+            rope.remove(rope.len_chars() - 1..rope.len_chars());
+        }
+        rope.remove(0..rope.len_chars());
+    };
+
+    run(&mut rope);
+    let before = allocations();
+    for _ in 0..100 {
+        run(&mut rope);
+    }
+    let after = allocations();
+    assert_eq!(after - before, 0);
+
+    rope.insert(0, text);
+    rope.clear();
+    let before = allocations();
+    rope.insert(0, text);
+    assert_eq!(allocations(), before);
+    assert_eq!(rope, text);
+}
+
+// Rendering into a scratch buffer which already has enough capacity shouldn't allocate.
+#[test]
+fn write_to_string_reuses_buffer() {
+    let rope = JumpRope::from("Hello there! This is some text which spans several nodes in the rope. ".repeat(10));
+    let mut scratch = String::with_capacity(rope.len_bytes());
+
+    let before = allocations();
+    for _ in 0..10 {
+        scratch.clear();
+        rope.write_to_string(&mut scratch);
+        scratch.clear();
+        rope.to_string_in_range(10..300, &mut scratch);
+    }
+    assert_eq!(allocations(), before);
+
+    scratch.clear();
+    rope.write_to_string(&mut scratch);
+    assert_eq!(rope, scratch);
+}
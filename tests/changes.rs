@@ -0,0 +1,91 @@
+#![cfg(feature = "changes")]
+
+use std::ops::Range;
+use rand::prelude::*;
+use jumprope::{diff, ChangeEvent, JumpRope, JumpRopeSized};
+
+mod common;
+use common::{random_edit, random_range, random_str};
+
+fn byte_pos(s: &str, char_pos: usize) -> usize {
+    s.char_indices().nth(char_pos).map_or(s.len(), |(i, _)| i)
+}
+
+/// Replay one edit's changes against the text from before the edit. The events don't carry the
+/// inserted text, so it's read out of the content after the edit. Each inserted range is moved
+/// through the events after it to find where it ended up.
+fn replay(before: &str, events: &[ChangeEvent], after: &str) -> String {
+    let mut inserted: Vec<Range<usize>> = events.iter()
+        .map(|e| e.char_pos..e.char_pos + e.chars_inserted)
+        .collect();
+    for (j, later) in events.iter().enumerate() {
+        for range in &mut inserted[..j] {
+            if later.char_pos + later.chars_removed <= range.start {
+                range.start = range.start - later.chars_removed + later.chars_inserted;
+                range.end = range.end - later.chars_removed + later.chars_inserted;
+            } else {
+                assert!(later.char_pos >= range.end, "Edits overlap");
+            }
+        }
+    }
+
+    let mut text = before.to_string();
+    for (e, range) in events.iter().zip(inserted) {
+        let start = byte_pos(&text, e.char_pos);
+        assert_eq!(start, e.byte_pos);
+        let end = byte_pos(&text, e.char_pos + e.chars_removed);
+        assert_eq!(end - start, e.bytes_removed);
+
+        let content: String = after.chars().skip(range.start).take(range.len()).collect();
+        assert_eq!(content.len(), e.bytes_inserted);
+        text.replace_range(start..end, &content);
+    }
+    text
+}
+
+#[test]
+fn changes_replay_edits() {
+    let mut rng = SmallRng::seed_from_u64(40);
+    let mut rope = JumpRopeSized::<16>::from(random_str(50, &mut rng));
+    rope.track_changes();
+
+    for _ in 0..1000 {
+        let before = rope.to_string();
+        if rng.gen_bool(0.05) {
+            let mut other = rope.clone();
+            other.replace(random_range(rope.len_chars(), &mut rng), &random_str(rng.gen_range(0..5), &mut rng));
+            other.insert(0, "xx");
+            rope.apply_edits(&diff(&rope, &other));
+        } else {
+            random_edit(&mut rope, &mut rng);
+        }
+
+        let after = rope.to_string();
+        assert_eq!(replay(&before, &rope.take_changes(), &after), after);
+    }
+}
+
+#[test]
+fn changes_whole_rope_edits() {
+    let mut rope = JumpRope::from("hi");
+    rope.track_changes();
+    rope.clear();
+    rope.clear();
+    rope.set_content("");
+    rope.set_content("↯a");
+    assert_eq!(rope.take_changes(), [
+        ChangeEvent { char_pos: 0, chars_removed: 2, chars_inserted: 0, byte_pos: 0, bytes_removed: 2, bytes_inserted: 0 },
+        ChangeEvent { char_pos: 0, chars_removed: 0, chars_inserted: 2, byte_pos: 0, bytes_removed: 0, bytes_inserted: 4 },
+    ]);
+
+    let (mut left, mut right) = rope.split_at_char(1).unwrap();
+    assert_eq!(left.take_changes(), [
+        ChangeEvent { char_pos: 1, chars_removed: 1, chars_inserted: 0, byte_pos: 3, bytes_removed: 1, bytes_inserted: 0 },
+    ]);
+    // The second half isn't tracked.
+    assert!(right.take_changes().is_empty());
+
+    left.stop_tracking_changes();
+    left.push_str("x");
+    assert!(left.take_changes().is_empty());
+}
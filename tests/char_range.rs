@@ -0,0 +1,123 @@
+use jumprope::{CharRange, Edit, EditSpan, JumpRope};
+
+fn r(start: usize, end: usize) -> CharRange {
+    CharRange::new(start, end)
+}
+
+#[test]
+fn char_range_is_normalized() {
+    assert_eq!(CharRange::new(5, 2), r(2, 5));
+    assert_eq!(CharRange::from(7..3), r(3, 7));
+    assert_eq!(r(2, 5).len(), 3);
+    assert!(r(4, 4).is_empty());
+    let range: std::ops::Range<usize> = r(1, 3).into();
+    assert_eq!(range, 1..3);
+}
+
+#[test]
+fn transform_cases() {
+    let sel = r(10, 20);
+    let cases = [
+        // Before the range.
+        (EditSpan::insert(5, 3), Some(r(13, 23))),
+        (EditSpan::delete(2..6), Some(r(6, 16))),
+        (EditSpan::delete(5..10), Some(r(5, 15))),
+        // Inserting at the start shifts the range.
+        (EditSpan::insert(10, 2), Some(r(12, 22))),
+        // After the range.
+        (EditSpan::insert(20, 2), Some(r(10, 20))),
+        (EditSpan::delete(20..30), Some(r(10, 20))),
+        (EditSpan::insert(25, 2), Some(r(10, 20))),
+        // Inside the range.
+        (EditSpan::insert(15, 4), Some(r(10, 24))),
+        (EditSpan::delete(12..15), Some(r(10, 17))),
+        (EditSpan { pos: 10, removed: 5, inserted: 1 }, Some(r(10, 16))),
+        // Whole range deleted.
+        (EditSpan::delete(10..20), None),
+        (EditSpan::delete(0..30), None),
+        (EditSpan { pos: 10, removed: 10, inserted: 3 }, None),
+        // Overlapping the start.
+        (EditSpan::delete(5..15), Some(r(5, 10))),
+        (EditSpan { pos: 5, removed: 10, inserted: 2 }, Some(r(7, 12))),
+        // Overlapping the end.
+        (EditSpan::delete(15..25), Some(r(10, 15))),
+        (EditSpan { pos: 15, removed: 10, inserted: 2 }, Some(r(10, 15))),
+    ];
+    for (edit, expected) in cases {
+        assert_eq!(sel.transform_after_edit(&edit), expected, "{:?}", edit);
+    }
+}
+
+#[test]
+fn transform_empty_range() {
+    let cursor = r(5, 5);
+    assert_eq!(cursor.transform_after_edit(&EditSpan::insert(5, 2)), Some(r(7, 7)));
+    assert_eq!(cursor.transform_after_edit(&EditSpan::insert(6, 2)), Some(r(5, 5)));
+    assert_eq!(cursor.transform_after_edit(&EditSpan::delete(2..5)), Some(r(2, 2)));
+    assert_eq!(cursor.transform_after_edit(&EditSpan::delete(5..8)), Some(r(5, 5)));
+    assert_eq!(cursor.transform_after_edit(&EditSpan::delete(2..8)), Some(r(2, 2)));
+}
+
+#[test]
+fn transform_matches_model() {
+    // Track which of the original characters end up inside the transformed range.
+    let len = 8;
+    for start in 0..=len {
+        for end in start..=len {
+            let sel = r(start, end);
+            for pos in 0..=len {
+                for removed in 0..=len - pos {
+                    for inserted in 0..3 {
+                        let edit = EditSpan { pos, removed, inserted };
+                        let mut doc: Vec<Option<usize>> = (0..len).map(Some).collect();
+                        doc.splice(pos..pos + removed, (0..inserted).map(|_| None));
+
+                        let survivors: Vec<usize> = (start..end)
+                            .filter(|i| *i < pos || *i >= pos + removed)
+                            .collect();
+
+                        match sel.transform_after_edit(&edit) {
+                            None => {
+                                assert!(!sel.is_empty());
+                                assert!(survivors.is_empty());
+                            }
+                            Some(result) => {
+                                assert!(result.end() <= doc.len());
+                                let inside: Vec<usize> = doc[result.to_range()].iter()
+                                    .filter_map(|c| *c)
+                                    .collect();
+                                assert_eq!(inside, survivors, "{:?} {:?}", sel, edit);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn transform_from_diff_edit() {
+    let edit = Edit { range: 2..4, content: "κ↯𐆚".into() };
+    assert_eq!(EditSpan::from(&edit), EditSpan { pos: 2, removed: 2, inserted: 3 });
+}
+
+#[test]
+fn rope_range_methods() {
+    let mut rope = JumpRope::from("hello κ world");
+    assert_eq!(rope.slice_range(&r(6, 7)), "κ");
+    assert_eq!(rope.slice_range(&r(3, 3)), "");
+
+    rope.replace_range(&r(6, 7), "big");
+    assert_eq!(rope, "hello big world");
+
+    rope.delete_range(&r(5, 9));
+    assert_eq!(rope, "hello world");
+    rope.check();
+}
+
+#[test]
+#[should_panic]
+fn slice_range_out_of_bounds() {
+    JumpRope::from("hi").slice_range(&r(1, 5));
+}
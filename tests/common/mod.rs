@@ -0,0 +1,74 @@
+//! Helpers shared by the integration tests. Each test binary includes this with `mod common;`.
+
+// Not every test binary uses every helper.
+#![allow(dead_code)]
+
+use std::ops::Range;
+use rand::prelude::*;
+use jumprope::JumpRopeSized;
+
+/// A small alphabet, so random strings repeat themselves (which gives searches and diffs
+/// something to find). It includes newlines and characters which are 2, 3 and 4 bytes long.
+const CHARS: &[char] = &['a', 'b', ' ', '\n', 'κ', '↯', '𐆚'];
+
+/// A random string of `len` characters from a small alphabet.
+pub fn random_str(len: usize, rng: &mut SmallRng) -> String {
+    (0..len).map(|_| CHARS[rng.gen_range(0..CHARS.len())]).collect()
+}
+
+/// A random range of up to 20 characters, in content which is `len` characters long.
+pub fn random_range(len: usize, rng: &mut SmallRng) -> Range<usize> {
+    let pos = rng.gen_range(0..=len);
+    pos..(pos + rng.gen_range(0..20usize)).min(len)
+}
+
+/// Make a random edit to the rope, using any of the methods which change its content or its
+/// nodes. Returns the content the rope should have afterwards.
+pub fn random_edit(rope: &mut JumpRopeSized<16>, rng: &mut SmallRng) -> String {
+    let mut chars: Vec<char> = rope.to_string().chars().collect();
+    let len = chars.len();
+    let range = random_range(len, rng);
+    match rng.gen_range(0..20) {
+        0 => {
+            let s = random_str(rng.gen_range(0..50), rng);
+            rope.set_content(&s);
+            return s;
+        }
+        1 => {
+            let s = random_str(rng.gen_range(0..50), rng);
+            rope.append(JumpRopeSized::from(s.as_str()));
+            chars.extend(s.chars());
+        }
+        2 => {
+            let (left, _) = std::mem::take(rope).split_at_char(range.start).unwrap();
+            *rope = left;
+            chars.truncate(range.start);
+        }
+        3 if len < 20 => {
+            rope.clear();
+            chars.clear();
+        }
+        4 => rope.compact(),
+        5 => rope.rebalance(),
+        6 => {
+            let parts = [random_str(3, rng), random_str(10, rng)];
+            rope.extend(parts.iter().map(String::as_str));
+            chars.extend(parts.concat().chars());
+        }
+        7..=11 => {
+            rope.remove(range.clone());
+            chars.drain(range);
+        }
+        12..=15 => {
+            let s = random_str(rng.gen_range(0..20), rng);
+            rope.insert(range.start, &s);
+            chars.splice(range.start..range.start, s.chars());
+        }
+        _ => {
+            let s = random_str(rng.gen_range(0..5), rng);
+            rope.replace(range.clone(), &s);
+            chars.splice(range, s.chars());
+        }
+    }
+    chars.into_iter().collect()
+}
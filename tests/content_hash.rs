@@ -0,0 +1,65 @@
+#![cfg(feature = "content_hash")]
+
+use std::collections::HashMap;
+use rand::prelude::*;
+use jumprope::{JumpRope, JumpRopeSized};
+
+mod common;
+use common::random_str;
+
+#[test]
+fn hash_ignores_layout() {
+    let mut rng = SmallRng::seed_from_u64(50);
+    let s = random_str(1000, &mut rng);
+    let hash = JumpRope::from(s.as_str()).content_hash();
+    assert_eq!(JumpRopeSized::<16>::from(s.as_str()).content_hash(), hash);
+
+    // Built backwards in small pieces, so the nodes are split differently.
+    let chars: Vec<char> = s.chars().collect();
+    let mut r = JumpRopeSized::<16>::new();
+    r.enable_content_hash();
+    for piece in chars.rchunks(3) {
+        r.insert(0, &piece.iter().collect::<String>());
+    }
+    assert_eq!(r, s.as_str());
+    r.check();
+    assert_eq!(r.content_hash(), hash);
+    assert_eq!(r.clone().content_hash(), hash);
+
+    assert_eq!(JumpRope::new().content_hash(), JumpRopeSized::<16>::from("").content_hash());
+    assert_ne!(JumpRope::from("\0").content_hash(), JumpRope::new().content_hash());
+}
+
+#[test]
+fn hash_through_edits() {
+    let mut rng = SmallRng::seed_from_u64(51);
+    let mut r = JumpRopeSized::<16>::new();
+    r.enable_content_hash();
+    let mut expected = String::new();
+
+    // Every distinct document seen has a distinct hash.
+    let mut seen: HashMap<u64, String> = HashMap::new();
+    for i in 0..2000 {
+        let len = r.len_chars();
+        let pos = rng.gen_range(0..=len);
+        if len == 0 || rng.gen_bool(0.55) {
+            let s = random_str(rng.gen_range(1..10), &mut rng);
+            r.insert(pos, &s);
+            let byte_pos = expected.char_indices().nth(pos).map_or(expected.len(), |(i, _)| i);
+            expected.insert_str(byte_pos, &s);
+        } else {
+            let end = (pos + rng.gen_range(1..10usize)).min(len);
+            r.remove(pos..end);
+            expected = expected.chars().take(pos).chain(expected.chars().skip(end)).collect();
+        }
+
+        // Ask for the hash most of the time, so nodes are a mix of cached and dirty.
+        if i % 4 != 0 {
+            let hash = r.content_hash();
+            assert_eq!(hash, JumpRopeSized::<16>::from(expected.as_str()).content_hash());
+            let prev = seen.entry(hash).or_insert_with(|| expected.clone());
+            assert_eq!(*prev, expected, "Hash collision");
+        }
+    }
+    assert_eq!(r, expected.as_str());
+}
@@ -0,0 +1,177 @@
+#![cfg(feature = "cow")]
+
+// These tests are small enough to run under miri, which also checks that shared nodes are freed
+// exactly once.
+
+use rand::prelude::*;
+use jumprope::{JumpRope, JumpRopeSized};
+
+mod common;
+use common::{random_edit, random_str};
+
+#[test]
+fn forks_are_independent() {
+    let mut rng = SmallRng::seed_from_u64(80);
+    let steps = if cfg!(miri) { 30 } else { 500 };
+
+    let start = random_str(200, &mut rng);
+    let mut ropes = vec![(JumpRopeSized::<16>::from(start.as_str()), start)];
+    for _ in 0..steps {
+        let i = rng.gen_range(0..ropes.len());
+        match rng.gen_range(0..10) {
+            0 if ropes.len() < 6 => {
+                let fork = (ropes[i].0.fork(), ropes[i].1.clone());
+                assert!(ropes[i].0.is_shared() && fork.0.is_shared());
+                ropes.push(fork);
+            }
+            1 if ropes.len() > 1 => { ropes.swap_remove(i); }
+            _ => {
+                let (rope, expected) = &mut ropes[i];
+                *expected = random_edit(rope, &mut rng);
+            }
+        }
+
+        for (rope, expected) in &ropes {
+            rope.check();
+            assert_eq!(rope, expected);
+        }
+    }
+}
+
+#[test]
+fn edited_rope_stops_sharing() {
+    let rope = JumpRopeSized::<16>::from("a".repeat(200));
+    assert!(!rope.is_shared());
+
+    let mut fork = rope.fork();
+    let other = rope.fork();
+    assert!(rope.is_shared() && fork.is_shared() && other.is_shared());
+
+    // Only the edited rope gets its own nodes.
+    fork.insert(100, "b");
+    assert!(!fork.is_shared());
+    assert!(rope.is_shared() && other.is_shared());
+
+    drop(other);
+    assert!(!rope.is_shared());
+    fork.check();
+    rope.check();
+    assert_eq!(rope, "a".repeat(200));
+    assert_eq!(fork.char_at(100), Some('b'));
+
+    // Clones never share.
+    assert!(!rope.clone().is_shared());
+}
+
+#[test]
+fn drop_original_first() {
+    let mut rope = JumpRopeSized::<16>::from("hello world ".repeat(20));
+    let mut fork = rope.fork();
+    let mut fork_of_fork = fork.fork();
+
+    rope.remove(0..100);
+    drop(rope);
+    fork.clear();
+    drop(fork);
+
+    assert!(!fork_of_fork.is_shared());
+    assert_eq!(fork_of_fork, "hello world ".repeat(20));
+    fork_of_fork.insert(5, "!");
+    fork_of_fork.check();
+}
+
+#[test]
+fn replace_shared_content() {
+    let mut rope = JumpRopeSized::<16>::from("a".repeat(100));
+    let fork = rope.fork();
+    rope.set_content("hi there");
+    assert_eq!(rope, "hi there");
+    assert_eq!(fork, "a".repeat(100));
+
+    let rope = rope.fork();
+    let chunks: String = rope.fork().into_chunks().collect();
+    assert_eq!(chunks, "hi there");
+    assert_eq!(rope, "hi there");
+}
+
+#[test]
+fn fork_copies_metadata() {
+    // The history is only checked when it's enabled.
+    #[allow(unused_mut)]
+    let mut rope = JumpRope::from("hi there");
+    #[cfg(feature = "history")]
+    rope.enable_history();
+    rope.insert(2, "!");
+
+    let mut fork = rope.fork();
+    assert_eq!(fork, "hi! there");
+    #[cfg(feature = "history")] {
+        fork.undo();
+        assert_eq!(fork, "hi there");
+        assert_eq!(rope, "hi! there");
+    }
+    fork.check();
+}
+
+#[test]
+fn concurrent_forks() {
+    let mut rng = SmallRng::seed_from_u64(81);
+    let threads = if cfg!(miri) { 3 } else { 8 };
+    let steps = if cfg!(miri) { 10 } else { 200 };
+    let start = random_str(300, &mut rng);
+    let rope = JumpRopeSized::<16>::from(start.as_str());
+
+    let handles: Vec<_> = (0..threads).map(|t| {
+        let mut fork = rope.fork();
+        std::thread::spawn(move || {
+            // Each thread makes different edits. The first one only reads its fork.
+            let mut rng = SmallRng::seed_from_u64(t);
+            if t > 0 {
+                for _ in 0..steps {
+                    let expected = random_edit(&mut fork, &mut rng);
+                    assert_eq!(fork, expected);
+                }
+            }
+            fork.check();
+            fork
+        })
+    }).collect();
+
+    let forks: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(forks[0], rope.to_string());
+    assert_eq!(rope, start);
+    rope.check();
+}
+
+#[test]
+fn checkpoints_share_nodes() {
+    let mut rope = JumpRopeSized::<16>::from("hello world ".repeat(20));
+    let checkpoint = rope.checkpoint();
+    assert!(rope.is_shared());
+    #[cfg(feature = "history")]
+    rope.enable_history();
+
+    rope.replace(0..5, "goodbye");
+    assert!(!rope.is_shared());
+    assert_eq!(checkpoint, "hello world ".repeat(20));
+
+    // Restoring swaps the checkpoint's nodes back in, so they're shared again.
+    rope.restore(&checkpoint);
+    assert!(rope.is_shared());
+    rope.check();
+    assert_eq!(rope, "hello world ".repeat(20));
+
+    #[cfg(feature = "history")] {
+        rope.undo();
+        assert_eq!(rope.slice_chars(0..7).collect::<String>(), "goodbye");
+    }
+    rope.insert(0, "!");
+    rope.check();
+    assert_eq!(checkpoint, "hello world ".repeat(20));
+
+    let mut other = JumpRopeSized::<16>::from("x");
+    other.restore(&checkpoint.clone());
+    drop(checkpoint);
+    other.check();
+    assert_eq!(other, "hello world ".repeat(20));
+}
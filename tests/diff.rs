@@ -0,0 +1,79 @@
+use rand::prelude::*;
+use jumprope::{diff, Edit, JumpRope, JumpRopeSized};
+
+mod common;
+use common::{random_edit, random_str};
+
+fn check_edits(a: &JumpRopeSized<16>, edits: &[Edit]) {
+    let mut last_end = 0;
+    for edit in edits {
+        assert!(edit.range.start >= last_end && edit.range.end <= a.len_chars());
+        assert!(!edit.range.is_empty() || !edit.content.is_empty());
+        last_end = edit.range.end;
+    }
+}
+
+#[test]
+fn diff_identical() {
+    let a = JumpRope::from("hi there\nsome more text which spans a few nodes\n".repeat(10));
+    assert_eq!(diff(&a, &a.clone()), []);
+    assert_eq!(diff(&JumpRope::new(), &JumpRope::new()), []);
+}
+
+#[test]
+fn diff_disjoint() {
+    let mut a = JumpRope::from("aaaa\nbbbb");
+    let b = JumpRope::from("xyz");
+    let edits = diff(&a, &b);
+    assert_eq!(edits, [Edit { range: 0..9, content: "xyz".into() }]);
+    a.apply_edits(&edits);
+    assert_eq!(a, b);
+
+    assert_eq!(diff(&JumpRope::new(), &b), [Edit { range: 0..0, content: "xyz".into() }]);
+    assert_eq!(diff(&b, &JumpRope::new()), [Edit { range: 0..3, content: "".into() }]);
+}
+
+#[test]
+fn diff_only_copies_changes() {
+    // The edit is in the middle of two big ropes which only share a prefix and suffix.
+    let text = "Some text\n".repeat(1000);
+    let a = JumpRope::from(format!("{text}old{text}"));
+    let b = JumpRope::from(format!("{text}new{text}"));
+    assert_eq!(diff(&a, &b), [Edit { range: 10000..10003, content: "new".into() }]);
+}
+
+#[test]
+fn diff_random_mutations() {
+    let mut rng = SmallRng::seed_from_u64(7);
+    for _ in 0..200 {
+        let a = JumpRopeSized::<16>::from(random_str(rng.gen_range(0..200), &mut rng));
+        let mut b = a.clone();
+        for _ in 0..rng.gen_range(0..10) {
+            random_edit(&mut b, &mut rng);
+        }
+
+        let edits = diff(&a, &b);
+        check_edits(&a, &edits);
+        let mut result = a.clone();
+        result.apply_edits(&edits);
+        assert_eq!(result, b);
+
+        // And the other way.
+        let edits = diff(&b, &a);
+        check_edits(&b, &edits);
+        let mut result = b.clone();
+        result.apply_edits(&edits);
+        assert_eq!(result, a);
+    }
+}
+
+#[test]
+fn diff_big_changes() {
+    // Big enough that the diff gives up and replaces everything in the middle.
+    let mut rng = SmallRng::seed_from_u64(8);
+    let mut a = JumpRope::from(random_str(5000, &mut rng));
+    let b = JumpRope::from(random_str(5000, &mut rng));
+    let edits = diff(&a, &b);
+    a.apply_edits(&edits);
+    assert_eq!(a, b);
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "edit_log")]
+
+use rand::prelude::*;
+use jumprope::{EditLog, JumpRope, JumpRopeSized, LogEntry};
+
+mod common;
+use common::{random_edit, random_str};
+
+#[test]
+fn replay_random_session() {
+    let mut rng = SmallRng::seed_from_u64(70);
+    let start = random_str(100, &mut rng);
+    let mut rope = JumpRopeSized::<16>::from(start.as_str());
+    rope.enable_edit_log();
+
+    let steps = if cfg!(miri) { 20 } else { 300 };
+    for _ in 0..steps {
+        let expected = random_edit(&mut rope, &mut rng);
+
+        // Replaying the log so far reproduces the live rope.
+        let mut copy = JumpRope::from(start.as_str());
+        rope.edit_log().unwrap().replay(&mut copy);
+        copy.check();
+        assert_eq!(copy, expected);
+    }
+
+    // The entries alone are enough to rebuild the log.
+    let log = rope.take_edit_log().unwrap();
+    assert!(rope.edit_log().is_none());
+    let mut rebuilt = EditLog::new();
+    for entry in log.entries() {
+        rebuilt.push(entry.clone());
+    }
+    assert_eq!(rebuilt, log);
+    let mut copy = JumpRope::from(start.as_str());
+    rebuilt.replay(&mut copy);
+    assert_eq!(copy, rope.to_string());
+}
+
+#[test]
+fn log_entries() {
+    let mut rope = JumpRope::from("hello");
+    rope.enable_edit_log();
+    rope.insert(5, " world");
+    rope.replace(0..5, "κ");
+    rope.set_content("x");
+    rope.insert(0, "");
+
+    let log = rope.edit_log().unwrap();
+    let entry = |pos: usize, deleted: &str, inserted: &str| LogEntry {
+        pos, deleted: deleted.into(), inserted: inserted.into(),
+    };
+    assert!(log.entries().eq(&[
+        entry(5, "", " world"),
+        entry(0, "hello", ""),
+        entry(0, "", "κ"),
+        entry(0, "κ world", "x"),
+    ]));
+    assert_eq!(log.revision(), 4);
+    assert_eq!(log.first_revision(), 0);
+}
+
+#[test]
+fn map_position_follows_text() {
+    let mut rng = SmallRng::seed_from_u64(71);
+    let mut rope = JumpRopeSized::<16>::from(random_str(50, &mut rng) + "#" + &random_str(50, &mut rng));
+    rope.enable_edit_log();
+
+    // The revision and position of the marker, each time it was seen.
+    let mut seen = vec![(0, 50)];
+    let steps = if cfg!(miri) { 20 } else { 300 };
+    for _ in 0..steps {
+        let len = rope.len_chars();
+        let pos = rng.gen_range(0..=len);
+        if rng.gen_bool(0.5) {
+            rope.insert(pos, &random_str(rng.gen_range(0..20), &mut rng));
+        } else {
+            rope.remove(pos..(pos + rng.gen_range(0..5usize)).min(len));
+        }
+
+        let Some(marker) = rope.chars().position(|c| c == '#') else { break; };
+        let log = rope.edit_log().unwrap();
+        for &(rev, pos) in &seen {
+            assert_eq!(log.map_position(rev, pos), marker);
+        }
+        seen.push((log.revision(), marker));
+    }
+    assert!(seen.len() > 10);
+
+    // Mapping from the latest revision doesn't move anything.
+    let log = rope.edit_log().unwrap();
+    assert_eq!(log.map_position(log.revision(), 3), 3);
+}
+
+#[test]
+fn limited_log() {
+    let mut rng = SmallRng::seed_from_u64(72);
+    let mut rope = JumpRopeSized::<16>::from(random_str(100, &mut rng));
+    rope.enable_edit_log();
+    rope.set_edit_log_limit(10);
+
+    // The content at each revision.
+    let mut states = vec![rope.to_string()];
+    for _ in 0..100 {
+        let pos = rng.gen_range(0..=rope.len_chars());
+        rope.insert(pos, &random_str(rng.gen_range(1..5), &mut rng));
+        states.push(rope.to_string());
+    }
+
+    let log = rope.edit_log().unwrap();
+    assert_eq!(log.len(), 10);
+    assert_eq!(log.revision(), 100);
+    assert_eq!(log.first_revision(), 90);
+    let mut copy = JumpRope::from(states[90].as_str());
+    log.replay(&mut copy);
+    assert_eq!(copy, rope.to_string());
+
+    let mut log = log.clone();
+    log.set_limit(Some(3));
+    assert_eq!(log.first_revision(), 97);
+    log.set_limit(None);
+    log.push(LogEntry { pos: 0, deleted: String::new(), inserted: "a".into() });
+    assert_eq!(log.len(), 4);
+}
+
+#[test]
+#[should_panic]
+fn map_dropped_revision() {
+    let mut log = EditLog::with_limit(1);
+    log.push(LogEntry { pos: 0, deleted: String::new(), inserted: "a".into() });
+    log.push(LogEntry { pos: 0, deleted: String::new(), inserted: "b".into() });
+    log.map_position(0, 0);
+}
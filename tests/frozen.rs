@@ -0,0 +1,107 @@
+use rand::prelude::*;
+use jumprope::{FrozenRope, JumpRope};
+
+mod common;
+use common::random_str;
+
+fn check_reads(frozen: &FrozenRope, expected: &str) {
+    let chars: Vec<char> = expected.chars().collect();
+    assert_eq!(frozen, &expected);
+    assert_eq!(frozen.to_string(), expected);
+    assert_eq!(frozen.len_chars(), chars.len());
+    assert_eq!(frozen.len_bytes(), expected.len());
+    assert_eq!(frozen.is_empty(), expected.is_empty());
+    assert_eq!(frozen.substrings().collect::<String>(), expected);
+    assert_eq!(frozen.chars().collect::<Vec<_>>(), chars);
+
+    let len = chars.len();
+    for (i, c) in chars.iter().enumerate().step_by(97) {
+        assert_eq!(frozen.char_at(i), Some(*c));
+    }
+    assert_eq!(frozen.char_at(len), None);
+
+    for range in [0..0, 0..len, 3..3, 10..20, 4090..4110, len / 2..len, len..len] {
+        let range = range.start.min(len)..range.end.min(len);
+        let slice: String = chars[range.clone()].iter().collect();
+        assert_eq!(frozen.slice_substrings(range.clone()).collect::<String>(), slice);
+        assert_eq!(frozen.slice_chars(range).collect::<String>(), slice);
+    }
+}
+
+#[test]
+fn frozen_is_detached() {
+    let mut rng = SmallRng::seed_from_u64(60);
+    let len = if cfg!(miri) { 500 } else { 20000 };
+    let s = random_str(len, &mut rng);
+    let mut rope = JumpRope::from(s.as_str());
+    let frozen = rope.freeze();
+    let copy = frozen.clone();
+
+    for _ in 0..1000 {
+        let pos = rng.gen_range(0..=rope.len_chars());
+        if rng.gen_bool(0.5) {
+            rope.insert(pos, &random_str(rng.gen_range(0..20usize), &mut rng));
+        } else {
+            rope.remove(pos..(pos + rng.gen_range(0..20usize)).min(rope.len_chars()));
+        }
+    }
+    rope.remove(0..rope.len_chars() / 2);
+
+    check_reads(&frozen, &s);
+    check_reads(&copy, &s);
+    check_reads(&rope.freeze(), &rope.to_string());
+    check_reads(&JumpRope::new().freeze(), "");
+}
+
+#[test]
+fn find_in_frozen() {
+    let mut rng = SmallRng::seed_from_u64(61);
+    let s = random_str(10000, &mut rng) + "needle" + &random_str(10000, &mut rng);
+    let rope = JumpRope::from(s.as_str());
+    let frozen = rope.freeze();
+
+    for pattern in ["needle", "ab", "κ↯", "\n\n", ""] {
+        for start in [0, 1, 4000, 9999, 10001, 20006] {
+            assert_eq!(frozen.find_at(pattern, start), rope.find_at(pattern, start), "{:?} {}", pattern, start);
+        }
+    }
+    assert_eq!(frozen.find_at("needle", 0), Some(10000));
+    assert_eq!(frozen.find_at("a", 30000), None);
+}
+
+#[test]
+fn read_from_another_thread() {
+    let mut rope = JumpRope::from("the quick brown fox");
+    let frozen = rope.freeze();
+    let handles: Vec<_> = (0..4).map(|_| {
+        let frozen = frozen.clone();
+        std::thread::spawn(move || {
+            assert_eq!(frozen, "the quick brown fox");
+            frozen.find_at("fox", 0)
+        })
+    }).collect();
+
+    rope.replace(0..3, "a");
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), Some(16));
+    }
+    assert_eq!(rope, "a quick brown fox");
+}
+
+#[test]
+fn compare_across_chunks() {
+    let s = "a".repeat(10000);
+    let frozen = JumpRope::from(s.as_str()).freeze();
+    assert_eq!(frozen, s);
+
+    // Comparing against a string with a character across the end of a chunk must not panic.
+    let mut end = 0;
+    for chunk in frozen.substrings() {
+        end += chunk.len();
+        if end == s.len() { break; }
+        let other = format!("{}é{}", &s[..end - 1], &s[end + 1..]);
+        assert_eq!(other.len(), s.len());
+        assert_ne!(frozen, other);
+    }
+    assert!(end > 4096);
+}
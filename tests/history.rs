@@ -0,0 +1,147 @@
+#![cfg(feature = "history")]
+
+use rand::prelude::*;
+use jumprope::{JumpRope, JumpRopeSized};
+
+mod common;
+use common::{random_range, random_str};
+
+#[test]
+fn undo_random_edits() {
+    let mut rng = SmallRng::seed_from_u64(12);
+    let start = random_str(100, &mut rng);
+    let mut rope = JumpRopeSized::<16>::from(start.as_str());
+    rope.enable_history();
+
+    // The content after each undo step.
+    let mut states = vec![start.clone()];
+    for _ in 0..1000 {
+        let range = random_range(rope.len_chars(), &mut rng);
+        let (pos, end) = (range.start, range.end);
+        // Empty edits don't add an undo step.
+        let changed = match rng.gen_range(0..3) {
+            0 => {
+                rope.insert(pos, &random_str(rng.gen_range(1..20), &mut rng));
+                true
+            }
+            1 => {
+                rope.remove(pos..end);
+                pos < end
+            }
+            _ => {
+                let s = random_str(rng.gen_range(0..5), &mut rng);
+                rope.replace(pos..end, &s);
+                pos < end || !s.is_empty()
+            }
+        };
+        if changed { states.push(rope.to_string()); }
+    }
+    let end = rope.to_string();
+
+    while rope.undo() {
+        rope.check();
+    }
+    assert_eq!(rope, start.as_str());
+    assert!(!rope.undo());
+
+    while rope.redo() {}
+    assert_eq!(rope, end.as_str());
+
+    // Stepping back one at a time goes through every state.
+    for state in states.iter().rev().skip(1) {
+        assert!(rope.undo());
+        assert_eq!(rope, state.as_str());
+    }
+    assert!(!rope.undo());
+}
+
+#[test]
+fn undo_groups() {
+    let mut rope = JumpRope::from("abc");
+    rope.enable_history();
+    rope.begin_undo_group();
+    rope.insert(3, "d");
+    rope.begin_undo_group();
+    rope.remove(0..1);
+    rope.end_undo_group();
+    rope.replace(0..1, "xyz");
+    rope.end_undo_group();
+    rope.push_str("!");
+    assert_eq!(rope, "xyzcd!");
+
+    assert!(rope.undo());
+    assert_eq!(rope, "xyzcd");
+    assert!(rope.undo());
+    assert_eq!(rope, "abc");
+    assert!(rope.redo());
+    assert_eq!(rope, "xyzcd");
+
+    // Undo ends an open group.
+    rope.begin_undo_group();
+    rope.push_str("1");
+    rope.push_str("2");
+    assert!(rope.undo());
+    assert_eq!(rope, "xyzcd");
+}
+
+#[test]
+fn edits_clear_redo() {
+    let mut rope = JumpRope::from("abc");
+    rope.enable_history();
+    rope.push_str("d");
+    rope.push_str("e");
+    assert!(rope.undo());
+    assert_eq!(rope, "abcd");
+    rope.insert(0, "x");
+    assert!(!rope.redo());
+    assert_eq!(rope, "xabcd");
+    assert!(rope.undo());
+    assert!(rope.undo());
+    assert_eq!(rope, "abc");
+}
+
+#[test]
+fn history_limit() {
+    let mut rope = JumpRope::new();
+    rope.enable_history();
+    for c in ["a", "b", "c", "d", "e"] {
+        rope.push_str(c);
+    }
+    rope.set_history_limit(3);
+    while rope.undo() {}
+    assert_eq!(rope, "ab");
+
+    rope.push_str("x");
+    rope.push_str("y");
+    rope.push_str("z");
+    rope.push_str("w");
+    while rope.undo() {}
+    assert_eq!(rope, "abx");
+}
+
+#[test]
+fn undo_whole_rope_edits() {
+    let long = "a much longer string which takes a few nodes";
+    let mut rope = JumpRope::from("hello");
+    rope.enable_history();
+    rope.append(JumpRope::from(long));
+    rope.set_content("replaced");
+    let (left, _right) = rope.split_at_char(3).unwrap();
+    let mut rope = left;
+    rope.extend(["x", "yz"]);
+    rope.clear();
+    assert_eq!(rope, "");
+
+    let appended = format!("hello{long}");
+    for s in ["repxyz", "rep", "replaced", appended.as_str(), "hello"] {
+        assert!(rope.undo());
+        assert_eq!(rope, s);
+    }
+    assert!(!rope.undo());
+
+    let mut clone = rope.clone();
+    rope.disable_history();
+    assert!(!rope.redo());
+    assert!(clone.redo());
+    assert_eq!(clone, appended.as_str());
+}
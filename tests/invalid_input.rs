@@ -0,0 +1,106 @@
+// Every API which takes a position should reject (or, for the lenient methods, safely clamp)
+// positions past the end of the rope and byte offsets inside a character. None of them should
+// ever leave invalid content in the rope.
+
+use jumprope::{JumpRope, JumpRopeSized, RopeError};
+
+// 1, 2, 3 and 4 byte characters. Byte offsets 2, 4, 5, 7, 8 and 9 are inside a character.
+const CONTENT: &str = "a½↯𐆐";
+const INSIDE: [usize; 6] = [2, 4, 5, 7, 8, 9];
+const LEN_BYTES: usize = 10;
+const LEN_CHARS: usize = 4;
+
+fn rope() -> JumpRopeSized<4> {
+    // Small nodes, so multi-byte characters sit next to node boundaries.
+    let mut r = JumpRopeSized::<4>::from(CONTENT);
+    r.check();
+    assert_eq!(r.len_bytes(), LEN_BYTES);
+    r
+}
+
+fn oob(pos: usize, len: usize) -> RopeError {
+    RopeError::PositionOutOfBounds { pos, len }
+}
+
+#[test]
+fn byte_offsets_inside_characters() {
+    for pos in INSIDE {
+        let invalid = Some(RopeError::InvalidCodepoint { pos });
+        let mut r = rope();
+        assert_eq!(r.byte_to_char(pos).err(), invalid);
+        assert_eq!(r.insert_at_byte(pos, "x").err(), invalid);
+        assert_eq!(r.slice_bytes(0..pos).err(), invalid);
+        assert_eq!(r.slice_bytes(pos..LEN_BYTES).err(), invalid);
+        assert_eq!(r.truncate_bytes(pos).err(), invalid);
+        r.check();
+        assert_eq!(r, CONTENT);
+    }
+}
+
+#[test]
+fn byte_offsets_on_boundaries() {
+    let boundaries: Vec<usize> = (0..=LEN_BYTES).filter(|b| CONTENT.is_char_boundary(*b)).collect();
+    assert_eq!(boundaries.len(), LEN_CHARS + 1);
+
+    for (char_pos, &pos) in boundaries.iter().enumerate() {
+        let mut r = rope();
+        assert_eq!(r.byte_to_char(pos), Ok(char_pos));
+        assert_eq!(r.slice_bytes(pos..LEN_BYTES).as_deref(), Ok(&CONTENT[pos..]));
+        assert_eq!(r.insert_at_byte(pos, "x"), Ok(()));
+        r.check();
+        assert_eq!(r, format!("{}x{}", &CONTENT[..pos], &CONTENT[pos..]));
+    }
+}
+
+#[test]
+fn byte_offsets_past_the_end() {
+    for pos in [LEN_BYTES + 1, LEN_BYTES + 4, usize::MAX] {
+        let mut r = rope();
+        assert_eq!(r.byte_to_char(pos), Err(oob(pos, LEN_BYTES)));
+        assert_eq!(r.insert_at_byte(pos, "x"), Err(oob(pos, LEN_BYTES)));
+        assert_eq!(r.slice_bytes(0..pos), Err(oob(pos, LEN_BYTES)));
+        assert_eq!(r.char_containing_byte(pos), None);
+        // Truncating to a longer length is a no-op.
+        assert_eq!(r.truncate_bytes(pos), Ok(()));
+        r.check();
+        assert_eq!(r, CONTENT);
+    }
+
+    assert_eq!(rope().slice_bytes(3..1), Err(oob(3, 1)));
+}
+
+#[test]
+fn char_positions_past_the_end() {
+    for pos in [LEN_CHARS + 1, LEN_CHARS + 10, usize::MAX] {
+        let mut r = rope();
+        assert_eq!(r.try_insert(pos, "x"), Err(oob(pos, LEN_CHARS)));
+        assert_eq!(r.try_remove(pos..pos), Err(oob(pos, LEN_CHARS)));
+        assert_eq!(r.slice(pos, 1), Err(oob(pos, LEN_CHARS)));
+        assert!(r.try_slice(pos, 0).is_err());
+        assert_eq!(r.get(pos..), None);
+        assert_eq!(r.char_at(pos), None);
+        assert_eq!(r.find_at("a", pos), None);
+        r.check();
+        assert_eq!(r, CONTENT);
+    }
+}
+
+#[test]
+fn lenient_methods_clamp() {
+    // The unchecked methods clamp positions to the end of the rope, and can't split characters
+    // because they only take character offsets.
+    let mut r = rope();
+    r.insert(usize::MAX, "½");
+    r.insert_clamped(LEN_CHARS + 100, "𐆐");
+    r.remove(LEN_CHARS + 1..usize::MAX);
+    r.remove(usize::MAX..usize::MAX);
+    r.check();
+    assert_eq!(r, format!("{}½", CONTENT));
+
+    let mut r = JumpRope::from(CONTENT);
+    for pos in [0, 1, 2, 3, 4, 5, 100, usize::MAX] {
+        r.insert(pos, "𐆐½");
+        r.check();
+    }
+    assert_eq!(r.len_chars(), LEN_CHARS + 16);
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "marks")]
+
+use rand::prelude::*;
+use jumprope::{Affinity, JumpRope, JumpRopeSized, MarkId};
+
+mod common;
+use common::{random_range, random_str};
+
+/// The brute force model. Marks are stored in the document itself, between the characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Item {
+    Char(char),
+    Mark(MarkId, Affinity),
+}
+
+/// Returns the range of items at char position pos: everything from just after char pos - 1 up
+/// to char pos. This range only contains marks.
+fn gap_at(items: &[Item], pos: usize) -> std::ops::Range<usize> {
+    let mut chars = 0;
+    let mut start = 0;
+    for (i, item) in items.iter().enumerate() {
+        if let Item::Char(_) = item {
+            if chars == pos { return start..i; }
+            chars += 1;
+            start = i + 1;
+        }
+    }
+    start..items.len()
+}
+
+fn model_insert(items: &mut Vec<Item>, pos: usize, s: &str) {
+    // Marks which stay before the new text go first, then the text, then marks which move after
+    // it.
+    let gap = gap_at(items, pos);
+    let marks: Vec<Item> = items.splice(gap.clone(), []).collect();
+    let (before, after): (Vec<Item>, Vec<Item>) = marks.into_iter()
+        .partition(|m| matches!(m, Item::Mark(_, Affinity::Before)));
+    let new_items = before.into_iter()
+        .chain(s.chars().map(Item::Char))
+        .chain(after);
+    items.splice(gap.start..gap.start, new_items);
+}
+
+fn model_remove(items: &mut Vec<Item>, range: std::ops::Range<usize>) {
+    let mut chars = 0;
+    items.retain(|item| match item {
+        Item::Char(_) => {
+            chars += 1;
+            !range.contains(&(chars - 1))
+        }
+        Item::Mark(..) => true,
+    });
+}
+
+fn model_mark_pos(items: &[Item], id: MarkId) -> Option<usize> {
+    let mut chars = 0;
+    for item in items {
+        match item {
+            Item::Char(_) => chars += 1,
+            Item::Mark(m, _) if *m == id => return Some(chars),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[test]
+fn marks_match_model() {
+    let mut rng = SmallRng::seed_from_u64(30);
+    let mut rope = JumpRopeSized::<16>::new();
+    let mut items = Vec::new();
+    let mut ids = Vec::new();
+
+    for i in 0..2000 {
+        let len = rope.len_chars();
+        match rng.gen_range(0..10) {
+            0 | 1 => {
+                let pos = rng.gen_range(0..=len);
+                let affinity = if rng.gen_bool(0.5) { Affinity::Before } else { Affinity::After };
+                let id = rope.add_mark(pos, affinity);
+                let gap = gap_at(&items, pos);
+                items.insert(gap.start, Item::Mark(id, affinity));
+                ids.push(id);
+            }
+            2 if !ids.is_empty() => {
+                let id = ids.swap_remove(rng.gen_range(0..ids.len()));
+                let pos = model_mark_pos(&items, id);
+                items.retain(|item| !matches!(item, Item::Mark(m, _) if *m == id));
+                assert_eq!(rope.remove_mark(id), pos);
+                assert_eq!(rope.mark_pos(id), None);
+            }
+            3 | 4 | 5 => {
+                let pos = rng.gen_range(0..=len);
+                let s = random_str(rng.gen_range(1..20), &mut rng);
+                rope.insert(pos, &s);
+                model_insert(&mut items, pos, &s);
+            }
+            6 => {
+                let range = random_range(len, &mut rng);
+                let s = random_str(rng.gen_range(0..5), &mut rng);
+                rope.replace(range.clone(), &s);
+                model_remove(&mut items, range.clone());
+                model_insert(&mut items, range.start, &s);
+            }
+            _ => {
+                let range = random_range(len, &mut rng);
+                rope.remove(range.clone());
+                model_remove(&mut items, range);
+            }
+        }
+
+        if i % 50 == 0 {
+            rope.check();
+            let content: String = items.iter().filter_map(|item| match item {
+                Item::Char(c) => Some(*c),
+                Item::Mark(..) => None,
+            }).collect();
+            assert_eq!(rope, content.as_str());
+        }
+        for &id in &ids {
+            assert_eq!(rope.mark_pos(id), model_mark_pos(&items, id));
+        }
+    }
+    assert_eq!(rope.num_marks(), ids.len());
+}
+
+#[test]
+fn marks_through_whole_rope_edits() {
+    let mut rope = JumpRope::from("hello world");
+    let a = rope.add_mark(5, Affinity::Before);
+    let b = rope.add_mark(100, Affinity::After);
+    let c = rope.add_mark(11, Affinity::Before);
+    assert_eq!(rope.mark_pos(b), Some(11));
+
+    let mut clone = rope.clone();
+    clone.remove(0..6);
+    assert_eq!(clone.mark_pos(a), Some(0));
+    assert_eq!(rope.mark_pos(a), Some(5));
+
+    rope.append(JumpRope::from(" and a much longer string which takes a few nodes"));
+    assert_eq!(rope.mark_pos(a), Some(5));
+    assert_eq!(rope.mark_pos(b), Some(rope.len_chars()));
+    assert_eq!(rope.mark_pos(c), Some(11));
+
+    let (left, _right) = rope.split_at_char(8).unwrap();
+    assert_eq!(left.mark_pos(a), Some(5));
+    assert_eq!(left.mark_pos(b), Some(8));
+    let mut rope = left;
+
+    rope.set_content("replaced");
+    assert_eq!(rope.mark_pos(a), Some(0));
+    assert_eq!(rope.mark_pos(b), Some(8));
+
+    rope.extend(["x", "yz"]);
+    assert_eq!(rope.mark_pos(b), Some(11));
+
+    rope.clear();
+    assert_eq!(rope.mark_pos(a), Some(0));
+    assert_eq!(rope.mark_pos(b), Some(0));
+    assert_eq!(rope.num_marks(), 3);
+}
@@ -0,0 +1,126 @@
+// Property tests for the operational transform functions. Ops are built to fit a random
+// document, then applied to ropes.
+
+use jumprope::JumpRope;
+use jumprope::ot::{Component, Op, Side};
+use proptest::prelude::*;
+
+const CHARS: [char; 8] = ['\n', 'a', 'b', ' ', '½', 'Δ', '↯', '𐆚'];
+
+fn content() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(CHARS.to_vec()), 0..10)
+        .prop_map(|chars| chars.into_iter().collect())
+}
+
+/// A run in an op, before it's fitted to a document. (kind, length), content.
+type RawPart = ((usize, usize), String);
+
+fn raw_op() -> impl Strategy<Value = Vec<RawPart>> {
+    prop::collection::vec(((0..3usize, 0..10usize), content()), 0..8)
+}
+
+/// Build an op from the raw parts which applies to a document of len characters.
+fn fit_op(parts: &[RawPart], len: usize) -> Op {
+    let mut op = Op::new();
+    let mut remaining = len;
+    for ((kind, n), content) in parts {
+        match kind {
+            0 => {
+                let n = (*n).min(remaining);
+                op.retain(n);
+                remaining -= n;
+            }
+            1 => { op.insert(content); }
+            _ => {
+                let n = (*n).min(remaining);
+                op.delete(n);
+                remaining -= n;
+            }
+        }
+    }
+    op.retain(remaining);
+    op
+}
+
+fn apply(doc: &JumpRope, op: &Op) -> JumpRope {
+    let mut doc = doc.clone();
+    doc.apply_op(op);
+    doc.check();
+    assert_eq!(doc.len_chars(), op.target_len());
+    doc
+}
+
+proptest! {
+    #[test]
+    fn transform_converges(doc in content(), a in raw_op(), b in raw_op()) {
+        let doc = JumpRope::from(doc.repeat(3));
+        let a = fit_op(&a, doc.len_chars());
+        let b = fit_op(&b, doc.len_chars());
+
+        let left = apply(&apply(&doc, &a), &Op::transform(&b, &a, Side::Right));
+        let right = apply(&apply(&doc, &b), &Op::transform(&a, &b, Side::Left));
+        prop_assert_eq!(left.to_string(), right.to_string());
+    }
+
+    #[test]
+    fn compose_matches_apply(doc in content(), a in raw_op(), b in raw_op()) {
+        let doc = JumpRope::from(doc.repeat(3));
+        let a = fit_op(&a, doc.len_chars());
+        let mid = apply(&doc, &a);
+        let b = fit_op(&b, mid.len_chars());
+
+        let composed = Op::compose(&a, &b);
+        prop_assert_eq!(composed.base_len(), a.base_len());
+        prop_assert_eq!(composed.target_len(), b.target_len());
+        prop_assert_eq!(apply(&doc, &composed).to_string(), apply(&mid, &b).to_string());
+    }
+
+    #[test]
+    fn diff_applies(a in content(), b in content(), shared in content()) {
+        // The shared text gives the documents a common prefix and suffix.
+        let doc = JumpRope::from(format!("{}{}{}", shared, a, shared));
+        let other = format!("{}{}{}", shared, b, shared);
+
+        let op = doc.diff(&other);
+        prop_assert_eq!(op.base_len(), doc.len_chars());
+        prop_assert_eq!(apply(&doc, &op), other.as_str());
+        prop_assert_eq!(op.is_noop(), a == b);
+    }
+}
+
+#[test]
+fn ops_are_normalized() {
+    let mut op = Op::new();
+    op.retain(2).retain(1).delete(2).insert("a").delete(1).insert("b").retain(0).insert("");
+    assert_eq!(op.components(), [
+        Component::Retain(3),
+        Component::Insert("ab".into()),
+        Component::Delete(3),
+    ]);
+    assert_eq!(op.base_len(), 6);
+    assert_eq!(op.target_len(), 5);
+    assert!(!op.is_noop());
+
+    assert_eq!(Op::from_edit(1, 2, "xyz", 4), {
+        let mut op = Op::new();
+        op.retain(1).insert("xyz").delete(2).retain(1);
+        op
+    });
+    assert!(Op::from_edit(2, 0, "", 4).is_noop());
+}
+
+#[test]
+fn transform_sides() {
+    let doc = JumpRope::from("ab");
+    let a = Op::from_edit(1, 0, "X", 2);
+    let b = Op::from_edit(1, 0, "Y", 2);
+    assert_eq!(apply(&apply(&doc, &b), &Op::transform(&a, &b, Side::Left)), "aXYb");
+    assert_eq!(apply(&apply(&doc, &b), &Op::transform(&a, &b, Side::Right)), "aYXb");
+}
+
+#[test]
+#[should_panic]
+fn apply_wrong_length() {
+    let mut doc = JumpRope::from("abc");
+    doc.apply_op(&Op::from_edit(0, 0, "x", 2));
+}
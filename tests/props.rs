@@ -0,0 +1,124 @@
+// Property tests, comparing ropes against a String after random edits. These complement the
+// fuzzer in fuzz/, but they're small enough to run with every cargo test.
+
+use jumprope::{JumpRope, JumpRopeSized};
+use proptest::prelude::*;
+
+const UNI_CHARS: [char; 24] = [
+  '\n', 'a', 'b', 'c', '1', '2', '3', ' ', '_', // ASCII.
+  '©', '¥', '½', // The Latin-1 suppliment (U+80 - U+ff)
+  'Ύ', 'Δ', 'δ', 'Ϡ', // Greek (U+0370 - U+03FF)
+  '←', '↯', '↻', '⇈', // Arrows (U+2190 – U+21FF)
+  '𐆐', '𐆔', '𐆘', '𐆚', // Ancient roman symbols (U+10190 – U+101CF)
+];
+
+/// A position in the document, relative to its length when the op is applied. Most positions are
+/// near the ends of the document, where off by one bugs live. Failing scripts shrink toward
+/// `Start`, so they read like "insert at the start, then delete before the end".
+#[derive(Debug, Clone, Copy)]
+enum Pos {
+    Start,
+    End,
+    BeforeEnd,
+    /// Past the end of the document. Ropes truncate these to the end.
+    AfterEnd,
+    /// Some position in the document. This is scaled down to fit.
+    Within(usize),
+}
+
+impl Pos {
+    fn resolve(self, len: usize) -> usize {
+        match self {
+            Pos::Start => 0,
+            Pos::End => len,
+            Pos::BeforeEnd => len.saturating_sub(1),
+            Pos::AfterEnd => len + 1,
+            Pos::Within(n) => n % (len + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(Pos, String),
+    Del(Pos, usize),
+}
+
+fn pos() -> impl Strategy<Value = Pos> {
+    prop_oneof![
+        Just(Pos::Start),
+        Just(Pos::End),
+        Just(Pos::BeforeEnd),
+        Just(Pos::AfterEnd),
+        any::<usize>().prop_map(Pos::Within),
+    ]
+}
+
+fn content() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(UNI_CHARS.to_vec()), 0..20)
+        .prop_map(|chars| chars.into_iter().collect())
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (pos(), content()).prop_map(|(pos, content)| Op::Insert(pos, content)),
+        (pos(), 0..10usize).prop_map(|(pos, len)| Op::Del(pos, len)),
+    ]
+}
+
+/// Applies an op to the String model. Positions past the end are truncated, like in the rope.
+fn apply_to_string(s: &mut String, op: &Op) {
+    let byte_pos = |s: &String, pos: usize| {
+        s.char_indices().nth(pos).map_or(s.len(), |(i, _)| i)
+    };
+    let len = s.chars().count();
+    match op {
+        Op::Insert(pos, content) => {
+            let pos = byte_pos(s, pos.resolve(len));
+            s.insert_str(pos, content);
+        }
+        Op::Del(pos, del_len) => {
+            let pos = pos.resolve(len);
+            let range = byte_pos(s, pos)..byte_pos(s, pos + del_len);
+            s.replace_range(range, "");
+        }
+    }
+}
+
+fn apply_to_rope<const N: usize>(rope: &mut JumpRopeSized<N>, op: &Op) {
+    let len = rope.len_chars();
+    match op {
+        Op::Insert(pos, content) => rope.insert(pos.resolve(len), content),
+        Op::Del(pos, del_len) => {
+            let pos = pos.resolve(len);
+            rope.remove(pos..pos + del_len);
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn edits_match_string(ops in prop::collection::vec(op(), 0..40)) {
+        let mut expected = String::new();
+        let mut rope = JumpRope::new();
+        // Every node of this rope only fits 4 bytes, so edits with 4 byte characters split nodes
+        // at every opportunity.
+        let mut small = JumpRopeSized::<4>::new();
+
+        for op in &ops {
+            apply_to_string(&mut expected, op);
+            apply_to_rope(&mut rope, op);
+            apply_to_rope(&mut small, op);
+
+            rope.check();
+            small.check();
+            prop_assert_eq!(rope.to_string(), expected.as_str());
+            prop_assert_eq!(small.to_string(), expected.as_str());
+            prop_assert_eq!(rope.len_chars(), expected.chars().count());
+            prop_assert_eq!(small.len_bytes(), expected.len());
+        }
+
+        prop_assert!(rope.chars().eq(expected.chars()));
+        prop_assert!(small.chars().rev().eq(expected.chars().rev()));
+    }
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "regex")]
+
+use rand::prelude::*;
+use regex::Regex;
+use jumprope::{JumpRope, JumpRopeSized};
+
+fn check_replace(r: &mut JumpRopeSized<16>, re: &Regex, replacement: &str) {
+    let s = r.to_string();
+    let expected = re.replace_all(&s, replacement);
+    let count = re.find_iter(&s).count();
+    assert_eq!(r.regex_replace_all(re, replacement), count);
+    assert_eq!(r, &expected);
+    r.check();
+}
+
+#[test]
+fn replace_with_captures() {
+    let mut r = JumpRope::from("2024-01-15, 1999-12-31 and κόσμε");
+    let re = Regex::new(r"(?P<y>\d{4})-(\d{2})-(\d{2})").unwrap();
+    assert_eq!(r.regex_replace_all(&re, "$3/$2/${y} $$"), 2);
+    assert_eq!(r, "15/01/2024 $, 31/12/1999 $ and κόσμε");
+
+    // No matches.
+    assert_eq!(r.regex_replace_all(&Regex::new("xyz").unwrap(), "!"), 0);
+    assert_eq!(r, "15/01/2024 $, 31/12/1999 $ and κόσμε");
+}
+
+#[test]
+fn replace_across_nodes() {
+    // Each match is long enough to span a few nodes.
+    let mut rng = SmallRng::seed_from_u64(40);
+    let mut s = String::new();
+    for i in 0..50 {
+        s.push_str(&"κ".repeat(rng.gen_range(0..40usize)));
+        s.push_str(&format!("<{}{}>", i, "a".repeat(rng.gen_range(0..60usize))));
+    }
+    let mut r = JumpRopeSized::<16>::from(s.as_str());
+    check_replace(&mut r, &Regex::new(r"<(\d+)a*>").unwrap(), "[$1 𐆚]");
+    check_replace(&mut r, &Regex::new(r"κ+").unwrap(), "a long replacement string which grows the rope");
+}
+
+#[test]
+fn replace_changing_length() {
+    let mut rng = SmallRng::seed_from_u64(41);
+    const CHARS: &[char] = &['a', 'b', ' ', '\n', 'κ', '𐆚'];
+    for _ in 0..20 {
+        let s: String = (0..rng.gen_range(0..500usize)).map(|_| CHARS[rng.gen_range(0..CHARS.len())]).collect();
+        let mut r = JumpRopeSized::<16>::from(s.as_str());
+
+        // Deleting every match, growing every match, and empty matches.
+        check_replace(&mut r, &Regex::new("b+").unwrap(), "");
+        check_replace(&mut r, &Regex::new("(a|κ)").unwrap(), "$1$1$1");
+        check_replace(&mut r, &Regex::new("𐆚*").unwrap(), "-");
+        check_replace(&mut r, &Regex::new("(?s).").unwrap(), "");
+        assert!(r.is_empty());
+    }
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn replace_is_one_undo_step() {
+    let mut r = JumpRope::from("a b a b");
+    r.enable_history();
+    assert_eq!(r.regex_replace_all(&Regex::new("a").unwrap(), "xyz"), 2);
+    assert_eq!(r, "xyz b xyz b");
+    r.undo();
+    assert_eq!(r, "a b a b");
+}
@@ -6,8 +6,12 @@ use rand::prelude::*;
 use std::cmp::min;
 use std::ops::Range;
 use std::ptr;
-use jumprope::JumpRope;
+#[cfg(feature = "allocator-api2")]
+use std::cell::Cell;
+use jumprope::{JumpRope, JumpRopeSized, RopeSnapshot};
 use jumprope::JumpRopeBuf;
+#[cfg(feature = "allocator-api2")]
+use allocator_api2::alloc::{AllocError, Allocator, Global, Layout};
 
 const UNI_CHARS: [char; 24] = [
   '\n', 'a', 'b', 'c', '1', '2', '3', ' ', '_', // ASCII.
@@ -37,7 +41,7 @@ fn random_ascii_string(len: usize, rng: &mut SmallRng) -> String {
     s
 }
 
-fn check(r: &JumpRope, expected: &str) {
+fn check<const N: usize>(r: &JumpRopeSized<N>, expected: &str) {
     // println!("--- rope ---");
     // r.print();
 
@@ -55,7 +59,21 @@ fn check(r: &JumpRope, expected: &str) {
         // And if we convert back, we should get the number of characters.
         assert_eq!(r.wchars_to_chars(r.len_wchars()), r.len_chars());
     }
-    assert_eq!(*r, JumpRope::from(expected), "Rope comparison fails");
+    #[cfg(feature = "line_conversion")] {
+        assert_eq!(r.len_lines(), expected.matches('\n').count() + 1);
+
+        let mut char_pos = 0;
+        for (line, line_str) in expected.split('\n').enumerate() {
+            let len = line_str.chars().count();
+            assert_eq!(r.line_to_char(line), char_pos);
+            assert_eq!(r.char_to_line(char_pos), line);
+            assert_eq!(r.char_to_line(char_pos + len), line);
+            assert_eq!(r.line_char_len(line), Some(len));
+            char_pos += len + 1;
+        }
+        assert_eq!(r.line_char_len(r.len_lines()), None);
+    }
+    assert_eq!(*r, JumpRopeSized::<N>::from(expected), "Rope comparison fails");
 
     let clone = r.clone();
     // println!("--- clone ---");
@@ -110,6 +128,1161 @@ fn new_string_has_content() {
     check(&r, "κό𝕐𝕆😘σμε");
 }
 
+#[test]
+fn push_and_push_str() {
+    let mut rng = SmallRng::seed_from_u64(16);
+    let mut r = JumpRopeSized::<16>::new();
+    let mut expected = String::new();
+    for _ in 0..500 {
+        if rng.gen_bool(0.5) {
+            let c = UNI_CHARS[rng.gen_range(0..UNI_CHARS.len())];
+            r.push(c);
+            expected.push(c);
+        } else {
+            let len = rng.gen_range(0..30);
+            let s = random_unicode_string(len, &mut rng);
+            r.push_str(&s);
+            expected.push_str(&s);
+        }
+    }
+    check(&r, &expected);
+}
+
+#[test]
+fn prepend() {
+    let mut r = JumpRopeSized::<16>::new();
+    r.prepend("");
+    check(&r, "");
+    r.prepend("↯b");
+    check(&r, "↯b");
+    // The head node has room.
+    r.prepend("a");
+    check(&r, "a↯b");
+
+    let mut rng = SmallRng::seed_from_u64(32);
+    let mut expected = random_unicode_string(300, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+    for _ in 0..100 {
+        let s = random_unicode_string(rng.gen_range(0..30), &mut rng);
+        r.prepend(&s);
+        expected.insert_str(0, &s);
+        // Mixing in other edits checks the cursor left by prepend is usable.
+        r.push('x');
+        expected.push('x');
+    }
+    check(&r, &expected);
+}
+
+#[test]
+fn pop() {
+    let mut rng = SmallRng::seed_from_u64(17);
+    let expected = random_unicode_string(300, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+
+    let mut popped = String::new();
+    while let Some(c) = r.pop() {
+        popped.push(c);
+    }
+    assert_eq!(popped, expected.chars().rev().collect::<String>());
+    check(&r, "");
+    assert_eq!(r.pop(), None);
+}
+
+#[test]
+fn try_insert_out_of_bounds() {
+    let mut r = JumpRope::from("hi");
+    assert_eq!(r.try_insert(3, "x"), Err(jumprope::RopeError::PositionOutOfBounds { pos: 3, len: 2 }));
+    check(&r, "hi");
+
+    assert_eq!(r.try_insert(2, "!"), Ok(()));
+    check(&r, "hi!");
+}
+
+#[test]
+fn edits_at_the_end_boundary() {
+    use jumprope::RopeError::PositionOutOfBounds;
+
+    let mut rng = SmallRng::seed_from_u64(20);
+    for len in [0, 1, 300] {
+        let s = random_unicode_string(len, &mut rng);
+        let mut r = JumpRopeSized::<16>::from(s.as_str());
+
+        // One past the end fails, and leaves the rope alone.
+        assert_eq!(r.try_insert(len + 1, "x"), Err(PositionOutOfBounds { pos: len + 1, len }));
+        assert_eq!(r.try_insert(usize::MAX, "x"), Err(PositionOutOfBounds { pos: usize::MAX, len }));
+        assert_eq!(r.try_remove(len + 1..len + 2), Err(PositionOutOfBounds { pos: len + 1, len }));
+        assert_eq!(r.slice_cow(len, 1), Err(PositionOutOfBounds { pos: len + 1, len }));
+        assert_eq!(r.slice_cow(len + 1, 0), Err(PositionOutOfBounds { pos: len + 1, len }));
+        check(&r, &s);
+
+        // Exactly at the end succeeds.
+        assert_eq!(r.slice_cow(len, 0).unwrap(), "");
+        assert_eq!(r.try_remove(len..len + 1), Ok(()));
+        check(&r, &s);
+        assert_eq!(r.try_insert(len, "x"), Ok(()));
+        check(&r, &format!("{}x", s));
+        assert_eq!(r.try_remove(len..len + 1), Ok(()));
+        check(&r, &s);
+    }
+}
+
+#[test]
+fn insert_clamped_past_end() {
+    let mut r = JumpRope::from("hi there");
+    r.insert_clamped(1_000_000_000, " friend");
+    check(&r, "hi there friend");
+
+    r.insert_clamped(usize::MAX, "!");
+    check(&r, "hi there friend!");
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn line_char_len() {
+    let r = JumpRope::from("abc\n\nκόσμε\nlast");
+    check(&r, "abc\n\nκόσμε\nlast");
+    assert_eq!(r.line_char_len(0), Some(3));
+    assert_eq!(r.line_char_len(1), Some(0)); // Empty line
+    assert_eq!(r.line_char_len(2), Some(5));
+    assert_eq!(r.line_char_len(3), Some(4)); // Last line has no trailing newline
+    assert_eq!(r.line_char_len(4), None);
+
+    let r = JumpRope::from("trailing\n");
+    assert_eq!(r.line_char_len(0), Some(8));
+    assert_eq!(r.line_char_len(1), Some(0));
+    assert_eq!(r.line_char_len(2), None);
+
+    assert_eq!(JumpRope::new().line_char_len(0), Some(0));
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn insert_at_line_col() {
+    let mut r = JumpRope::from("abc\nκόσμε\n");
+
+    // Start of a line
+    r.insert_at_line_col(1, 0, ">").unwrap();
+    check(&r, "abc\n>κόσμε\n");
+
+    // Middle of a line
+    r.insert_at_line_col(1, 3, "__").unwrap();
+    check(&r, "abc\n>κό__σμε\n");
+
+    // End of a line, just before the newline.
+    r.insert_at_line_col(0, 3, "d").unwrap();
+    check(&r, "abcd\n>κό__σμε\n");
+
+    // The empty last line
+    r.insert_at_line_col(2, 0, "end").unwrap();
+    check(&r, "abcd\n>κό__σμε\nend");
+
+    assert_eq!(r.insert_at_line_col(0, 5, "x"), Err(jumprope::RopeError::PositionOutOfBounds { pos: 5, len: 4 }));
+    assert_eq!(r.insert_at_line_col(3, 0, "x"), Err(jumprope::RopeError::PositionOutOfBounds { pos: 3, len: 3 }));
+    check(&r, "abcd\n>κό__σμε\nend");
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn insert_line() {
+    let mut r = JumpRope::new();
+    r.insert_line(0, "only").unwrap();
+    check(&r, "only\n");
+
+    let mut r = JumpRope::from("abc\nκόσμε");
+    // The top.
+    r.insert_line(0, "first\n").unwrap();
+    check(&r, "first\nabc\nκόσμε");
+
+    // The middle. A newline is only added if the text doesn't end with one.
+    r.insert_line(2, "mid").unwrap();
+    check(&r, "first\nabc\nmid\nκόσμε");
+    r.insert_line(2, "a\nb").unwrap();
+    check(&r, "first\nabc\na\nb\nmid\nκόσμε");
+
+    // Before the last line, then just past it.
+    r.insert_line(5, "").unwrap();
+    check(&r, "first\nabc\na\nb\nmid\n\nκόσμε");
+    r.insert_line(7, "end").unwrap();
+    check(&r, "first\nabc\na\nb\nmid\n\nκόσμε\nend\n");
+    assert_eq!(r.len_lines(), 9);
+
+    assert_eq!(r.insert_line(10, "x"), Err(jumprope::RopeError::PositionOutOfBounds { pos: 10, len: 9 }));
+    check(&r, "first\nabc\na\nb\nmid\n\nκόσμε\nend\n");
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn delete_line() {
+    let mut r = JumpRope::from("first\nκόσμε\nmid\n\nlast");
+    assert_eq!(r.len_lines(), 5);
+
+    // The first line.
+    r.delete_line(0).unwrap();
+    check(&r, "κόσμε\nmid\n\nlast");
+    assert_eq!(r.len_lines(), 4);
+
+    // A middle line, then an empty one.
+    r.delete_line(1).unwrap();
+    check(&r, "κόσμε\n\nlast");
+    r.delete_line(1).unwrap();
+    check(&r, "κόσμε\nlast");
+    assert_eq!(r.len_lines(), 2);
+
+    // The last line takes the newline before it.
+    r.delete_line(1).unwrap();
+    check(&r, "κόσμε");
+    assert_eq!(r.len_lines(), 1);
+
+    assert_eq!(r.delete_line(1), Err(jumprope::RopeError::PositionOutOfBounds { pos: 1, len: 1 }));
+    r.delete_line(0).unwrap();
+    check(&r, "");
+    assert_eq!(r.len_lines(), 1);
+
+    // An empty last line.
+    let mut r = JumpRope::from("a\nb\n");
+    r.delete_line(2).unwrap();
+    check(&r, "a\nb");
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn line_ops_match_model() {
+    let mut rng = SmallRng::seed_from_u64(23);
+    let mut r = JumpRopeSized::<16>::new();
+    // The rope's content is always lines.join("\n").
+    let mut lines = vec![String::new()];
+
+    for _ in 0..500 {
+        let len_lines = lines.len();
+        assert_eq!(r.len_lines(), len_lines);
+        let n = rng.gen_range(0..len_lines + 2);
+        match rng.gen_range(0..4usize) {
+            0 => {
+                assert_eq!(r.line(n).as_ref(), lines.get(n));
+            }
+            1 => {
+                let text = random_unicode_string(rng.gen_range(0..20), &mut rng).replace('\n', "");
+                let result = r.insert_line(n, &text);
+                if n < len_lines {
+                    lines.insert(n, text);
+                } else if n == len_lines {
+                    // The new line goes after the last line, and ends with a newline too.
+                    lines.push(text);
+                    lines.push(String::new());
+                } else {
+                    assert!(result.is_err());
+                }
+            }
+            2 if len_lines > 3 || n >= len_lines => {
+                let removed = r.remove_line(n);
+                if n < len_lines {
+                    assert_eq!(removed, Some(lines.remove(n)));
+                    if lines.is_empty() { lines.push(String::new()); }
+                } else {
+                    assert_eq!(removed, None);
+                }
+            }
+            _ => {
+                let m = rng.gen_range(0..len_lines + 1);
+                let result = r.swap_lines(n, m);
+                if n < len_lines && m < len_lines {
+                    assert_eq!(result, Ok(()));
+                    lines.swap(n, m);
+                } else {
+                    assert!(result.is_err());
+                }
+            }
+        }
+        r.check();
+        assert_eq!(r, lines.join("\n"));
+    }
+
+    // Adjacent lines, and a line ending in \r\n.
+    let mut r = JumpRope::from("a\r\nbc\nd");
+    r.swap_lines(1, 0).unwrap();
+    check(&r, "bc\r\na\nd");
+    r.swap_lines(2, 1).unwrap();
+    check(&r, "bc\r\nd\na");
+    assert_eq!(r.remove_line(0).as_deref(), Some("bc"));
+    assert_eq!(r.remove_line(1).as_deref(), Some("a"));
+    assert_eq!(r.remove_line(0).as_deref(), Some("d"));
+    check(&r, "");
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn line_hashes() {
+    let mut rng = SmallRng::seed_from_u64(29);
+    let mut lines: Vec<String> = (0..100)
+        .map(|_| random_unicode_string(rng.gen_range(0..30), &mut rng).replace('\n', ""))
+        .collect();
+    let r = JumpRopeSized::<16>::from(lines.join("\n"));
+    let hashes = r.line_hashes(0..100);
+    assert_eq!(hashes.len(), 100);
+
+    // The node layout doesn't matter.
+    let mut other = JumpRope::new();
+    for line in lines.iter().rev() {
+        other.insert(0, line);
+        other.insert(line.chars().count(), "\n");
+    }
+    other.remove(other.len_chars() - 1..other.len_chars());
+    assert_eq!(other.to_string(), r.to_string());
+    assert_eq!(other.line_hashes(0..100), hashes);
+
+    // Sub-ranges, and ranges past the end.
+    assert_eq!(r.line_hashes(20..30), &hashes[20..30]);
+    assert_eq!(r.line_hashes(95..200), &hashes[95..]);
+    assert_eq!(r.line_hashes(100..110), []);
+
+    // Edit a few lines. Only their hashes change.
+    let mut r = r;
+    for line in [0, 37, 99] {
+        let pos = r.line_to_char(line);
+        r.insert(pos, "edit");
+        lines[line].insert_str(0, "edit");
+    }
+    let new_hashes = r.line_hashes(0..100);
+    for (i, (a, b)) in hashes.iter().zip(&new_hashes).enumerate() {
+        assert_eq!(a == b, ![0, 37, 99].contains(&i), "line {}", i);
+    }
+
+    // The last line, with and without a newline after it.
+    assert_eq!(JumpRope::from("a\nb").line_hashes(1..2), JumpRope::from("b").line_hashes(0..1));
+    let trailing = JumpRope::from("a\n").line_hashes(0..2);
+    assert_eq!(trailing, [JumpRope::from("a").line_hashes(0..1)[0], JumpRope::new().line_hashes(0..1)[0]]);
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn prefix_lines_matches_string() {
+    // Reference implementations, working on whole lines.
+    fn prefix(s: &str, lines: Range<usize>, prefix: &str, skip_empty: bool) -> (String, usize) {
+        let mut changed = 0;
+        let result = s.split('\n').enumerate().map(|(i, line)| {
+            if lines.contains(&i) && !(skip_empty && (line.is_empty() || line == "\r")) {
+                changed += 1;
+                format!("{}{}", prefix, line)
+            } else { line.to_string() }
+        }).collect::<Vec<_>>().join("\n");
+        (result, changed)
+    }
+    fn unprefix(s: &str, lines: Range<usize>, prefix: &str) -> (String, usize) {
+        let mut changed = 0;
+        let result = s.split('\n').enumerate().map(|(i, line)| {
+            match line.strip_prefix(prefix) {
+                Some(rest) if lines.contains(&i) => { changed += 1; rest }
+                _ => line,
+            }
+        }).collect::<Vec<_>>().join("\n");
+        (result, changed)
+    }
+
+    let mut rng = SmallRng::seed_from_u64(22);
+    for _ in 0..200 {
+        const PARTS: [&str; 7] = ["\n", "\r\n", "  ", "a", "κ", "# ", "𐆚"];
+        let s: String = (0..rng.gen_range(0..200usize))
+            .map(|_| PARTS[rng.gen_range(0..PARTS.len())])
+            .collect();
+        let mut r = JumpRopeSized::<16>::from(s.as_str());
+        let len_lines = r.len_lines();
+        let start = rng.gen_range(0..=len_lines);
+        let lines = start..rng.gen_range(start..=len_lines + 2);
+        let p = ["  ", "# ", "κ"][rng.gen_range(0..3usize)];
+
+        let skip_empty = rng.gen_bool(0.5);
+        let (expected, changed) = prefix(&s, lines.clone(), p, skip_empty);
+        assert_eq!(r.prefix_lines(lines.clone(), p, skip_empty), changed);
+        check(&r, &expected);
+
+        let (expected, changed) = unprefix(&expected, lines.clone(), p);
+        assert_eq!(r.unprefix_lines(lines.clone(), p), changed);
+        check(&r, &expected);
+    }
+}
+
+#[test]
+fn delete_word_backward() {
+    let mut r = JumpRope::from("fn  foo_bar2(κόσμε);\n  next");
+    // Position 0 deletes nothing.
+    assert_eq!(r.delete_word_backward(0), (0, "".into()));
+
+    let len = r.len_chars();
+    assert_eq!(r.delete_word_backward(len), (23, "next".into()));
+    // Whitespace goes with the word (or newline) before it.
+    assert_eq!(r.delete_word_backward(23), (20, "\n  ".into()));
+    assert_eq!(r.delete_word_backward(20), (18, ");".into()));
+    assert_eq!(r.delete_word_backward(18), (13, "κόσμε".into()));
+    assert_eq!(r.delete_word_backward(13), (12, "(".into()));
+    assert_eq!(r.delete_word_backward(12), (4, "foo_bar2".into()));
+    check(&r, "fn  ");
+    assert_eq!(r.delete_word_backward(4), (0, "fn  ".into()));
+    check(&r, "");
+}
+
+#[test]
+fn delete_word_forward() {
+    let mut r = JumpRope::from("a.. b\n\ncd");
+    assert_eq!(r.delete_word_forward(1), (1, "..".into()));
+    assert_eq!(r.delete_word_forward(1), (1, " b".into()));
+    assert_eq!(r.delete_word_forward(1), (1, "\n".into()));
+    assert_eq!(r.delete_word_forward(1), (1, "\n".into()));
+    assert_eq!(r.delete_word_forward(1), (1, "cd".into()));
+    assert_eq!(r.delete_word_forward(1), (1, "".into()));
+    check(&r, "a");
+}
+
+#[test]
+fn delete_word_keeps_clusters() {
+    // A combining accent stays with its letter, and the family emoji (joined with ZWJs) is
+    // deleted as one piece of punctuation.
+    let mut r = JumpRope::from("cafe\u{301} 👨\u{200D}👩\u{200D}👧!");
+    let len = r.len_chars();
+    assert_eq!(r.delete_word_backward(len), (6, "👨\u{200D}👩\u{200D}👧!".into()));
+    check(&r, "cafe\u{301} ");
+    assert_eq!(r.delete_word_backward(6), (0, "cafe\u{301} ".into()));
+
+    let mut r = JumpRope::from("x\u{301}\u{301}y z");
+    assert_eq!(r.delete_word_forward(0), (0, "x\u{301}\u{301}y".into()));
+    check(&r, " z");
+
+    // Deleting punctuation before a combining mark takes the mark too.
+    let mut r = JumpRope::from("ab.\u{301}");
+    assert_eq!(r.delete_word_backward(4), (2, ".\u{301}".into()));
+}
+
+#[test]
+fn edit_newline_runs() {
+    let mut rng = SmallRng::seed_from_u64(19);
+    let mut expected = random_unicode_string(300, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+
+    for i in 0..300 {
+        // Runs of newlines, sometimes mixed with other characters and spanning several nodes.
+        let run_len = rng.gen_range(1..40usize);
+        let content = if i % 3 == 0 {
+            "\n".repeat(run_len)
+        } else {
+            (0..run_len).map(|_| if rng.gen_bool(0.7) { '\n' } else { 'κ' }).collect()
+        };
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, &content);
+        string_insert_at(&mut expected, pos, &content);
+        r.check();
+
+        let pos = rng.gen_range(0..r.len_chars());
+        let len = rng.gen_range(1..=min(r.len_chars() - pos, 40));
+        r.remove(pos..pos + len);
+        string_del_at(&mut expected, pos, len);
+        r.check();
+    }
+    check(&r, &expected);
+
+    r.remove(0..r.len_chars());
+    r.insert(0, &"\n".repeat(100));
+    check(&r, &"\n".repeat(100));
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn lines_at() {
+    let mut rng = SmallRng::seed_from_u64(25);
+    for s in ["", "\n", "a", "a\n", "a\nb", "a\r\nb\r\n", "\n\n\nx\n\n"] {
+        let r = JumpRopeSized::<4>::from(s);
+        for line in 0..6 {
+            assert_eq!(r.lines_at(line).collect::<Vec<_>>(), s.lines().skip(line).collect::<Vec<_>>());
+        }
+    }
+
+    let s = random_unicode_string(1000, &mut rng);
+    let r = JumpRopeSized::<16>::from(s.as_str());
+    assert_eq!(r.lines_at(2).collect::<Vec<_>>(), s.lines().skip(2).collect::<Vec<_>>());
+    let num_lines = s.lines().count();
+    for line in [0, 1, num_lines / 2, num_lines - 1, num_lines, num_lines + 5] {
+        assert_eq!(r.lines_at(line).collect::<Vec<_>>(), s.lines().skip(line).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn char_at() {
+    let mut rng = SmallRng::seed_from_u64(5);
+    let s = random_unicode_string(1000, &mut rng);
+    let r = JumpRope::from(s.as_str());
+
+    for (i, c) in s.chars().enumerate() {
+        assert_eq!(r.char_at(i), Some(c));
+    }
+    assert_eq!(r.char_at(1000), None);
+    assert_eq!(JumpRope::new().char_at(0), None);
+}
+
+#[test]
+fn char_at_or() {
+    let r = JumpRope::from("κόσμε");
+    assert_eq!(r.char_at_or(0, '\0'), 'κ');
+    assert_eq!(r.char_at_or(4, '\0'), 'ε');
+    assert_eq!(r.char_at_or(5, '\0'), '\0');
+    assert_eq!(r.char_at_or(100, '$'), '$');
+    assert_eq!(JumpRope::new().char_at_or(0, '$'), '$');
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+fn char_at_line_col() {
+    let r = JumpRope::from("abc\nκόσμε\n\nend");
+
+    // First char of a line
+    assert_eq!(r.char_at_line_col(0, 0), Some('a'));
+    assert_eq!(r.char_at_line_col(1, 0), Some('κ'));
+    assert_eq!(r.char_at_line_col(3, 0), Some('e'));
+
+    // Multibyte char in the middle of a line
+    assert_eq!(r.char_at_line_col(1, 3), Some('μ'));
+
+    // Newlines are at the end of each line
+    assert_eq!(r.char_at_line_col(1, 5), Some('\n'));
+    assert_eq!(r.char_at_line_col(2, 0), Some('\n'));
+
+    // Out of range
+    assert_eq!(r.char_at_line_col(1, 6), None);
+    assert_eq!(r.char_at_line_col(2, 1), None);
+    assert_eq!(r.char_at_line_col(3, 3), None);
+    assert_eq!(r.char_at_line_col(4, 0), None);
+}
+
+#[test]
+fn common_prefix_len() {
+    let mut rng = SmallRng::seed_from_u64(8);
+    let s = random_unicode_string(500, &mut rng);
+    let r = JumpRope::from(s.as_str());
+
+    // Identical strings
+    assert_eq!(r.common_prefix_len(&s), 500);
+    // One string is a prefix of the other
+    let mut longer = s.clone();
+    longer.push_str("more");
+    assert_eq!(r.common_prefix_len(&longer), 500);
+    let shorter: String = s.chars().take(200).collect();
+    assert_eq!(r.common_prefix_len(&shorter), 200);
+    // Completely different strings
+    assert_eq!(r.common_prefix_len(""), 0);
+    assert_eq!(JumpRope::new().common_prefix_len(&s), 0);
+
+    // Multibyte characters which share their first byte aren't counted.
+    let r = JumpRope::from("κόσμε, ¥ world");
+    assert_eq!(r.common_prefix_len("κόσμε, ¥ world"), 14);
+    assert_eq!(r.common_prefix_len("κόσμε, ©"), 7); // '¥' and '©' both start with 0xc2
+    assert_eq!(r.common_prefix_len("κόσ"), 3);
+    assert_eq!(r.common_prefix_len("hi"), 0);
+}
+
+#[test]
+fn common_suffix_len() {
+    let mut rng = SmallRng::seed_from_u64(9);
+    let s = random_unicode_string(500, &mut rng);
+    let r = JumpRope::from(s.as_str());
+
+    // Identical strings
+    assert_eq!(r.common_suffix_len(&s), 500);
+    // One string is a suffix of the other
+    assert_eq!(r.common_suffix_len(&format!("more{}", s)), 500);
+    let shorter: String = s.chars().skip(300).collect();
+    assert_eq!(r.common_suffix_len(&shorter), 200);
+    let r2 = JumpRope::from(shorter.as_str());
+    assert_eq!(r2.common_suffix_len(&s), 200);
+    // Completely different strings
+    assert_eq!(r.common_suffix_len(""), 0);
+    assert_eq!(JumpRope::new().common_suffix_len(&s), 0);
+
+    // Shared multibyte suffix. '↯' and '↻' only differ in their last byte, and 'ό' and 'ύ' only
+    // differ in their last byte.
+    let r = JumpRope::from("hello ↯ κόσμε");
+    assert_eq!(r.common_suffix_len("bye ↯ κόσμε"), 8);
+    assert_eq!(r.common_suffix_len("↻ κόσμε"), 6);
+    assert_eq!(r.common_suffix_len("κύσμε"), 3);
+    assert_eq!(r.common_suffix_len("hi"), 0);
+}
+
+#[test]
+fn find_at() {
+    assert_eq!(JumpRope::new().find_at("a", 0), None);
+    assert_eq!(JumpRope::new().find_at("", 0), Some(0));
+    assert_eq!(JumpRope::from("abc").find_at("", 2), Some(2));
+    assert_eq!(JumpRope::from("abc").find_at("c", 4), None);
+
+    // Small nodes, so matches frequently straddle node boundaries.
+    let mut rng = SmallRng::seed_from_u64(20);
+    for pattern in ["ab", "½↯", "a𐆐b\n", "↯↯↯↯↯↯↯↯↯↯↯↯↯↯↯↯↯↯↯↯"] {
+        let mut expected = String::new();
+        for _ in 0..100 {
+            let len = rng.gen_range(0..20);
+            expected.push_str(&random_unicode_string(len, &mut rng));
+            expected.push_str(pattern);
+        }
+        let r = JumpRopeSized::<10>::from(expected.as_str());
+
+        let mut found = vec![];
+        let mut start = 0;
+        while let Some(pos) = r.find_at(pattern, start) {
+            found.push(pos);
+            start = pos + pattern.chars().count();
+        }
+
+        let expected_positions: Vec<usize> = expected.match_indices(pattern)
+            .map(|(i, _)| expected[..i].chars().count())
+            .collect();
+        assert!(expected_positions.len() >= 100);
+        assert_eq!(found, expected_positions);
+    }
+}
+
+#[test]
+fn write_to_string() {
+    let mut rng = SmallRng::seed_from_u64(10);
+    let s = random_unicode_string(1000, &mut rng);
+    let r = JumpRope::from(s.as_str());
+
+    let mut out = String::from("prefix ");
+    r.write_to_string(&mut out);
+    assert_eq!(out, format!("prefix {}", s));
+
+    for range in [0..0, 0..1000, 10..20, 500..1000, 999..1000] {
+        let mut out = String::new();
+        r.to_string_in_range(range.clone(), &mut out);
+        let expected: String = s.chars().skip(range.start).take(range.len()).collect();
+        assert_eq!(out, expected);
+    }
+}
+
+#[test]
+fn is_ascii() {
+    assert!(JumpRope::new().is_ascii());
+
+    let mut rng = SmallRng::seed_from_u64(12);
+    let s = random_ascii_string(1000, &mut rng);
+    let mut r = JumpRope::from(s.as_str());
+    assert!(r.is_ascii());
+
+    // Every non-ASCII character in UNI_CHARS makes the rope non-ASCII.
+    for c in UNI_CHARS.iter().filter(|c| !c.is_ascii()) {
+        let mut buf = [0; 4];
+        r.insert(500, c.encode_utf8(&mut buf));
+        assert!(!r.is_ascii());
+        r.remove(500..501);
+        assert!(r.is_ascii());
+    }
+    check(&r, s.as_str());
+}
+
+#[test]
+fn slice_cow() {
+    use std::borrow::Cow;
+
+    // Loaded in one go, each node holds exactly 16 characters with the gap at the end.
+    let s: String = (0..16 * 10).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    let r = JumpRopeSized::<16>::from(s.as_str());
+
+    // Inside a node
+    assert!(matches!(r.slice_cow(20, 5), Ok(Cow::Borrowed(x)) if x == &s[20..25]));
+    // A whole node, ending exactly at a node boundary
+    assert!(matches!(r.slice_cow(16, 16), Ok(Cow::Borrowed(x)) if x == &s[16..32]));
+    assert!(matches!(r.slice_cow(30, 2), Ok(Cow::Borrowed(x)) if x == &s[30..32]));
+    // One character past the boundary
+    assert!(matches!(r.slice_cow(30, 3), Ok(Cow::Owned(x)) if x == s[30..33]));
+    assert!(matches!(r.slice_cow(10, 100), Ok(Cow::Owned(x)) if x == s[10..110]));
+
+    assert_eq!(r.slice_cow(160, 0).unwrap(), "");
+    assert_eq!(r.slice_cow(150, 10).unwrap(), &s[150..160]);
+    assert_eq!(r.slice_cow(150, 11), Err(jumprope::RopeError::PositionOutOfBounds { pos: 161, len: 160 }));
+    assert!(r.slice_cow(usize::MAX, 2).is_err());
+
+    // Multibyte characters, with edits moving the gaps around.
+    let mut rng = SmallRng::seed_from_u64(13);
+    let mut expected = random_unicode_string(300, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+    for _ in 0..300 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, "ab");
+        string_insert_at(&mut expected, pos, "ab");
+
+        let pos = rng.gen_range(0..r.len_chars());
+        let len = rng.gen_range(0..=(r.len_chars() - pos).min(10));
+        let slice: String = expected.chars().skip(pos).take(len).collect();
+        assert_eq!(r.slice_cow(pos, len).unwrap(), slice);
+    }
+}
+
+#[test]
+fn set_content() {
+    let mut rng = SmallRng::seed_from_u64(15);
+    let s = random_ascii_string(2000, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(s.as_str());
+    let node_count = r.node_count();
+
+    // Content of the same length uses exactly the same nodes.
+    let s = random_ascii_string(2000, &mut rng);
+    r.set_content(&s);
+    check(&r, &s);
+    assert_eq!(r.node_count(), node_count);
+
+    // Edit, so the nodes aren't all full and the cursor is cached.
+    let mut expected = s;
+    for _ in 0..200 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        let content = random_unicode_string(rng.gen_range(1..5), &mut rng);
+        r.insert(pos, &content);
+        string_insert_at(&mut expected, pos, &content);
+    }
+    r.remove(30..400);
+    string_del_at(&mut expected, 30, 370);
+    check(&r, &expected);
+
+    for len in [3000, 10, 0, 500, 5000] {
+        let s = random_unicode_string(len, &mut rng);
+        r.set_content(&s);
+        check(&r, &s);
+    }
+
+    // The rope is still usable after being replaced.
+    r.insert(100, "hi");
+    r.remove(0..50);
+    r.check();
+}
+
+#[test]
+fn checkpoint_and_restore() {
+    let mut rng = SmallRng::seed_from_u64(33);
+    let original = random_unicode_string(1000, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(original.as_str());
+    let checkpoint = r.checkpoint();
+
+    for _ in 0..3 {
+        for _ in 0..300 {
+            let len = r.len_chars();
+            let pos = rng.gen_range(0..=len);
+            if rng.gen_bool(0.5) {
+                r.insert(pos, &random_unicode_string(rng.gen_range(1..30), &mut rng));
+            } else {
+                r.remove(pos..(pos + rng.gen_range(0..30usize)).min(len));
+            }
+        }
+        r.restore(&checkpoint);
+        check(&r, &original);
+    }
+
+    // Edits after restoring behave normally (the cached cursor from before is gone).
+    let mut expected = original.clone();
+    for _ in 0..100 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, "ab");
+        string_insert_at(&mut expected, pos, "ab");
+    }
+    r.remove(10..20);
+    string_del_at(&mut expected, 10, 10);
+    check(&r, &expected);
+
+    // Checkpoints can be restored into other ropes too.
+    let mut other = JumpRopeSized::<16>::new();
+    other.restore(&checkpoint);
+    check(&other, &original);
+}
+
+#[test]
+fn snapshot() {
+    let mut rng = SmallRng::seed_from_u64(34);
+    let mut expected = random_unicode_string(500, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+
+    // Take a snapshot before every edit, like an undo stack.
+    let mut snapshots: Vec<RopeSnapshot<16>> = vec![];
+    let mut contents = vec![];
+    for _ in 0..100 {
+        snapshots.push(r.snapshot());
+        contents.push(expected.clone());
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, "κa");
+        string_insert_at(&mut expected, pos, "κa");
+        let pos = rng.gen_range(0..r.len_chars());
+        r.remove(pos..pos + 1);
+        string_del_at(&mut expected, pos, 1);
+    }
+    check(&r, &expected);
+
+    for (snapshot, content) in snapshots.iter().zip(&contents) {
+        assert_eq!(snapshot, content);
+        assert_eq!(snapshot.to_string(), *content);
+        assert_eq!(snapshot.substrings().collect::<String>(), *content);
+        assert!(snapshot.chars().eq(content.chars()));
+        assert_eq!(snapshot.len_chars(), content.chars().count());
+        assert_eq!(snapshot.len_bytes(), content.len());
+        assert_eq!(snapshot.clone(), content);
+    }
+
+    // Walk back through the stack.
+    while let Some(snapshot) = snapshots.pop() {
+        r.restore(&snapshot);
+        check(&r, &snapshot.to_string());
+    }
+    check(&r, &contents[0]);
+}
+
+#[test]
+fn for_each_chunk() {
+    let mut rng = SmallRng::seed_from_u64(24);
+    let mut r = JumpRopeSized::<16>::new();
+    for _ in 0..200 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, &random_unicode_string(rng.gen_range(1..10), &mut rng));
+
+        let mut bytes = 0;
+        let mut chunks = vec![];
+        r.for_each_chunk(|s| {
+            bytes += s.len();
+            chunks.push(s.to_string());
+        });
+        assert_eq!(bytes, r.len_bytes());
+        assert_eq!(chunks, r.substrings().collect::<Vec<_>>());
+    }
+
+    JumpRope::new().for_each_chunk(|_| panic!("Empty ropes have no chunks"));
+}
+
+#[test]
+fn scan_forward_and_backward() {
+    let mut rng = SmallRng::seed_from_u64(25);
+    let s = random_unicode_string(if cfg!(miri) { 100 } else { 2000 }, &mut rng);
+    let chars: Vec<char> = s.chars().collect();
+    let r = JumpRopeSized::<16>::from(s.as_str());
+
+    for from in 0..=chars.len() {
+        // Stop at a random set of characters.
+        let stop = chars[rng.gen_range(0..chars.len())];
+        let expected = chars[from..].iter().position(|c| *c == stop).map_or(chars.len(), |i| from + i);
+        assert_eq!(r.scan_forward(from, |c| c != stop), expected);
+        let expected = chars[..from].iter().rposition(|c| *c == stop).map_or(0, |i| i + 1);
+        assert_eq!(r.scan_backward(from, |c| c != stop), expected);
+    }
+
+    let empty = JumpRope::new();
+    assert_eq!(empty.scan_forward(0, |_| true), 0);
+    assert_eq!(empty.scan_backward(0, |_| true), 0);
+}
+
+/// Find the bracket matching the one at pos, by tracking the nesting depth while scanning.
+fn matching_bracket<const N: usize>(r: &JumpRopeSized<N>, pos: usize) -> Option<usize> {
+    let mut depth = 0;
+    match r.char_at(pos)? {
+        '(' => {
+            let end = r.scan_forward(pos, |c| {
+                if c == '(' { depth += 1; }
+                if c == ')' { depth -= 1; }
+                depth > 0
+            });
+            (end < r.len_chars()).then_some(end)
+        }
+        ')' => {
+            let start = r.scan_backward(pos + 1, |c| {
+                if c == ')' { depth += 1; }
+                if c == '(' { depth -= 1; }
+                depth > 0
+            });
+            start.checked_sub(1)
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn scan_matching_brackets() {
+    // Build a large document of nested brackets, recording where each pair is.
+    let mut rng = SmallRng::seed_from_u64(26);
+    let mut s = String::new();
+    let mut open = vec![];
+    let mut pairs = vec![];
+    let mut len = 0;
+    for _ in 0..if cfg!(miri) { 200 } else { 20000 } {
+        match rng.gen_range(0..3usize) {
+            0 => { open.push(len); s.push('('); }
+            1 if !open.is_empty() => { pairs.push((open.pop().unwrap(), len)); s.push(')'); }
+            _ => s.push(['a', 'κ', '\n', '𐆚'][rng.gen_range(0..4usize)]),
+        }
+        len += 1;
+    }
+    let r = JumpRopeSized::<16>::from(s.as_str());
+
+    for (start, end) in pairs {
+        assert_eq!(matching_bracket(&r, start), Some(end));
+        assert_eq!(matching_bracket(&r, end), Some(start));
+    }
+    // Brackets which were never closed.
+    for start in open {
+        assert_eq!(matching_bracket(&r, start), None);
+    }
+}
+
+#[test]
+fn insert_and_remove_reporting() {
+    let mut rng = SmallRng::seed_from_u64(27);
+    let mut expected = random_unicode_string(500, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+    let byte_pos = |s: &str, pos: usize| s.char_indices().nth(pos).map_or(s.len(), |(i, _)| i);
+
+    for _ in 0..300 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        let content = random_unicode_string(rng.gen_range(0..10), &mut rng);
+        let start = byte_pos(&expected, pos);
+        assert_eq!(r.insert_reporting(pos, &content), Ok(start..start + content.len()));
+        string_insert_at(&mut expected, pos, &content);
+        assert_eq!(&expected[start..start + content.len()], content);
+
+        let pos = rng.gen_range(0..=r.len_chars());
+        let len = rng.gen_range(0..=(r.len_chars() - pos).min(10));
+        let range = byte_pos(&expected, pos)..byte_pos(&expected, pos + len);
+        assert_eq!(r.remove_reporting(pos..pos + len), Ok(range));
+        string_del_at(&mut expected, pos, len);
+        check(&r, &expected);
+    }
+
+    let len = r.len_chars();
+    assert!(r.insert_reporting(len + 1, "x").is_err());
+    assert!(r.remove_reporting(len + 1..len + 2).is_err());
+    check(&r, &expected);
+}
+
+#[test]
+fn as_str() {
+    assert_eq!(JumpRope::new().as_str(), Some(""));
+    let r = JumpRopeSized::<100>::from("short line κόσμε");
+    assert_eq!(r.as_str(), Some("short line κόσμε"));
+
+    let mut r = JumpRopeSized::<16>::new();
+    r.push_str("abc");
+    r.push_str("def");
+    assert_eq!(r.as_str(), Some("abcdef"));
+    r.insert(3, "-");
+    assert_eq!(r.as_str(), None);
+
+    // Content spanning several nodes isn't contiguous.
+    let mut rng = SmallRng::seed_from_u64(28);
+    let s = random_unicode_string(100, &mut rng);
+    let r = JumpRopeSized::<16>::from(s.as_str());
+    assert!(r.node_count() > 1);
+    assert_eq!(r.as_str(), None);
+}
+
+#[test]
+fn chunks_in_range() {
+    let mut rng = SmallRng::seed_from_u64(14);
+    let mut expected = random_unicode_string(300, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+    for _ in 0..300 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, "ab");
+        string_insert_at(&mut expected, pos, "ab");
+
+        let start = rng.gen_range(0..=r.len_chars());
+        let end = rng.gen_range(start..=r.len_chars());
+        let mut chunks = String::new();
+        for s in r.chunks_in_range(start..end) {
+            assert!(!s.is_empty());
+            chunks.push_str(s);
+        }
+        assert_eq!(chunks, r.slice_cow(start, end - start).unwrap());
+    }
+
+    let len = r.len_chars();
+    assert_eq!(r.chunks_in_range(..).collect::<String>(), expected);
+    assert_eq!(r.chunks_in_range(..=len - 1).collect::<String>(), expected);
+    assert_eq!(r.chunks_in_range(len..).count(), 0);
+    assert_eq!(r.chunks_in_range(10..10).count(), 0);
+}
+
+#[test]
+fn into_chunks() {
+    assert_eq!(JumpRope::new().into_chunks().count(), 0);
+
+    let mut rng = SmallRng::seed_from_u64(18);
+    let mut expected = random_unicode_string(500, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+    for _ in 0..50 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, "ab");
+        string_insert_at(&mut expected, pos, "ab");
+    }
+
+    let chunks: Vec<String> = r.into_chunks().collect();
+    assert!(chunks.iter().all(|s| !s.is_empty()));
+    assert_eq!(chunks.concat(), expected);
+}
+
+#[cfg(feature = "allocator-api2")]
+#[test]
+fn into_chunks_frees_nodes() {
+    let alloc = Counting::default();
+    let mut rng = SmallRng::seed_from_u64(19);
+    let expected = random_unicode_string(1000, &mut rng);
+    let mut r = JumpRopeSized::<16, &Counting>::new_in(&alloc);
+    r.insert(0, &expected);
+
+    let mut result = String::new();
+    let mut chunks = r.into_chunks();
+    let mut live_bytes = alloc.live_bytes.get();
+    while let Some(s) = chunks.next() {
+        result.push_str(&s);
+        // Every chunk after the first comes from a node, which is freed right away.
+        if result.len() > s.len() {
+            assert!(alloc.live_bytes.get() < live_bytes);
+        }
+        live_bytes = alloc.live_bytes.get();
+    }
+    assert_eq!(result, expected);
+    assert_eq!(alloc.live_bytes.get(), 0);
+    drop(chunks);
+    assert_eq!(alloc.live_bytes.get(), 0);
+}
+
+#[test]
+fn char_boundaries() {
+    assert_eq!(JumpRope::new().char_boundaries().collect::<Vec<_>>(), [0]);
+
+    let mut rng = SmallRng::seed_from_u64(24);
+    let mut r = JumpRopeSized::<16>::from(random_unicode_string(300, &mut rng));
+    for _ in 0..20 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, "½b");
+
+        let s = r.to_string();
+        let mut expected: Vec<usize> = s.char_indices().map(|(i, _)| i).collect();
+        expected.push(s.len());
+        assert_eq!(r.char_boundaries().collect::<Vec<_>>(), expected);
+    }
+}
+
+#[test]
+fn chars_in_range() {
+    let mut rng = SmallRng::seed_from_u64(27);
+    let r = JumpRopeSized::<16>::from(random_unicode_string(300, &mut rng));
+    for _ in 0..100 {
+        let a = rng.gen_range(0..=r.len_chars());
+        let b = rng.gen_range(a..=r.len_chars());
+        assert_eq!(r.chars_in_range(a..b).collect::<String>(), r.slice(a, b - a).unwrap());
+        assert_eq!(r.chars_in_range(a..).count(), r.len_chars() - a);
+    }
+    assert_eq!(r.chars_in_range(..).collect::<String>(), r.to_string());
+}
+
+#[test]
+fn to_bytes_and_chars() {
+    assert!(JumpRope::new().to_bytes().is_empty());
+    assert!(JumpRope::new().to_chars().is_empty());
+
+    let mut rng = SmallRng::seed_from_u64(26);
+    let r = JumpRopeSized::<16>::from(random_unicode_string(500, &mut rng));
+    let bytes = r.to_bytes();
+    assert_eq!(bytes, r.to_string().into_bytes());
+    assert_eq!(bytes.capacity(), r.len_bytes());
+    let chars = r.to_chars();
+    assert_eq!(chars, r.to_string().chars().collect::<Vec<_>>());
+    assert_eq!(chars.capacity(), r.len_chars());
+}
+
+#[test]
+fn chars_rev() {
+    assert_eq!(JumpRope::new().chars().rev().count(), 0);
+    assert_eq!(JumpRope::from("hi there").chars().rev().collect::<String>(), "ereht ih");
+
+    let mut rng = SmallRng::seed_from_u64(15);
+    let mut expected = random_unicode_string(200, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+    for _ in 0..100 {
+        // Edits leave gaps in the middle of nodes.
+        let pos = rng.gen_range(0..=r.len_chars());
+        r.insert(pos, "xyz");
+        string_insert_at(&mut expected, pos, "xyz");
+
+        assert_eq!(r.chars().rev().collect::<String>(), expected.chars().rev().collect::<String>());
+        assert_eq!(r.substrings().rev().collect::<Vec<_>>().concat().len(), expected.len());
+
+        // Iterating from both ends should meet in the middle.
+        let mut iter = r.chars();
+        let mut front = String::new();
+        let mut back = String::new();
+        loop {
+            let c = if rng.gen_bool(0.5) { iter.next().map(|c| front.push(c)) } else { iter.next_back().map(|c| back.push(c)) };
+            if c.is_none() { break; }
+        }
+        assert!(iter.next().is_none() && iter.next_back().is_none());
+        front.extend(back.chars().rev());
+        assert_eq!(front, expected);
+    }
+}
+
+#[test]
+fn get_range() {
+    use std::ops::Bound;
+
+    let mut rng = SmallRng::seed_from_u64(17);
+    let s = random_unicode_string(500, &mut rng);
+    let r = JumpRopeSized::<16>::from(s.as_str());
+    let sub = |range: Range<usize>| s.chars().skip(range.start).take(range.len()).collect::<String>();
+
+    for _ in 0..200 {
+        let start = rng.gen_range(0..=500);
+        let end = rng.gen_range(start..=500);
+        assert_eq!(r.get(start..end), Some(sub(start..end)));
+    }
+    assert_eq!(r.get(..).as_deref(), Some(s.as_str()));
+    assert_eq!(r.get(100..), Some(sub(100..500)));
+    assert_eq!(r.get(..=99), Some(sub(0..100)));
+    assert_eq!(r.get((Bound::Excluded(10), Bound::Included(20))), Some(sub(11..21)));
+
+    // Empty ranges
+    assert_eq!(r.get(0..0).as_deref(), Some(""));
+    assert_eq!(r.get(500..500).as_deref(), Some(""));
+    assert_eq!(JumpRope::new().get(..).as_deref(), Some(""));
+
+    // Out of bounds and inverted ranges
+    assert_eq!(r.get(0..501), None);
+    assert_eq!(r.get(501..), None);
+    assert_eq!(r.get(..=500), None);
+    assert_eq!(r.get(..=usize::MAX), None);
+    assert_eq!(r.get((Bound::Excluded(usize::MAX), Bound::Unbounded)), None);
+    #[allow(clippy::reversed_empty_ranges)]
+    let inverted = 20..10;
+    assert_eq!(r.get(inverted), None);
+}
+
+#[test]
+fn extend_string_with_range() {
+    let mut rng = SmallRng::seed_from_u64(18);
+    let s = random_unicode_string(500, &mut rng);
+    let r = JumpRopeSized::<16>::from(s.as_str());
+    let sub = |range: Range<usize>| s.chars().skip(range.start).take(range.len()).collect::<String>();
+
+    let mut out = String::new();
+    let mut expected = String::new();
+    for _ in 0..100 {
+        let start = rng.gen_range(0..=500);
+        let end = rng.gen_range(start..=500);
+        r.extend_string_with_range(start..end, &mut out).unwrap();
+        expected.push_str(&sub(start..end));
+    }
+    r.extend_string_with_range(.., &mut out).unwrap();
+    r.extend_string_with_range(..=9, &mut out).unwrap();
+    r.extend_string_with_range(500.., &mut out).unwrap();
+    expected.push_str(&s);
+    expected.push_str(&sub(0..10));
+    assert_eq!(out, expected);
+
+    // Errors leave the buffer alone.
+    use jumprope::RopeError::PositionOutOfBounds;
+    assert_eq!(r.extend_string_with_range(490..501, &mut out), Err(PositionOutOfBounds { pos: 501, len: 500 }));
+    #[allow(clippy::reversed_empty_ranges)]
+    let inverted = 20..10;
+    assert_eq!(r.extend_string_with_range(inverted, &mut out), Err(PositionOutOfBounds { pos: 20, len: 10 }));
+    assert_eq!(out, expected);
+}
+
 #[test]
 fn del_at_location() {
     let mut r = JumpRope::from("012345678");
@@ -143,6 +1316,196 @@ fn del_past_end_of_string() {
     check(&r, "hi ");
 }
 
+#[test]
+fn bounds_policy() {
+    // The rope has 8 characters. For each (pos, len), the expected result of try_remove / slice
+    // and of try_slice. None means PositionOutOfBounds.
+    let table: &[(usize, usize, Option<&str>, Option<&str>)] = &[
+        // Before the end.
+        (3, 0, Some(""), Some("")),
+        (3, 2, Some("th"), Some("th")),
+        (3, 5, Some("there"), Some("there")),
+        (3, 6, Some("there"), None),
+        (3, usize::MAX, Some("there"), None),
+        // At the end.
+        (8, 0, Some(""), Some("")),
+        (8, 1, Some(""), None),
+        // Past the end.
+        (9, 0, None, None),
+        (9, 1, None, None),
+        (usize::MAX, 1, None, None),
+    ];
+
+    for &(pos, len, clamped, strict) in table {
+        let mut r = JumpRope::from("hi there");
+        let oob = jumprope::RopeError::PositionOutOfBounds { pos, len: 8 };
+
+        match clamped {
+            Some(s) => assert_eq!(r.slice(pos, len).unwrap(), s, "slice({}, {})", pos, len),
+            None => assert_eq!(r.slice(pos, len), Err(oob), "slice({}, {})", pos, len),
+        }
+        match strict {
+            Some(s) => assert_eq!(r.try_slice(pos, len).unwrap(), s, "try_slice({}, {})", pos, len),
+            None => assert!(r.try_slice(pos, len).is_err(), "try_slice({}, {})", pos, len),
+        }
+
+        let result = r.try_remove(pos..pos.saturating_add(len));
+        match clamped {
+            Some(s) => {
+                assert_eq!(result, Ok(()));
+                check(&r, &format!("{}{}", &"hi there"[..pos], &"hi there"[pos + s.len()..]));
+            }
+            None => {
+                assert_eq!(result, Err(oob));
+                check(&r, "hi there");
+            }
+        }
+    }
+}
+
+#[test]
+fn zero_length_edits() {
+    let s = "κόσμε and a rope which spans a bunch of nodes";
+    let mut r = JumpRope::from(s);
+    let len = r.len_chars();
+
+    for pos in [0, 1, len / 2, len, len + 1, usize::MAX] {
+        r.remove(pos..pos);
+        r.replace(pos..pos, "");
+        r.insert(pos, "");
+        check(&r, s);
+    }
+
+    // Empty ranges are still checked against the length of the rope.
+    assert_eq!(r.try_remove(len..len), Ok(()));
+    assert_eq!(r.slice(len, 0).unwrap(), "");
+    let oob = jumprope::RopeError::PositionOutOfBounds { pos: len + 1, len };
+    assert_eq!(r.try_remove(len + 1..len + 1), Err(oob));
+    assert_eq!(r.slice(len + 1, 0), Err(oob));
+    assert_eq!(r.try_slice(len + 1, 0), Err(oob));
+    assert_eq!(r.try_insert(len + 1, ""), Err(oob));
+    check(&r, s);
+}
+
+#[test]
+fn clear_and_reuse() {
+    let mut r = JumpRope::from("hi there this is a string which is longer than one node");
+    r.clear();
+    check(&r, "");
+
+    r.insert(0, "and this is some more text which also spans a few nodes");
+    check(&r, "and this is some more text which also spans a few nodes");
+    r.remove(3..20);
+    check(&r, "ande text which also spans a few nodes");
+    r.clear();
+    r.clear();
+    check(&r, "");
+}
+
+#[test]
+fn current_height_grows() {
+    let mut r = JumpRope::new();
+    assert_eq!(r.current_height(), 0);
+
+    let mut last_height = 0;
+    for _ in 0..2000 {
+        r.insert(r.len_chars(), "0123456789");
+        let height = r.current_height();
+        assert!(height >= last_height);
+        assert!(height <= JumpRope::max_height());
+        last_height = height;
+    }
+
+    // 20000 characters is at least 2000 nodes in debug mode and ~50 nodes in release mode.
+    assert!(r.current_height() > 1);
+}
+
+#[test]
+fn custom_rng_controls_height() {
+    use rand::rngs::mock::StepRng;
+    let content = "abcdefghij".repeat(100);
+
+    // Random bytes >= BIAS stop a node's tower growing, so this RNG makes every node height 1.
+    let mut r = JumpRopeSized::<16>::with_rng(StepRng::new(u64::MAX, 0));
+    r.insert(0, content.as_str());
+    check(&r, content.as_str());
+    assert_eq!(r.current_height(), 1);
+
+    // And this one makes every node as tall as possible.
+    let mut r = JumpRopeSized::<16>::with_rng(StepRng::new(0, 0));
+    r.insert(0, content.as_str());
+    r.remove(10..500);
+    r.insert(5, content.as_str());
+    check(&r, &format!("abcde{}fghij{}", content, &content[500..]));
+    assert_eq!(r.current_height(), JumpRope::max_height());
+}
+
+/// An RNG which gives nodes each height from 1 to the maximum in turn.
+#[derive(Default)]
+struct EveryHeight {
+    height: usize,
+    bytes: usize,
+}
+
+impl EveryHeight {
+    fn next_byte(&mut self) -> u8 {
+        // A node of height h needs h - 1 bytes < BIAS, followed by one which isn't. Except nodes of
+        // the maximum height, which stop growing without reading another byte.
+        let height = self.height + 1;
+        self.bytes += 1;
+        if self.bytes == height || (height == JumpRope::max_height() && self.bytes == height - 1) {
+            self.height = height % JumpRope::max_height();
+            self.bytes = 0;
+            if height < JumpRope::max_height() { return u8::MAX; }
+        }
+        0
+    }
+}
+
+impl RngCore for EveryHeight {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        u64::from_le_bytes(std::array::from_fn(|_| self.next_byte()))
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for b in dest { *b = self.next_byte(); }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[test]
+fn edits_on_towers_of_every_height() {
+    let mut rng = SmallRng::seed_from_u64(18);
+    let mut expected = random_unicode_string(400, &mut rng);
+    let mut r = JumpRopeSized::<16>::with_rng(EveryHeight::default());
+    r.insert(0, &expected);
+    assert!(r.height_histogram().iter().all(|n| *n > 0), "{:?}", r.height_histogram());
+
+    // Growing and shrinking nodes updates the skip counts at every level of the towers above.
+    for _ in 0..500 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        let content = random_unicode_string(rng.gen_range(1..5), &mut rng);
+        r.insert(pos, &content);
+        string_insert_at(&mut expected, pos, &content);
+        r.check();
+
+        let pos = rng.gen_range(0..r.len_chars());
+        let len = rng.gen_range(1..=min(r.len_chars() - pos, 5));
+        r.remove(pos..pos + len);
+        string_del_at(&mut expected, pos, len);
+        r.check();
+    }
+    check(&r, &expected);
+}
+
 #[test]
 fn really_long_ascii_string() {
     let mut rng = SmallRng::seed_from_u64(1234);
@@ -159,6 +1522,792 @@ fn really_long_ascii_string() {
     check(&r, expect.as_str());
 }
 
+#[test]
+fn nodes_larger_than_u8() {
+    // Full nodes of 300 bytes each.
+    let s = random_ascii_string(3000, &mut SmallRng::seed_from_u64(21));
+    let mut r = JumpRopeSized::<300>::from(s.as_str());
+    check(&r, &s);
+    let stats = r.stats();
+    assert_eq!(stats.used_bytes, 3000);
+    // 10 full nodes, plus the head if it's empty.
+    assert!(stats.num_nodes <= 11);
+
+    r.remove(250..260);
+    r.insert(250, &s[250..260]);
+    check(&r, &s);
+}
+
+#[test]
+fn bulk_load_fills_nodes() {
+    let mut rng = SmallRng::seed_from_u64(321);
+    let s = random_unicode_string(20000, &mut rng);
+
+    let r = JumpRope::from(s.as_str());
+    check(&r, s.as_str());
+
+    // Each node should be filled to within a character (4 bytes) of capacity. The gap in each node
+    // is at the end, so there's one substring per node.
+    let n = JumpRope::max_bytes_per_node();
+    assert!(r.substrings().count() <= s.len() / (n - 3) + 1);
+}
+
+fn big_inserts_sized<const N: usize>() {
+    let mut rng = SmallRng::seed_from_u64(99);
+    let mut r = JumpRopeSized::<N>::new();
+    let mut expected = String::new();
+
+    for i in 0..20 {
+        let len = rng.gen_range(0..2000);
+        let content = random_unicode_string(len, &mut rng);
+        // Alternate appending with inserting into the middle of existing nodes.
+        let pos = if i % 2 == 0 { r.len_chars() } else { rng.gen_range(0..=r.len_chars()) };
+        r.insert(pos, content.as_str());
+        string_insert_at(&mut expected, pos, content.as_str());
+        check(&r, expected.as_str());
+    }
+}
+
+#[test]
+fn big_inserts() {
+    big_inserts_sized::<{ JumpRope::max_bytes_per_node() }>();
+    big_inserts_sized::<4>();
+    big_inserts_sized::<64>();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Far too slow under miri.
+fn huge_insert_into_middle() {
+    let mut rng = SmallRng::seed_from_u64(100);
+    let start = random_unicode_string(30, &mut rng);
+    let mut r = JumpRope::from(start.as_str());
+    let mut expected = start;
+
+    // About 1MB, in one insert. This becomes a long chain of new nodes.
+    let content = random_unicode_string(500_000, &mut rng);
+    assert!(content.len() > 1_000_000);
+    r.insert(17, content.as_str());
+    string_insert_at(&mut expected, 17, content.as_str());
+    check(&r, expected.as_str());
+
+    // The chain should be spread over a few levels of the skip list.
+    let histogram = r.height_histogram();
+    assert!(histogram[0] > 0 && histogram[1] > 0 && histogram[2] > 0);
+
+    // And the nodes around it still work.
+    r.insert(16, "ab");
+    string_insert_at(&mut expected, 16, "ab");
+    let pos = r.len_chars() - 3;
+    r.insert(pos, "cd");
+    string_insert_at(&mut expected, pos, "cd");
+    check(&r, expected.as_str());
+}
+
+fn inserts_many_nodes_long_sized<const N: usize>() {
+    let mut rng = SmallRng::seed_from_u64(101);
+    for multiple in [10, 100, 10_000] {
+        let content = random_unicode_string(N * multiple, &mut rng);
+        let start = random_unicode_string(N * 3, &mut rng);
+        let len = start.chars().count();
+
+        for pos in [0, len / 2, len] {
+            let mut r = JumpRopeSized::<N>::from(start.as_str());
+            let mut expected = start.clone();
+            r.insert(pos, content.as_str());
+            string_insert_at(&mut expected, pos, content.as_str());
+            check(&r, expected.as_str());
+
+            // Edit either side of the inserted run, next to the tail of the split node.
+            let end = pos + content.chars().count();
+            r.insert(end, "xy");
+            string_insert_at(&mut expected, end, "xy");
+            r.remove(pos.saturating_sub(1)..pos + 1);
+            string_del_at(&mut expected, pos.saturating_sub(1), pos + 1 - pos.saturating_sub(1));
+            check(&r, expected.as_str());
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Far too slow under miri.
+fn inserts_many_nodes_long() {
+    inserts_many_nodes_long_sized::<{ JumpRope::max_bytes_per_node() }>();
+    inserts_many_nodes_long_sized::<16>();
+}
+
+#[test]
+fn nodes_of_four_byte_chars() {
+    // Each node holds 2 characters in 8 bytes, so character and byte offsets within a node differ.
+    let s = "𐆐𐆔𐆘𐆚".repeat(10);
+    let len = s.chars().count();
+    for pos in 0..=len {
+        let mut r = JumpRopeSized::<8>::from(s.as_str());
+        r.insert(pos, "𐆐");
+        let mut expected = s.clone();
+        string_insert_at(&mut expected, pos, "𐆐");
+        check(&r, &expected);
+
+        r.remove(pos..pos + 1);
+        check(&r, &s);
+        assert_eq!(r.char_at(pos), s.chars().nth(pos));
+    }
+
+    #[cfg(feature = "wchar_conversion")] {
+        // Every character is 2 UTF-16 code units.
+        let mut r = JumpRopeSized::<8>::from(s.as_str());
+        for wchar_pos in (0..=len * 2).step_by(2) {
+            assert_eq!(r.wchars_to_chars(wchar_pos), wchar_pos / 2);
+        }
+        r.insert_at_wchar(len, "𐆐");
+        let mut expected = s.clone();
+        string_insert_at(&mut expected, len / 2, "𐆐");
+        check(&r, &expected);
+    }
+}
+
+fn multibyte_splits_sized<const N: usize>() {
+    // For each width of character, shift the content by a few ASCII bytes so node boundaries land
+    // at every offset within a codepoint.
+    for c in ['½', '↯', '𐆐'] {
+        for prefix_len in 0..4 {
+            let prefix = "a".repeat(prefix_len);
+            let run = c.to_string().repeat(N);
+
+            let mut r = JumpRopeSized::<N>::from(prefix.as_str());
+            r.insert(prefix_len, &run);
+            check(&r, &format!("{}{}", prefix, run));
+
+            // Inserting into the middle of full nodes spills into neighbouring nodes.
+            let mut expected = format!("{}{}", prefix, run);
+            for i in 0..N {
+                let pos = prefix_len + i * 2;
+                r.insert(pos, &run[..c.len_utf8() * (i % 3 + 1)]);
+                string_insert_at(&mut expected, pos, &run[..c.len_utf8() * (i % 3 + 1)]);
+                check(&r, &expected);
+            }
+
+            let r = JumpRopeSized::<N>::from(format!("{}{}", prefix, run));
+            check(&r, &format!("{}{}", prefix, run));
+        }
+    }
+}
+
+#[test]
+fn multibyte_splits() {
+    multibyte_splits_sized::<10>();
+    multibyte_splits_sized::<11>();
+    multibyte_splits_sized::<12>();
+    multibyte_splits_sized::<13>();
+}
+
+fn del_node_boundaries_sized<const N: usize>() {
+    // An ASCII string loaded in one go fills every node exactly, so node k covers
+    // chars k*N..(k+1)*N.
+    let s: String = (0..N * 20).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+
+    for start in [N - 1, N, N + 1] {
+        for end in [N * 10 - 1, N * 10, N * 10 + 1, N * 20] {
+            let mut r = JumpRopeSized::<N>::from(s.as_str());
+            r.remove(start..end);
+
+            let mut expected = s.clone();
+            expected.replace_range(start..end, "");
+            check(&r, expected.as_str());
+        }
+    }
+
+    // Deleting everything.
+    let mut r = JumpRopeSized::<N>::from(s.as_str());
+    r.remove(0..s.len());
+    check(&r, "");
+}
+
+fn empty_node_edges_sized<const N: usize>() {
+    let s: String = (0..N * 10).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+
+    // Delete exactly one node's content.
+    for k in 0..10 {
+        let mut r = JumpRopeSized::<N>::from(s.as_str());
+        r.remove(k * N..(k + 1) * N);
+        let mut expected = s.clone();
+        expected.replace_range(k * N..(k + 1) * N, "");
+        check(&r, expected.as_str());
+    }
+
+    // Empty a node a character at a time, from either end.
+    for from_end in [false, true] {
+        let mut r = JumpRopeSized::<N>::from(s.as_str());
+        let mut expected = s.clone();
+        for i in 0..N {
+            let pos = if from_end { N * 4 - 1 - i } else { N * 3 };
+            r.remove(pos..pos + 1);
+            expected.remove(pos);
+            check(&r, expected.as_str());
+        }
+    }
+
+    // Insert at node edges, then delete the inserted content again.
+    let mut r = JumpRopeSized::<N>::from(s.as_str());
+    for k in (0..=10).rev() {
+        r.insert(k * N, "xy");
+        r.remove(k * N..k * N + 2);
+        check(&r, s.as_str());
+    }
+}
+
+#[test]
+fn empty_node_edges() {
+    empty_node_edges_sized::<4>();
+    empty_node_edges_sized::<16>();
+    empty_node_edges_sized::<{ JumpRope::max_bytes_per_node() }>();
+}
+
+#[test]
+fn del_node_boundaries() {
+    del_node_boundaries_sized::<4>();
+    del_node_boundaries_sized::<16>();
+    del_node_boundaries_sized::<{ JumpRope::max_bytes_per_node() }>();
+}
+
+#[test]
+fn del_big_ranges() {
+    let mut rng = SmallRng::seed_from_u64(7);
+    let mut expected = random_unicode_string(20000, &mut rng);
+    let mut r = JumpRope::from(expected.as_str());
+
+    while r.len_chars() > 0 {
+        let len = r.len_chars();
+        let start = rng.gen_range(0..len);
+        let end = rng.gen_range(start..=len.min(start + 5000));
+        r.remove(start..end);
+        string_del_at(&mut expected, start, end - start);
+        check(&r, expected.as_str());
+    }
+}
+
+fn compact_sized<const N: usize>() {
+    let mut rng = SmallRng::seed_from_u64(11);
+    let mut expected = random_unicode_string(5000, &mut rng);
+    let mut r = JumpRopeSized::<N>::from(expected.as_str());
+
+    // Lots of small deletes all over the document leave nodes partially full.
+    for _ in 0..2000 {
+        let pos = rng.gen_range(0..r.len_chars());
+        let len = rng.gen_range(1..=3usize).min(r.len_chars() - pos);
+        r.remove(pos..pos + len);
+        string_del_at(&mut expected, pos, len);
+    }
+    check(&r, expected.as_str());
+
+    let nodes_before = r.substrings().count();
+    r.compact();
+    check(&r, expected.as_str());
+    // There's one substring per node after compacting, since each node's gap is at the end.
+    let nodes = r.substrings().count();
+    assert!(nodes <= nodes_before);
+    assert!(nodes <= expected.len() / (N - 3) + 1);
+
+    // Compacting again does nothing.
+    r.compact();
+    check(&r, expected.as_str());
+    assert_eq!(r.substrings().count(), nodes);
+
+    // And the rope is still editable.
+    r.insert(10, "hi there");
+    string_insert_at(&mut expected, 10, "hi there");
+    check(&r, expected.as_str());
+}
+
+#[test]
+fn compact() {
+    compact_sized::<4>();
+    compact_sized::<16>();
+    compact_sized::<{ JumpRope::max_bytes_per_node() }>();
+
+    let mut r = JumpRope::new();
+    r.compact();
+    check(&r, "");
+}
+
+#[test]
+fn repeat() {
+    let mut rng = SmallRng::seed_from_u64(23);
+    for len in [0, 1, 5, 100] {
+        let s = random_unicode_string(len, &mut rng);
+        let r = JumpRopeSized::<16>::from(s.as_str());
+        for n in [0, 1, 2, 3, 7, 8, 33] {
+            let repeated = r.repeat(n);
+            check(&repeated, &s.repeat(n));
+        }
+        assert_eq!(r.repeat(3).to_string(), r.to_string().repeat(3));
+    }
+}
+
+fn split_at_char_sized<const N: usize>() {
+    let mut rng = SmallRng::seed_from_u64(22);
+    let s = random_unicode_string(N * 20, &mut rng);
+    let len = s.chars().count();
+
+    // Every offset near the start, node boundaries and the end, plus some random ones.
+    let mut positions: Vec<usize> = (0..N * 2).chain(len - N..=len).collect();
+    positions.extend((0..20).map(|_| rng.gen_range(0..=len)));
+
+    for pos in positions {
+        let mut r = JumpRopeSized::<N>::from(s.as_str());
+        // Leave a gap in some of the nodes.
+        r.insert(pos / 2, "ab");
+        r.remove(pos / 2..pos / 2 + 2);
+
+        let (mut left, mut right) = r.split_at_char(pos).unwrap();
+        let byte_pos = s.char_indices().nth(pos).map_or(s.len(), |(b, _)| b);
+        check(&left, &s[..byte_pos]);
+        check(&right, &s[byte_pos..]);
+
+        // Both halves can still be edited.
+        left.insert(left.len_chars(), "xyz");
+        right.insert(0, "xyz");
+        check(&left, &format!("{}xyz", &s[..byte_pos]));
+        check(&right, &format!("xyz{}", &s[byte_pos..]));
+    }
+
+    let r = JumpRopeSized::<N>::from(s.as_str());
+    assert_eq!(r.split_at_char(len + 1).err(),
+        Some(jumprope::RopeError::PositionOutOfBounds { pos: len + 1, len }));
+}
+
+#[test]
+fn split_at_char() {
+    split_at_char_sized::<4>();
+    split_at_char_sized::<16>();
+    split_at_char_sized::<{ JumpRope::max_bytes_per_node() }>();
+}
+
+fn append_sized<const N: usize>() {
+    let mut rng = SmallRng::seed_from_u64(21);
+    for _ in 0..100 {
+        let a = random_unicode_string(rng.gen_range(0..300), &mut rng);
+        let b = random_unicode_string(rng.gen_range(0..300), &mut rng);
+        let mut r = JumpRopeSized::<N>::from(a.as_str());
+        let mut other = JumpRopeSized::<N>::from(b.as_str());
+        // Move the gaps around, and sometimes leave the first node of other empty.
+        if !b.is_empty() && rng.gen_bool(0.5) {
+            let len = rng.gen_range(1..=b.chars().count());
+            other.remove(0..len);
+            other.insert(0, &b.chars().take(len).collect::<String>());
+        }
+        r.append(other);
+        check(&r, &(a + b.as_str()));
+    }
+
+    // Appending ropes of very different heights, in both directions.
+    let short = JumpRopeSized::<N>::from("hi");
+    let tall_content = "x".repeat(N * 2000);
+    let mut r = JumpRopeSized::<N>::from(tall_content.as_str());
+    r.append(short.clone());
+    let mut expected = tall_content.clone() + "hi";
+    check(&r, &expected);
+
+    let mut r2 = short;
+    r2.append(r);
+    expected.insert_str(0, "hi");
+    check(&r2, &expected);
+
+    // Edits after appending see a valid rope.
+    r2.remove(N * 1000..N * 1500);
+    r2.insert(3, "yo");
+    string_del_at(&mut expected, N * 1000, N * 500);
+    string_insert_at(&mut expected, 3, "yo");
+    check(&r2, &expected);
+}
+
+#[test]
+fn append() {
+    append_sized::<4>();
+    append_sized::<16>();
+    append_sized::<{ JumpRope::max_bytes_per_node() }>();
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn from_str_parallel() {
+    let mut rng = SmallRng::seed_from_u64(8);
+    for len in [0, 1, 100, 20_000, 200_000] {
+        let s = random_unicode_string(len, &mut rng);
+        let r = JumpRope::from_str_parallel(&s);
+        check(&r, &s);
+        assert_eq!(r.to_string(), JumpRope::from(&s).to_string());
+    }
+
+    // More segments than there are threads.
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+    let s = random_unicode_string(100_000, &mut rng);
+    let r = pool.install(|| JumpRopeSized::<4>::from_str_parallel(&s));
+    check(&r, &s);
+}
+
+/// Passes allocations through to the global allocator, keeping track of how many bytes are in use.
+#[cfg(feature = "allocator-api2")]
+#[derive(Default)]
+struct Counting {
+    live_bytes: Cell<usize>,
+    num_allocs: Cell<usize>,
+}
+
+#[cfg(feature = "allocator-api2")]
+unsafe impl Allocator for Counting {
+    fn allocate(&self, layout: Layout) -> Result<ptr::NonNull<[u8]>, AllocError> {
+        self.live_bytes.set(self.live_bytes.get() + layout.size());
+        self.num_allocs.set(self.num_allocs.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: ptr::NonNull<u8>, layout: Layout) {
+        self.live_bytes.set(self.live_bytes.get() - layout.size());
+        Global.deallocate(ptr, layout)
+    }
+}
+
+#[cfg(feature = "allocator-api2")]
+#[test]
+fn custom_allocator() {
+    let alloc = Counting::default();
+    {
+        let mut rng = SmallRng::seed_from_u64(9);
+        let mut expected = String::new();
+        let mut r = JumpRopeSized::<16, &Counting>::new_in(&alloc);
+        for _ in 0..1000 {
+            let len = r.len_chars();
+            if len == 0 || rng.gen_bool(0.6) {
+                let pos = rng.gen_range(0..=len);
+                let content = random_unicode_string(rng.gen_range(1..20), &mut rng);
+                r.insert(pos, &content);
+                string_insert_at(&mut expected, pos, &content);
+            } else {
+                let pos = rng.gen_range(0..len);
+                let del_len = rng.gen_range(1..=min(len - pos, 30));
+                r.remove(pos..pos + del_len);
+                string_del_at(&mut expected, pos, del_len);
+            }
+            // Every node (including the ones in the free list) came from alloc.
+            assert_eq!(alloc.live_bytes.get(), r.mem_usage());
+        }
+        r.check();
+        assert_eq!(r, expected);
+        assert!(alloc.num_allocs.get() > 100);
+
+        let clone = r.clone();
+        clone.check();
+        assert_eq!(clone, expected);
+        assert_eq!(alloc.live_bytes.get(), r.mem_usage() + clone.mem_usage());
+    }
+    // Dropping the ropes gives everything back.
+    assert_eq!(alloc.live_bytes.get(), 0);
+}
+
+#[cfg(feature = "allocator-api2")]
+#[test]
+fn set_content_reuses_allocations() {
+    let alloc = Counting::default();
+    let mut rng = SmallRng::seed_from_u64(16);
+    let mut r = JumpRopeSized::<16, &Counting>::new_in(&alloc);
+    r.insert(0, &random_ascii_string(5000, &mut rng));
+    let num_allocs = alloc.num_allocs.get();
+
+    for _ in 0..10 {
+        let s = random_ascii_string(5000, &mut rng);
+        r.set_content(&s);
+        r.check();
+        assert_eq!(r, s);
+    }
+    assert_eq!(alloc.num_allocs.get(), num_allocs);
+
+    // Slightly longer content only needs a few more nodes.
+    r.set_content(&random_ascii_string(5050, &mut rng));
+    r.check();
+    assert!(alloc.num_allocs.get() - num_allocs <= 5);
+}
+
+#[test]
+fn byte_to_char() {
+    let mut rng = SmallRng::seed_from_u64(5);
+    let s = random_unicode_string(500, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(s.as_str());
+    r.insert(200, "");
+    r.remove(10..20);
+    r.insert(10, &s.chars().skip(10).take(10).collect::<String>());
+
+    for byte_pos in 0..=s.len() {
+        let expected = if s.is_char_boundary(byte_pos) {
+            Ok(s[..byte_pos].chars().count())
+        } else {
+            Err(jumprope::RopeError::InvalidCodepoint { pos: byte_pos })
+        };
+        assert_eq!(r.byte_to_char(byte_pos), expected);
+    }
+    assert_eq!(r.byte_to_char(s.len() + 1),
+        Err(jumprope::RopeError::PositionOutOfBounds { pos: s.len() + 1, len: s.len() }));
+}
+
+#[test]
+fn char_containing_byte() {
+    let r = JumpRope::from("a𝄞b");
+    // Every byte of the 4 byte character maps to it.
+    assert_eq!(r.char_containing_byte(0), Some((0, 'a')));
+    for byte_pos in 1..5 {
+        assert_eq!(r.char_containing_byte(byte_pos), Some((1, '𝄞')));
+    }
+    assert_eq!(r.char_containing_byte(5), Some((2, 'b')));
+    assert_eq!(r.char_containing_byte(6), None);
+    assert_eq!(JumpRope::new().char_containing_byte(0), None);
+
+    let mut rng = SmallRng::seed_from_u64(15);
+    let s = random_unicode_string(500, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(s.as_str());
+    r.insert(100, "");
+    r.remove(50..60);
+    r.insert(50, &s.chars().skip(50).take(10).collect::<String>());
+    for (char_pos, (byte_pos, c)) in s.char_indices().enumerate() {
+        for b in byte_pos..byte_pos + c.len_utf8() {
+            assert_eq!(r.char_containing_byte(b), Some((char_pos, c)));
+        }
+    }
+    assert_eq!(r.char_containing_byte(s.len()), None);
+}
+
+#[test]
+fn truncate_bytes() {
+    let mut r = JumpRope::from("a↯b↯c");
+    // Cutting in the middle of a character fails and leaves the rope as it was.
+    assert_eq!(r.truncate_bytes(3), Err(jumprope::RopeError::InvalidCodepoint { pos: 3 }));
+    check(&r, "a↯b↯c");
+
+    // Truncating past the end does nothing.
+    assert_eq!(r.truncate_bytes(100), Ok(()));
+    check(&r, "a↯b↯c");
+
+    assert_eq!(r.truncate_bytes(5), Ok(()));
+    check(&r, "a↯b");
+    assert_eq!(r.truncate_bytes(0), Ok(()));
+    check(&r, "");
+
+    let mut rng = SmallRng::seed_from_u64(6);
+    let mut expected = random_unicode_string(2000, &mut rng);
+    let mut r = JumpRope::from(expected.as_str());
+    while !expected.is_empty() {
+        let mut byte_len = rng.gen_range(0..expected.len());
+        while !expected.is_char_boundary(byte_len) { byte_len -= 1; }
+        r.truncate_bytes(byte_len).unwrap();
+        expected.truncate(byte_len);
+        check(&r, &expected);
+    }
+}
+
+#[test]
+fn copy_bytes() {
+    use jumprope::RopeError;
+
+    let mut rng = SmallRng::seed_from_u64(31);
+    let s = random_unicode_string(200, &mut rng);
+    let r = JumpRopeSized::<16>::from(s.as_str());
+    // A range which spans many nodes.
+    let mut start = 10;
+    while !s.is_char_boundary(start) { start += 1; }
+    let mut end = s.len() - 10;
+    while !s.is_char_boundary(end) { end -= 1; }
+    let expected = &s.as_bytes()[start..end];
+
+    let mut exact = vec![0; end - start];
+    assert_eq!(r.copy_bytes(start..end, &mut exact), Ok(end - start));
+    assert_eq!(exact, expected);
+
+    let mut oversized = vec![0xff; s.len() + 10];
+    assert_eq!(r.copy_bytes(start..end, &mut oversized), Ok(end - start));
+    assert_eq!(&oversized[..end - start], expected);
+    // The rest of the buffer is untouched.
+    assert!(oversized[end - start..].iter().all(|b| *b == 0xff));
+
+    let mut small = vec![0; end - start - 1];
+    assert_eq!(r.copy_bytes(start..end, &mut small),
+        Err(RopeError::BufferTooSmall { needed: end - start, len: end - start - 1 }));
+    assert!(small.iter().all(|b| *b == 0));
+
+    assert_eq!(r.copy_bytes(0..s.len() + 1, &mut oversized),
+        Err(RopeError::PositionOutOfBounds { pos: s.len() + 1, len: s.len() }));
+    assert_eq!(r.copy_bytes(end..start, &mut oversized),
+        Err(RopeError::PositionOutOfBounds { pos: end, len: start }));
+    assert_eq!(r.copy_bytes(start..start, &mut []), Ok(0));
+
+    let r = JumpRope::from("a↯b");
+    assert_eq!(r.copy_bytes(2..5, &mut oversized), Err(RopeError::InvalidCodepoint { pos: 2 }));
+}
+
+#[cfg(feature = "fuzzing")]
+#[test]
+fn apply_random_ops() {
+    use jumprope::FuzzOp;
+
+    let mut rng = SmallRng::seed_from_u64(14);
+    let mut r = JumpRopeSized::<16>::new();
+    let mut expected = String::new();
+    for _ in 0..5000 {
+        // Positions are (mostly) out of range, to exercise normalization.
+        let pos = if rng.gen_bool(0.1) { usize::MAX - rng.gen_range(0..3usize) } else { rng.gen_range(0..1000) };
+        let len = expected.chars().count();
+        if rng.gen_bool(0.6) {
+            let content = random_unicode_string(rng.gen_range(0..20), &mut rng);
+            string_insert_at(&mut expected, pos % (len + 1), &content);
+            r.apply_fuzz_op(FuzzOp::Insert(pos, content));
+        } else {
+            let del_len = if rng.gen_bool(0.05) { usize::MAX } else { rng.gen_range(0..20) };
+            let pos = pos % (len + 1);
+            string_del_at(&mut expected, pos, del_len.min(len - pos));
+            r.apply_fuzz_op(FuzzOp::Del(pos, del_len));
+        }
+        r.check();
+        assert_eq!(r, expected);
+    }
+}
+
+#[test]
+fn clone_copies_structure() {
+    let mut rng = SmallRng::seed_from_u64(7);
+    let mut expected = random_unicode_string(2000, &mut rng);
+    let mut r = JumpRopeSized::<16>::from(expected.as_str());
+    for _ in 0..500 {
+        let pos = rng.gen_range(0..=r.len_chars());
+        let content = random_unicode_string(rng.gen_range(1..10), &mut rng);
+        r.insert(pos, &content);
+        string_insert_at(&mut expected, pos, &content);
+    }
+
+    let mut clone = r.clone();
+    clone.check();
+    assert_eq!(clone, expected);
+    let mut clone_stats = clone.stats();
+    clone_stats.num_free_nodes = r.stats().num_free_nodes;
+    assert_eq!(clone_stats, r.stats());
+
+    // Editing either rope leaves the other alone.
+    let original = expected.clone();
+    clone.remove(100..1500);
+    clone.insert(0, "hi");
+    r.insert(200, "yo");
+    string_insert_at(&mut expected, 200, "yo");
+    check(&r, &expected);
+    let mut expected_clone = original;
+    string_del_at(&mut expected_clone, 100, 1400);
+    string_insert_at(&mut expected_clone, 0, "hi");
+    check(&clone, &expected_clone);
+}
+
+#[test]
+fn height_histogram() {
+    let r = JumpRopeSized::<16>::from("x".repeat(400_000));
+    let histogram = r.height_histogram();
+    assert_eq!(histogram.iter().sum::<usize>(), r.node_count() - 1);
+    assert_eq!(r.node_count(), 25_000);
+    assert_eq!(r.stats().height_histogram, histogram);
+
+    // Each height should have about BIAS / 256 (~1/4) as many nodes as the height below it.
+    assert!(histogram[0] > 18_000 && histogram[0] < 19_500, "{:?}", histogram);
+    for h in 0..histogram.len() - 1 {
+        if histogram[h] >= 1000 {
+            let ratio = histogram[h + 1] as f64 / histogram[h] as f64;
+            assert!(ratio > 0.18 && ratio < 0.33, "{:?}", histogram);
+        }
+    }
+    // The tallest node is nowhere near the maximum height.
+    assert_eq!(histogram[15..], [0; 5]);
+}
+
+#[test]
+fn stats_track_structure() {
+    let mut rng = SmallRng::seed_from_u64(42);
+    let mut expected = random_unicode_string(20000, &mut rng);
+    let mut r = JumpRope::from(expected.as_str());
+
+    let check_stats = |r: &JumpRope| {
+        let stats = r.stats();
+        assert_eq!(stats.used_bytes, r.len_bytes());
+        assert_eq!(stats.capacity_bytes, stats.num_nodes * JumpRope::max_bytes_per_node());
+        // Every node except the head is in the histogram.
+        assert_eq!(stats.height_histogram.iter().sum::<usize>(), stats.num_nodes - 1);
+        // All nodes take up the same amount of memory.
+        let allocated_nodes = stats.num_nodes - 1 + stats.num_free_nodes;
+        if allocated_nodes > 0 {
+            assert_eq!(r.mem_usage() % allocated_nodes, 0);
+        }
+        stats
+    };
+
+    // Each full node has less than a character (4 bytes) of unused space.
+    let n = JumpRope::max_bytes_per_node();
+    let min_fill = 0.95 * (n - 3) as f64 / n as f64;
+
+    // Bulk loading fills the nodes.
+    let loaded = check_stats(&r);
+    assert!(loaded.fill_factor > min_fill);
+    assert_eq!(loaded.num_free_nodes, 0);
+    // Most nodes have a height of 1.
+    assert!(loaded.height_histogram[0] > loaded.num_nodes / 2);
+
+    // Lots of small deletes leave them partially empty.
+    for _ in 0..5000 {
+        let pos = rng.gen_range(0..r.len_chars());
+        let len = rng.gen_range(1..=3usize).min(r.len_chars() - pos);
+        r.remove(pos..pos + len);
+        string_del_at(&mut expected, pos, len);
+    }
+    check(&r, expected.as_str());
+    let deleted = check_stats(&r);
+    assert!(deleted.fill_factor < loaded.fill_factor);
+    assert!(deleted.used_bytes < loaded.used_bytes);
+
+    // And compacting fills them back up.
+    r.compact();
+    check(&r, expected.as_str());
+    let compacted = check_stats(&r);
+    assert!(compacted.fill_factor > min_fill);
+    assert!(compacted.num_nodes < deleted.num_nodes);
+    assert_eq!(compacted.used_bytes, deleted.used_bytes);
+
+    // Clearing the rope keeps (some) nodes around for reuse.
+    r.clear();
+    let cleared = check_stats(&r);
+    assert_eq!(cleared.num_nodes, 1);
+    assert!(cleared.num_free_nodes > 0);
+    assert!(r.mem_usage() > 0);
+}
+
+#[test]
+fn many_nodes() {
+    // With 4 byte nodes, this rope has about 150k nodes.
+    let s: String = (0..600_000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    let mut r = JumpRopeSized::<4>::from(s.as_str());
+    assert!(r.stats().num_nodes > 100_000);
+
+    // The rope should be about log_4(num nodes) tall.
+    let height = r.current_height();
+    assert!((6..=16).contains(&height), "Unexpected height {}", height);
+
+    let mut rng = SmallRng::seed_from_u64(3);
+    let mut expected = s.clone();
+    for _ in 0..1000 {
+        let pos = rng.gen_range(0..r.len_chars());
+        assert_eq!(r.char_at(pos), Some(expected.as_bytes()[pos] as char));
+
+        if rng.gen_bool(0.5) {
+            r.insert(pos, "xyz");
+            expected.insert_str(pos, "xyz");
+        } else {
+            let end = (pos + 10).min(r.len_chars());
+            r.remove(pos..end);
+            expected.replace_range(pos..end, "");
+        }
+    }
+    check(&r, expected.as_str());
+}
+
 fn string_insert_at(s: &mut String, char_pos: usize, contents: &str) {
     // If you try to write past the end of the string for now I'll just write at the end.
     // Panicing might be a better policy.
@@ -206,14 +2355,29 @@ fn string_del_at(s: &mut String, pos: usize, length: usize) {
     s.drain(byte_range);
 }
 
+/// The number of random edits made in each run of random_edits. This defaults to 400, and can be
+/// changed with the JUMPROPE_FUZZ_OPS environment variable. Eg:
+///
+/// JUMPROPE_FUZZ_OPS=100000 cargo test --release fuzz_once
+fn num_fuzz_ops() -> usize {
+    match std::env::var("JUMPROPE_FUZZ_OPS") {
+        Ok(ops) => ops.parse().expect("JUMPROPE_FUZZ_OPS must be a number"),
+        Err(_) => 400,
+    }
+}
+
 fn random_edits(seed: u64, verbose: bool) {
-    let mut r = JumpRope::new();
+    random_edits_sized::<{ JumpRope::max_bytes_per_node() }>(seed, verbose);
+}
+
+fn random_edits_sized<const N: usize>(seed: u64, verbose: bool) {
+    let mut r = JumpRopeSized::<N>::new();
     let mut s = String::new();
 
     // let mut rng = rand::thread_rng();
     let mut rng = SmallRng::seed_from_u64(seed);
 
-    for _i in 0..400 {
+    for _i in 0..num_fuzz_ops() {
         if verbose { println!("{_i} s: '{s}'"); }
         // r.print();
 
@@ -267,6 +2431,79 @@ fn fuzz_once() {
     random_edits(10, false);
 }
 
+// Most edits happen close to the previous edit. This exercises the cached cursor in the rope.
+#[test]
+fn localized_edits() {
+    let mut rng = SmallRng::seed_from_u64(33);
+    let mut r = JumpRope::new();
+    let mut s = String::new();
+    let mut pos: usize = 0;
+
+    for _i in 0..2000 {
+        let len = s.chars().count();
+        match rng.gen_range(0..10) {
+            0 => {
+                // Jump somewhere nearby.
+                let dist = rng.gen_range(0..30);
+                pos = if rng.gen_bool(0.5) { pos.saturating_sub(dist) } else { min(pos + dist, len) };
+            }
+            1..=2 if pos > 0 => {
+                // Backspace.
+                r.remove(pos - 1..pos);
+                string_del_at(&mut s, pos - 1, 1);
+                pos -= 1;
+            }
+            3 if pos < len => {
+                // Delete forwards.
+                let dlen = min(rng.gen_range(1..5), len - pos);
+                r.remove(pos..pos + dlen);
+                string_del_at(&mut s, pos, dlen);
+            }
+            4 => {
+                let text = random_unicode_string(rng.gen_range(1..20), &mut rng);
+                let dlen = min(rng.gen_range(0..5), len - pos);
+                r.replace(pos..pos + dlen, &text);
+                string_del_at(&mut s, pos, dlen);
+                string_insert_at(&mut s, pos, &text);
+                pos += text.chars().count();
+            }
+            _ => {
+                // Type a character.
+                let text = random_unicode_string(1, &mut rng);
+                r.insert(pos, &text);
+                string_insert_at(&mut s, pos, &text);
+                pos += 1;
+            }
+        }
+
+        if !cfg!(miri) {
+            check(&r, s.as_str());
+        }
+    }
+}
+
+#[test]
+fn edit_after_move() {
+    // Moving the rope invalidates any pointers to its inline head node.
+    let mut r = JumpRope::from("hi");
+    r.insert(2, " there");
+    let mut boxed = Box::new(r);
+    boxed.insert(8, "!");
+    boxed.remove(0..1);
+    check(&boxed, "i there!");
+
+    let mut v = vec![*boxed];
+    v[0].insert(0, "H");
+    check(&v[0], "Hi there!");
+}
+
+#[test]
+fn fuzz_node_sizes() {
+    random_edits_sized::<4>(11, false);
+    random_edits_sized::<64>(12, false);
+    random_edits_sized::<1024>(13, false);
+}
+
 // Run with:
 // cargo test --release fuzz_forever -- --ignored --nocapture
 #[test]
@@ -447,6 +2684,22 @@ fn eq_variants() {
     assert_eq!(&rope, &String::from("Hi there"));
 }
 
+#[test]
+fn eq_across_nodes() {
+    let s = "a".repeat(100);
+    let rope = JumpRopeSized::<16>::from(s.as_str());
+    assert_eq!(rope, s);
+
+    // Comparing against a string with a character across the end of a node must not panic.
+    let mut end = 0;
+    for chunk in rope.substrings() {
+        end += chunk.len();
+        if end == 0 || end == s.len() { continue; }
+        let other = format!("{}é{}", &s[..end - 1], &s[end + 1..]);
+        assert_ne!(rope, other);
+    }
+}
+
 #[test]
 fn buffered_eq_variants() {
     let rope = JumpRopeBuf::from("Hi there");
@@ -458,4 +2711,66 @@ fn buffered_eq_variants() {
     assert_eq!(&rope, "Hi there");
     assert_eq!(&rope, String::from("Hi there"));
     assert_eq!(&rope, &String::from("Hi there"));
-}
\ No newline at end of file
+}
+
+#[test]
+fn small_inserts_fill_nodes_in_place() {
+    // Inserts which fit in the node they land in are copied straight into it, so a stream of
+    // small appends fills each node before the next one is allocated.
+    let mut r = JumpRopeSized::<64>::new();
+    let mut expected = String::new();
+    for i in 0..200 {
+        let s = if i % 3 == 0 { "κό" } else { "ab" };
+        r.insert(r.len_chars(), s);
+        expected.push_str(s);
+    }
+    r.check();
+    assert_eq!(r, expected.as_str());
+    assert_eq!(r.node_count(), expected.len().div_ceil(64));
+
+    // The same goes for inserts into the middle of a node with room to spare.
+    let mut r = JumpRopeSized::<64>::from("0123456789");
+    for _ in 0..27 {
+        r.insert(5, "xy");
+    }
+    r.check();
+    assert_eq!(r.len_bytes(), 64);
+    assert_eq!(r.node_count(), 1);
+}
+
+#[test]
+fn print_and_check() {
+    // print is only for debugging, so this just makes sure it handles a rope with a few nodes.
+    let r = JumpRopeSized::<8>::from("κόσμε and 𐆚 some more text");
+    r.check();
+    r.print();
+    JumpRope::new().print();
+}
+
+#[test]
+fn rebalance_after_mass_delete() {
+    let mut r = JumpRopeSized::<16>::new();
+    let line = "κόσμε and some text\n";
+    for _ in 0..5000 {
+        r.push_str(line);
+    }
+    let height = r.current_height();
+    assert!(height >= 5);
+
+    r.remove(40..r.len_chars() - 20);
+    r.rebalance();
+    check(&r, &line.repeat(3));
+    assert!(r.current_height() < height);
+    assert!(r.current_height() <= 2);
+
+    // It can still grow as usual.
+    for _ in 0..5000 {
+        r.insert(30, line);
+    }
+    r.check();
+    assert_eq!(r.len_bytes(), line.len() * 5003);
+
+    let mut empty = JumpRope::new();
+    empty.rebalance();
+    check(&empty, "");
+}